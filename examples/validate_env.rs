@@ -33,7 +33,7 @@ async fn main() {
                 Ok(tracks) => {
                     println!("\nFetched {} recent tracks:", tracks.len());
                     for (i, track) in tracks.iter().enumerate() {
-                        println!("  {}. {} - {}", i + 1, track.name, track.artist.text);
+                        println!("  {}. {track}", i + 1);
                     }
                 }
                 Err(e) => {
@@ -1,10 +1,25 @@
 use std::fs::File;
-use std::io::BufReader;
-use std::{collections::HashMap, path::Path};
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
 
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Weekday};
+use chrono_tz::Tz;
+use futures::{Stream, StreamExt};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 
-use crate::types::{LovedTrack, RecentTrack, Timestamped};
+use crate::error::Result as LastFmResult;
+use crate::file_handler::{FileFormat, FileHandler};
+use crate::types::{
+    best_image, sort_by_timestamp, ImageSize, LovedTrack, RecentTrack, Timestamped, TopTrack,
+    TrackInfo,
+};
 
 /// Trait for types that can be analyzed as tracks
 #[allow(dead_code)]
@@ -41,8 +56,85 @@ impl TrackAnalyzable for LovedTrack {
     }
 }
 
+impl TrackAnalyzable for TopTrack {
+    fn get_artist_name(&self) -> String {
+        self.artist.name.clone()
+    }
+
+    fn get_track_name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/// A track type-agnostic view, to combine different track kinds in one analysis
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizedTrack {
+    /// Artist name
+    pub artist: String,
+    /// Track name
+    pub track: String,
+    /// Album name, when known
+    pub album: Option<String>,
+    /// When the track was scrobbled or loved
+    pub timestamp: Option<u32>,
+    /// Number of plays this entry stands for (0 for loved tracks)
+    pub playcount: u32,
+}
+
+impl TrackAnalyzable for NormalizedTrack {
+    fn get_artist_name(&self) -> String {
+        self.artist.clone()
+    }
+
+    fn get_track_name(&self) -> String {
+        self.track.clone()
+    }
+}
+
+impl Timestamped for NormalizedTrack {
+    fn get_timestamp(&self) -> Option<u32> {
+        self.timestamp
+    }
+}
+
+impl From<RecentTrack> for NormalizedTrack {
+    fn from(track: RecentTrack) -> Self {
+        NormalizedTrack {
+            timestamp: track.get_timestamp(),
+            artist: track.artist.text,
+            track: track.name,
+            album: Some(track.album.text).filter(|album| !album.is_empty()),
+            playcount: 1,
+        }
+    }
+}
+
+impl From<LovedTrack> for NormalizedTrack {
+    fn from(track: LovedTrack) -> Self {
+        NormalizedTrack {
+            timestamp: track.get_timestamp(),
+            artist: track.artist.name,
+            track: track.name,
+            album: None,
+            playcount: 0,
+        }
+    }
+}
+
+impl From<TopTrack> for NormalizedTrack {
+    fn from(track: TopTrack) -> Self {
+        NormalizedTrack {
+            artist: track.artist.name,
+            track: track.name,
+            album: None,
+            timestamp: None,
+            playcount: track.playcount,
+        }
+    }
+}
+
 /// Represents statistics about tracks
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TrackStats {
     /// Total number of tracks
     pub total_tracks: usize,
@@ -55,11 +147,183 @@ pub struct TrackStats {
     /// Map of tracks played more than threshold
     pub tracks_above_threshold: HashMap<String, usize>,
     /// Most played artist
+    #[serde(with = "name_count")]
     pub most_played_artist: Option<(String, usize)>,
     /// Most played track
+    #[serde(with = "name_count")]
     pub most_played_track: Option<(String, usize)>,
 }
 
+/// (De)serialize a `(name, count)` pair as `{ "name": ..., "count": ... }`.
+///
+/// Stats saved before the pair had named fields are still read, from
+/// their `[name, count]` array form.
+mod name_count {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct NameCount {
+        name: String,
+        count: usize,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StoredNameCount {
+        Named(NameCount),
+        Pair(String, usize),
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<(String, usize)>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value
+            .as_ref()
+            .map(|(name, count)| NameCount {
+                name: name.clone(),
+                count: *count,
+            })
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<(String, usize)>, D::Error> {
+        Ok(
+            Option::<StoredNameCount>::deserialize(deserializer)?.map(|stored| match stored {
+                StoredNameCount::Named(NameCount { name, count })
+                | StoredNameCount::Pair(name, count) => (name, count),
+            }),
+        )
+    }
+}
+
+/// Represents a per-day summary of scrobbles
+#[derive(Debug)]
+pub struct DailySummary {
+    /// Number of days with at least one scrobble
+    pub total_days_active: usize,
+    /// Average number of scrobbles per active day
+    pub average_scrobbles_per_day: f64,
+    /// Day with the most scrobbles
+    pub busiest_day: Option<(NaiveDate, usize)>,
+    /// Active day with the fewest scrobbles
+    pub quietest_day: Option<(NaiveDate, usize)>,
+}
+
+/// Labelled values, in the shape charting libraries expect
+#[derive(Debug, Serialize)]
+pub struct ChartData {
+    /// Label of each value
+    pub labels: Vec<String>,
+    /// Values, in the same order as `labels`
+    pub values: Vec<usize>,
+}
+
+impl From<&[(u8, usize); 24]> for ChartData {
+    fn from(clock: &[(u8, usize); 24]) -> Self {
+        ChartData {
+            labels: clock
+                .iter()
+                .map(|(hour, _)| format!("{hour:02}:00"))
+                .collect(),
+            values: clock.iter().map(|(_, count)| *count).collect(),
+        }
+    }
+}
+
+/// Represents when scrobbles happen, by hour of the day and day of the week
+#[derive(Debug)]
+pub struct ListeningPatterns {
+    /// Number of scrobbles for every hour from 0 to 23
+    pub by_hour: [usize; 24],
+    /// Number of scrobbles for every day of the week, Monday first
+    pub by_weekday: [usize; 7],
+    /// Hour with the most scrobbles, the earliest on ties
+    pub peak_hour: Option<u8>,
+    /// Day of the week with the most scrobbles, the earliest on ties
+    pub peak_weekday: Option<Weekday>,
+}
+
+/// Represents how plays are spread across tags
+#[derive(Debug)]
+pub struct GenreStats {
+    /// Map of tags to the number of plays of tracks with that tag
+    pub tag_play_counts: HashMap<String, usize>,
+    /// Most played tags, most played first
+    pub top_genres: Vec<(String, usize)>,
+    /// Number of plays whose track resolved to no tag
+    pub untagged_plays: usize,
+}
+
+/// How `analyze_genres` cleans up tags
+#[derive(Debug, Clone)]
+pub struct GenreOptions {
+    /// Count tags in lowercase, merging e.g. `"Rock"` and `"rock"`
+    pub lowercase: bool,
+    /// Tags to ignore, such as `"seen live"`, compared case-insensitively
+    pub blocklist: Vec<String>,
+    /// Number of tags kept in `top_genres`
+    pub top_n: usize,
+}
+
+impl Default for GenreOptions {
+    fn default() -> Self {
+        GenreOptions {
+            lowercase: true,
+            blocklist: Vec::new(),
+            top_n: 10,
+        }
+    }
+}
+
+/// Represents the difference between two loved tracks snapshots
+#[derive(Debug)]
+pub struct LovedDiff {
+    /// Tracks loved since the old snapshot
+    pub added: Vec<LovedTrack>,
+    /// Tracks unloved since the old snapshot
+    pub removed: Vec<LovedTrack>,
+}
+
+/// Represents the earliest scrobbles in a listening history
+#[derive(Debug)]
+pub struct Firsts {
+    /// The earliest scrobble overall
+    pub first_ever: Option<RecentTrack>,
+    /// Map of artist names to the timestamp of their first scrobble
+    pub first_per_artist: HashMap<String, u32>,
+}
+
+/// Represents how loved tracks compare to the user's top tracks
+#[derive(Debug)]
+pub struct LovedTopReport {
+    /// Loved tracks that are also top tracks, with their play count
+    pub in_top: Vec<(LovedTrack, u32)>,
+    /// Loved tracks missing from the top tracks, i.e. loved but rarely played
+    pub underplayed: Vec<LovedTrack>,
+}
+
+/// Number of tracks each parallel worker counts before its counts are merged
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_SIZE: usize = 16_384;
+
+/// Label counted in place of an empty artist or track name
+pub const UNKNOWN_NAME: &str = "(unknown)";
+
+/// How analyses treat tracks with an empty artist or track name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyNames {
+    /// Count them under their empty name, e.g. `"Artist - "`
+    #[default]
+    Keep,
+    /// Leave them out of the analysis entirely
+    Skip,
+    /// Count empty names as `UNKNOWN_NAME`
+    Unknown,
+}
+
 pub struct AnalysisHandler;
 
 impl AnalysisHandler {
@@ -96,18 +360,302 @@ impl AnalysisHandler {
     /// # Returns
     /// * `TrackStats` - Analysis results
     pub fn analyze_tracks<T: TrackAnalyzable>(tracks: &[T], threshold: usize) -> TrackStats {
+        Self::analyze_tracks_with(tracks, threshold, EmptyNames::Keep)
+    }
+
+    /// Analyze tracks from a JSON file, counting them on all cores
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the JSON file
+    /// * `threshold` - Threshold for counting tracks with plays below this number
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened
+    /// * `serde_json::Error` - If the file cannot be deserialized
+    ///
+    /// # Returns
+    /// * `Result<TrackStats, Box<dyn std::error::Error>>` - Analysis results
+    #[cfg(feature = "parallel")]
+    pub fn analyze_file_parallel<T: DeserializeOwned + TrackAnalyzable + Sync>(
+        file_path: &Path,
+        threshold: usize,
+    ) -> Result<TrackStats, Box<dyn std::error::Error>> {
+        let file = File::open(file_path)?;
+        let reader = BufReader::new(file);
+
+        let tracks: Vec<T> = serde_json::from_reader(reader)?;
+
+        Ok(Self::analyze_tracks_parallel(&tracks, threshold))
+    }
+
+    /// Analyze a vector of tracks on all cores
+    ///
+    /// The slice is split into chunks counted in parallel, and the partial
+    /// counts are merged. Results are identical to `analyze_tracks`, which
+    /// remains the better choice for small histories.
+    ///
+    /// # Arguments
+    /// * `tracks` - Vector of tracks to analyze
+    /// * `threshold` - Threshold for counting tracks with plays below this number
+    ///
+    /// # Returns
+    /// * `TrackStats` - Analysis results
+    #[cfg(feature = "parallel")]
+    pub fn analyze_tracks_parallel<T: TrackAnalyzable + Sync>(
+        tracks: &[T],
+        threshold: usize,
+    ) -> TrackStats {
+        type Counts = (HashMap<String, usize>, HashMap<String, usize>);
+
+        fn merge(
+            mut into: HashMap<String, usize>,
+            from: HashMap<String, usize>,
+        ) -> HashMap<String, usize> {
+            for (name, count) in from {
+                *into.entry(name).or_insert(0) += count;
+            }
+            into
+        }
+
+        let (artist_play_counts, track_play_counts) = tracks
+            .par_chunks(PARALLEL_CHUNK_SIZE)
+            .map(|chunk| {
+                let mut counts: Counts = (HashMap::new(), HashMap::new());
+                for track in chunk {
+                    Self::count_track(track, &mut counts.0, &mut counts.1);
+                }
+                counts
+            })
+            .reduce(
+                || (HashMap::new(), HashMap::new()),
+                |(artists_a, tracks_a), (artists_b, tracks_b)| {
+                    (merge(artists_a, artists_b), merge(tracks_a, tracks_b))
+                },
+            );
+
+        Self::build_stats(
+            tracks.len(),
+            artist_play_counts,
+            track_play_counts,
+            threshold,
+        )
+    }
+
+    /// Analyze a vector of tracks, choosing how empty names are counted
+    ///
+    /// Scrobbles with bad metadata can have an empty artist or track name,
+    /// which otherwise shows up as identifiers like `"Artist - "`.
+    ///
+    /// # Arguments
+    /// * `tracks` - Vector of tracks to analyze
+    /// * `threshold` - Threshold for counting tracks with plays below this number
+    /// * `empty_names` - Whether to keep, skip or relabel tracks with empty names
+    ///
+    /// # Returns
+    /// * `TrackStats` - Analysis results, where `total_tracks` excludes skipped tracks
+    pub fn analyze_tracks_with<T: TrackAnalyzable>(
+        tracks: &[T],
+        threshold: usize,
+        empty_names: EmptyNames,
+    ) -> TrackStats {
         let mut artist_play_counts: HashMap<String, usize> = HashMap::new();
         let mut track_play_counts: HashMap<String, usize> = HashMap::new();
+        let mut total_tracks = 0;
 
         // Count plays for each artist and track
         for track in tracks {
             let artist_name = track.get_artist_name();
-            let track_identifier = track.get_track_identifier();
+            let track_name = track.get_track_name();
+            let has_empty_name = artist_name.is_empty() || track_name.is_empty();
+
+            match empty_names {
+                EmptyNames::Skip if has_empty_name => continue,
+                EmptyNames::Unknown if has_empty_name => {
+                    let label = |name: String| {
+                        if name.is_empty() {
+                            UNKNOWN_NAME.to_string()
+                        } else {
+                            name
+                        }
+                    };
+                    let artist_name = label(artist_name);
+                    let track_identifier = format!("{artist_name} - {}", label(track_name));
+
+                    *artist_play_counts.entry(artist_name).or_insert(0) += 1;
+                    *track_play_counts.entry(track_identifier).or_insert(0) += 1;
+                }
+                _ => Self::count_track(track, &mut artist_play_counts, &mut track_play_counts),
+            }
+            total_tracks += 1;
+        }
+
+        Self::build_stats(
+            total_tracks,
+            artist_play_counts,
+            track_play_counts,
+            threshold,
+        )
+    }
+
+    /// Merge new tracks into previously computed statistics.
+    ///
+    /// Only `tracks` are counted; the prior counts are reused as-is, so
+    /// `tracks` must not overlap with the plays already in `prior`.
+    ///
+    /// # Arguments
+    /// * `prior` - Statistics from an earlier analysis
+    /// * `tracks` - Tracks played since `prior` was computed
+    /// * `threshold` - Threshold for counting tracks with plays below this number
+    ///
+    /// # Returns
+    /// * `TrackStats` - Statistics covering both `prior` and `tracks`
+    pub fn merge_tracks<T: TrackAnalyzable>(
+        prior: TrackStats,
+        tracks: &[T],
+        threshold: usize,
+    ) -> TrackStats {
+        let mut artist_play_counts = prior.artist_play_counts;
+        let mut track_play_counts = prior.track_play_counts;
+
+        for track in tracks {
+            Self::count_track(track, &mut artist_play_counts, &mut track_play_counts);
+        }
+
+        Self::build_stats(
+            prior.total_tracks + tracks.len(),
+            artist_play_counts,
+            track_play_counts,
+            threshold,
+        )
+    }
+
+    /// Incrementally analyze a JSON tracks file on top of saved statistics.
+    ///
+    /// Meant for periodic jobs: save the `TrackStats` as JSON after each run,
+    /// then feed only the tracks fetched since (e.g. with
+    /// `get_user_recent_tracks_since`) instead of re-scanning the full history.
+    ///
+    /// # Arguments
+    /// * `stats_path` - Path to the previously saved `TrackStats` JSON
+    /// * `tracks_path` - Path to a JSON file with the new tracks only
+    /// * `threshold` - Threshold for counting tracks with plays below this number
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If either file cannot be opened
+    /// * `serde_json::Error` - If either file cannot be deserialized
+    ///
+    /// # Returns
+    /// * `Result<TrackStats, Box<dyn std::error::Error>>` - Merged statistics
+    pub fn analyze_file_since<T: DeserializeOwned + TrackAnalyzable>(
+        stats_path: &Path,
+        tracks_path: &Path,
+        threshold: usize,
+    ) -> Result<TrackStats, Box<dyn std::error::Error>> {
+        let prior: TrackStats = serde_json::from_reader(BufReader::new(File::open(stats_path)?))?;
+        let tracks: Vec<T> = serde_json::from_reader(BufReader::new(File::open(tracks_path)?))?;
+
+        Ok(Self::merge_tracks(prior, &tracks, threshold))
+    }
+
+    /// Analyze tracks from a newline-delimited JSON (NDJSON) file.
+    ///
+    /// Tracks are read and counted one line at a time, so the whole history
+    /// never has to be held in memory. Blank lines are ignored.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the NDJSON file
+    /// * `threshold` - Threshold for counting tracks with plays below this number
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or read
+    /// * `serde_json::Error` - If a line cannot be deserialized
+    ///
+    /// # Returns
+    /// * `Result<TrackStats, Box<dyn std::error::Error>>` - Analysis results
+    pub fn analyze_ndjson<T: DeserializeOwned + TrackAnalyzable>(
+        file_path: &Path,
+        threshold: usize,
+    ) -> Result<TrackStats, Box<dyn std::error::Error>> {
+        let file = File::open(file_path)?;
+        let reader = BufReader::new(file);
+
+        let mut artist_play_counts: HashMap<String, usize> = HashMap::new();
+        let mut track_play_counts: HashMap<String, usize> = HashMap::new();
+        let mut total_tracks = 0;
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let track: T = serde_json::from_str(&line)?;
+            Self::count_track(&track, &mut artist_play_counts, &mut track_play_counts);
+            total_tracks += 1;
+        }
+
+        Ok(Self::build_stats(
+            total_tracks,
+            artist_play_counts,
+            track_play_counts,
+            threshold,
+        ))
+    }
+
+    /// Analyze tracks as they arrive from an async stream.
+    ///
+    /// Counts are folded one track at a time, so a full history can be fetched
+    /// and analyzed without collecting it into a `Vec` or writing it to a file.
+    ///
+    /// # Arguments
+    /// * `stream` - Stream of tracks to analyze
+    /// * `threshold` - Threshold for counting tracks with plays below this number
+    ///
+    /// # Returns
+    /// * `TrackStats` - Analysis results
+    pub async fn analyze_stream<T, S>(stream: S, threshold: usize) -> TrackStats
+    where
+        T: TrackAnalyzable,
+        S: Stream<Item = T>,
+    {
+        let mut artist_play_counts: HashMap<String, usize> = HashMap::new();
+        let mut track_play_counts: HashMap<String, usize> = HashMap::new();
+        let mut total_tracks = 0;
 
-            *artist_play_counts.entry(artist_name).or_insert(0) += 1;
-            *track_play_counts.entry(track_identifier).or_insert(0) += 1;
+        let mut stream = std::pin::pin!(stream);
+        while let Some(track) = stream.next().await {
+            Self::count_track(&track, &mut artist_play_counts, &mut track_play_counts);
+            total_tracks += 1;
         }
 
+        Self::build_stats(
+            total_tracks,
+            artist_play_counts,
+            track_play_counts,
+            threshold,
+        )
+    }
+
+    /// Count a single play of a track towards the artist and track counters.
+    fn count_track<T: TrackAnalyzable>(
+        track: &T,
+        artist_play_counts: &mut HashMap<String, usize>,
+        track_play_counts: &mut HashMap<String, usize>,
+    ) {
+        let artist_name = track.get_artist_name();
+        let track_identifier = track.get_track_identifier();
+
+        *artist_play_counts.entry(artist_name).or_insert(0) += 1;
+        *track_play_counts.entry(track_identifier).or_insert(0) += 1;
+    }
+
+    /// Build the final statistics from the collected play counts.
+    fn build_stats(
+        total_tracks: usize,
+        artist_play_counts: HashMap<String, usize>,
+        track_play_counts: HashMap<String, usize>,
+        threshold: usize,
+    ) -> TrackStats {
         // Find most played artist and track
         let most_played_artist = artist_play_counts
             .iter()
@@ -134,7 +682,7 @@ impl AnalysisHandler {
             .collect();
 
         TrackStats {
-            total_tracks: tracks.len(),
+            total_tracks,
             artist_play_counts,
             track_play_counts,
             tracks_below_threshold,
@@ -144,119 +692,1075 @@ impl AnalysisHandler {
         }
     }
 
-    /// Print analysis results in a formatted way
+    /// Summarize scrobbles per day (UTC).
+    ///
+    /// Tracks without a timestamp (currently playing) are ignored.
+    /// Ties for the busiest and quietest day are resolved in favour of the earliest day.
     ///
     /// # Arguments
-    /// * `stats` - `TrackStats` to print
-    pub fn print_analysis(stats: &TrackStats) {
-        println!("=== Track Analysis ===");
-        println!("Total tracks: {}", stats.total_tracks);
+    /// * `tracks` - Tracks to summarize
+    ///
+    /// # Returns
+    /// * `DailySummary` - Per-day summary
+    #[must_use]
+    pub fn daily_summary(tracks: &[RecentTrack]) -> DailySummary {
+        Self::daily_summary_in(tracks, Tz::UTC)
+    }
 
-        if let Some((artist, count)) = &stats.most_played_artist {
-            println!("\nMost played artist: {artist} ({count} plays)");
-        }
+    /// Summarize scrobbles per day, with days bucketed in the given timezone.
+    ///
+    /// Tracks without a timestamp (currently playing) are ignored.
+    /// Ties for the busiest and quietest day are resolved in favour of the earliest day.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to summarize
+    /// * `tz` - Timezone defining day boundaries
+    ///
+    /// # Returns
+    /// * `DailySummary` - Per-day summary
+    #[allow(clippy::cast_precision_loss)]
+    pub fn daily_summary_in(tracks: &[RecentTrack], tz: Tz) -> DailySummary {
+        let mut day_counts: HashMap<NaiveDate, usize> = HashMap::new();
 
-        if let Some((track, count)) = &stats.most_played_track {
-            println!("Most played track: {track} ({count} plays)");
+        for played_at in tracks.iter().filter_map(RecentTrack::played_at) {
+            let day = played_at.with_timezone(&tz).date_naive();
+            *day_counts.entry(day).or_insert(0) += 1;
         }
 
-        println!("\nTop 10 Artists:");
-        let mut artists: Vec<_> = stats.artist_play_counts.iter().collect();
-        artists.sort_by(|a, b| b.1.cmp(a.1));
-        for (artist, count) in artists.iter().take(10) {
-            println!("  {artist} - {count} plays");
-        }
+        let total_scrobbles: usize = day_counts.values().sum();
+        let total_days_active = day_counts.len();
 
-        println!("\nTop 10 Tracks:");
-        let mut tracks: Vec<_> = stats.track_play_counts.iter().collect();
-        tracks.sort_by(|a, b| b.1.cmp(a.1));
-        for (track, count) in tracks.iter().take(10) {
-            println!("  {track} - {count} plays");
-        }
+        let average_scrobbles_per_day = if total_days_active == 0 {
+            0.0
+        } else {
+            total_scrobbles as f64 / total_days_active as f64
+        };
 
-        println!(
-            "\nTracks below threshold: {}",
-            stats.tracks_below_threshold.len()
-        );
+        let mut days: Vec<(NaiveDate, usize)> = day_counts.into_iter().collect();
+        days.sort_by_key(|(day, _)| *day);
 
-        println!(
-            "\nTracks above threshold: {}",
-            stats.tracks_above_threshold.len()
-        );
+        let busiest_day = days
+            .iter()
+            .copied()
+            .reduce(|best, day| if day.1 > best.1 { day } else { best });
+
+        let quietest_day = days
+            .iter()
+            .copied()
+            .reduce(|best, day| if day.1 < best.1 { day } else { best });
+
+        DailySummary {
+            total_days_active,
+            average_scrobbles_per_day,
+            busiest_day,
+            quietest_day,
+        }
     }
 
-    /// Get the most recent timestamp from a JSON file.
+    /// Count scrobbles per hour of the day (UTC).
     ///
-    /// # Arguments
-    /// * `file_path` - Path to the JSON file
+    /// Tracks without a timestamp (currently playing) are ignored.
     ///
-    /// # Errors
-    /// * `std::io::Error` - If the file cannot be opened or read
-    /// * `serde_json::Error` - If the JSON cannot be deserialized
+    /// # Arguments
+    /// * `tracks` - Tracks to count
     ///
     /// # Returns
-    /// * `Option<i64>` - Most recent timestamp
-    #[allow(dead_code)]
-    pub fn get_most_recent_timestamp<T: DeserializeOwned + Timestamped>(
+    /// * `[(u8, usize); 24]` - (hour, scrobbles) for every hour from 0 to 23
+    #[must_use]
+    pub fn listening_clock(tracks: &[RecentTrack]) -> [(u8, usize); 24] {
+        Self::listening_clock_in(tracks, Tz::UTC)
+    }
+
+    /// Count scrobbles per hour of the day in a given timezone.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to count
+    /// * `tz` - Timezone the hours are expressed in
+    ///
+    /// # Returns
+    /// * `[(u8, usize); 24]` - (hour, scrobbles) for every hour from 0 to 23
+    #[must_use]
+    pub fn listening_clock_in(tracks: &[RecentTrack], tz: Tz) -> [(u8, usize); 24] {
+        let by_hour = Self::analyze_listening_patterns(tracks, tz).by_hour;
+
+        std::array::from_fn(|hour| (u8::try_from(hour).unwrap_or(u8::MAX), by_hour[hour]))
+    }
+
+    /// Save a listening clock as JSON shaped for charting libraries.
+    ///
+    /// # Arguments
+    /// * `clock` - Listening clock from `listening_clock`
+    /// * `file_path` - Path to the JSON file to create (overwriting if it exists)
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be created or written to
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    pub fn export_listening_clock(
+        clock: &[(u8, usize); 24],
+        file_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let file = File::create(file_path)?;
+        serde_json::to_writer_pretty(file, &ChartData::from(clock))?;
+
+        Ok(())
+    }
+
+    /// Count scrobbles per hour of the day and per day of the week.
+    ///
+    /// Tracks without a timestamp (currently playing) are ignored.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to count
+    /// * `tz` - Timezone the hours and days are expressed in
+    ///
+    /// # Returns
+    /// * `ListeningPatterns` - Both histograms and their peaks
+    pub fn analyze_listening_patterns<T: Timestamped>(tracks: &[T], tz: Tz) -> ListeningPatterns {
+        let mut by_hour = [0; 24];
+        let mut by_weekday = [0; 7];
+
+        for played_at in tracks
+            .iter()
+            .filter_map(Timestamped::get_timestamp)
+            .filter_map(|uts| DateTime::from_timestamp(i64::from(uts), 0))
+        {
+            let local = played_at.with_timezone(&tz);
+            by_hour[local.hour() as usize] += 1;
+            by_weekday[local.weekday().num_days_from_monday() as usize] += 1;
+        }
+
+        ListeningPatterns {
+            peak_hour: Self::peak(&by_hour).and_then(|hour| u8::try_from(hour).ok()),
+            peak_weekday: Self::peak(&by_weekday)
+                .and_then(|day| u8::try_from(day).ok())
+                .and_then(|day| Weekday::try_from(day).ok()),
+            by_hour,
+            by_weekday,
+        }
+    }
+
+    /// Index of the earliest highest non-zero count.
+    fn peak(counts: &[usize]) -> Option<usize> {
+        // `max_by_key` keeps the last maximum, so the reversed scan yields the earliest
+        counts
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, count)| **count > 0)
+            .max_by_key(|(_, count)| **count)
+            .map(|(index, _)| index)
+    }
+
+    /// Count plays per tag.
+    ///
+    /// Every play counts once for each distinct tag of its track, so a play
+    /// of a track tagged `rock` and `indie` counts for both. Tracks whose
+    /// tags all resolve to nothing, or are blocklisted, are skipped.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to count
+    /// * `tags_for` - Tags of a track, given its artist and track name
+    /// * `options` - How tags are normalized and filtered
+    ///
+    /// # Returns
+    /// * `GenreStats` - Plays per tag and the most played tags
+    pub fn analyze_genres<T, F>(tracks: &[T], tags_for: F, options: &GenreOptions) -> GenreStats
+    where
+        T: TrackAnalyzable,
+        F: Fn(&str, &str) -> Vec<String>,
+    {
+        let mut tag_play_counts: HashMap<String, usize> = HashMap::new();
+        let mut untagged_plays = 0;
+        let blocklist: HashSet<String> = options
+            .blocklist
+            .iter()
+            .map(|blocked| blocked.to_lowercase())
+            .collect();
+
+        for track in tracks {
+            let tags: HashSet<String> = tags_for(&track.get_artist_name(), &track.get_track_name())
+                .into_iter()
+                .map(|tag| {
+                    let tag = tag.trim();
+                    if options.lowercase {
+                        tag.to_lowercase()
+                    } else {
+                        tag.to_string()
+                    }
+                })
+                .filter(|tag| !tag.is_empty() && !blocklist.contains(&tag.to_lowercase()))
+                .collect();
+
+            if tags.is_empty() {
+                untagged_plays += 1;
+            }
+            for tag in tags {
+                *tag_play_counts.entry(tag).or_insert(0) += 1;
+            }
+        }
+
+        let mut top_genres: Vec<(String, usize)> = tag_play_counts
+            .iter()
+            .map(|(tag, count)| (tag.clone(), *count))
+            .collect();
+        top_genres.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top_genres.truncate(options.top_n);
+
+        GenreStats {
+            tag_play_counts,
+            top_genres,
+            untagged_plays,
+        }
+    }
+
+    /// Count plays per tag, using track information fetched beforehand.
+    ///
+    /// Meant for the output of `LastFMHandler::enrich_recent_tracks`; tracks
+    /// missing from `infos`, or whose lookup failed, count as untagged.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to count
+    /// * `infos` - Track information, by (artist, track)
+    /// * `options` - How tags are normalized and filtered
+    ///
+    /// # Returns
+    /// * `GenreStats` - Plays per tag and the most played tags
+    pub fn analyze_genres_with_info<T: TrackAnalyzable>(
+        tracks: &[T],
+        infos: &HashMap<(String, String), LastFmResult<TrackInfo>>,
+        options: &GenreOptions,
+    ) -> GenreStats {
+        Self::analyze_genres(
+            tracks,
+            |artist, track| {
+                infos
+                    .get(&(artist.to_string(), track.to_string()))
+                    .and_then(|info| info.as_ref().ok())
+                    .map_or_else(Vec::new, |info| info.tags.clone())
+            },
+            options,
+        )
+    }
+
+    /// Compare two loved tracks snapshots.
+    ///
+    /// Tracks are matched on their mbid when present, otherwise on artist and track name.
+    ///
+    /// # Arguments
+    /// * `old` - The older snapshot
+    /// * `new` - The newer snapshot
+    ///
+    /// # Returns
+    /// * `LovedDiff` - Tracks added and removed between the snapshots
+    pub fn diff_loved(old: &[LovedTrack], new: &[LovedTrack]) -> LovedDiff {
+        fn key(track: &LovedTrack) -> String {
+            if track.mbid.is_empty() {
+                track.get_track_identifier()
+            } else {
+                track.mbid.clone()
+            }
+        }
+
+        let old_keys: HashSet<String> = old.iter().map(key).collect();
+        let new_keys: HashSet<String> = new.iter().map(key).collect();
+
+        LovedDiff {
+            added: new
+                .iter()
+                .filter(|track| !old_keys.contains(&key(track)))
+                .cloned()
+                .collect(),
+            removed: old
+                .iter()
+                .filter(|track| !new_keys.contains(&key(track)))
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Find the first scrobble ever and the first scrobble of each artist.
+    ///
+    /// Tracks without a timestamp (currently playing) are ignored.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to search, in any order
+    ///
+    /// # Returns
+    /// * `Firsts` - The earliest scrobbles
+    #[must_use]
+    pub fn firsts(tracks: &[RecentTrack]) -> Firsts {
+        let mut timestamped: Vec<(u32, &RecentTrack)> = tracks
+            .iter()
+            .filter_map(|track| track.get_timestamp().map(|ts| (ts, track)))
+            .collect();
+        timestamped.sort_by_key(|(ts, _)| *ts);
+
+        let mut first_per_artist: HashMap<String, u32> = HashMap::new();
+        for (ts, track) in &timestamped {
+            first_per_artist
+                .entry(track.get_artist_name())
+                .or_insert(*ts);
+        }
+
+        Firsts {
+            first_ever: timestamped.first().map(|(_, track)| (*track).clone()),
+            first_per_artist,
+        }
+    }
+
+    /// Compute the average length of listening sessions.
+    ///
+    /// Sessions are runs of scrobbles where consecutive plays are at most
+    /// `gap_secs` apart. A session's length is the time between its first and
+    /// last scrobble, so single-track sessions count as zero-length.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to analyze, in any order
+    /// * `gap_secs` - Maximum gap between two scrobbles of the same session
+    ///
+    /// # Returns
+    /// * `Option<Duration>` - Average session length, `None` if there are no timestamped tracks
+    #[must_use]
+    pub fn average_session_length(tracks: &[RecentTrack], gap_secs: u32) -> Option<Duration> {
+        let sessions = Self::session_bounds(tracks, gap_secs);
+        if sessions.is_empty() {
+            return None;
+        }
+
+        let total: u64 = sessions
+            .iter()
+            .map(|(start, end)| u64::from(end - start))
+            .sum();
+
+        Some(Duration::from_secs(total) / u32::try_from(sessions.len()).ok()?)
+    }
+
+    /// Split timestamped tracks into sessions, returning each session's first and last timestamp.
+    fn session_bounds(tracks: &[RecentTrack], gap_secs: u32) -> Vec<(u32, u32)> {
+        let mut timestamps: Vec<u32> = tracks
+            .iter()
+            .filter_map(Timestamped::get_timestamp)
+            .collect();
+        timestamps.sort_unstable();
+
+        let mut sessions: Vec<(u32, u32)> = Vec::new();
+        for ts in timestamps {
+            match sessions.last_mut() {
+                Some((_, end)) if ts - *end <= gap_secs => *end = ts,
+                _ => sessions.push((ts, ts)),
+            }
+        }
+
+        sessions
+    }
+
+    /// Find the longest period without any scrobble.
+    ///
+    /// Tracks without a timestamp (currently playing) are ignored. When several
+    /// gaps have the same length, the earliest one is returned.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to analyze, in any order
+    ///
+    /// # Returns
+    /// * `Option<(u32, u32)>` - Timestamps of the scrobbles before and after the gap,
+    ///   `None` if there are fewer than two timestamped tracks
+    pub fn longest_silence(tracks: &[RecentTrack]) -> Option<(u32, u32)> {
+        let mut timestamps: Vec<u32> = tracks
+            .iter()
+            .filter_map(Timestamped::get_timestamp)
+            .collect();
+        timestamps.sort_unstable();
+
+        timestamps
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .fold(None, |longest, (start, end)| match longest {
+                Some((s, e)) if e - s >= end - start => longest,
+                _ => Some((start, end)),
+            })
+    }
+
+    /// Find consecutive scrobbles of the same track recorded suspiciously close together.
+    ///
+    /// Last.fm occasionally records a play twice within seconds; such pairs
+    /// are good candidates for cleaning up. Tracks without a timestamp are ignored.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to inspect, in any order
+    /// * `window_secs` - Maximum gap between two scrobbles for them to count as duplicates
+    ///
+    /// # Returns
+    /// * `Vec<(RecentTrack, RecentTrack)>` - Pairs of (earlier, later) scrobbles, oldest first
+    #[must_use]
+    pub fn suspected_duplicates(
+        tracks: &[RecentTrack],
+        window_secs: u32,
+    ) -> Vec<(RecentTrack, RecentTrack)> {
+        let mut timestamped: Vec<RecentTrack> = tracks
+            .iter()
+            .filter(|track| track.get_timestamp().is_some())
+            .cloned()
+            .collect();
+        sort_by_timestamp(&mut timestamped);
+
+        timestamped
+            .windows(2)
+            .filter(|pair| {
+                let (earlier, later) = (&pair[0], &pair[1]);
+
+                earlier.get_track_identifier() == later.get_track_identifier()
+                    && later.get_timestamp().unwrap_or_default()
+                        - earlier.get_timestamp().unwrap_or_default()
+                        <= window_secs
+            })
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect()
+    }
+
+    /// Find top tracks that haven't been played since a given time.
+    ///
+    /// Tracks are matched on artist and track name.
+    ///
+    /// # Arguments
+    /// * `top` - Top tracks (usually all-time)
+    /// * `recent` - Recent scrobbles
+    /// * `since` - Timestamp from which a scrobble counts as recent
+    ///
+    /// # Returns
+    /// * `Vec<TopTrack>` - Top tracks with no scrobble at or after `since`, in their original order
+    pub fn neglected_favorites(
+        top: &[TopTrack],
+        recent: &[RecentTrack],
+        since: u32,
+    ) -> Vec<TopTrack> {
+        let recently_played: HashSet<String> = recent
+            .iter()
+            .filter(|track| track.get_timestamp().is_some_and(|ts| ts >= since))
+            .map(TrackAnalyzable::get_track_identifier)
+            .collect();
+
+        top.iter()
+            .filter(|track| !recently_played.contains(&track.get_track_identifier()))
+            .cloned()
+            .collect()
+    }
+
+    /// Collect one album art URL per album.
+    ///
+    /// Albums are keyed on their name; tracks without an album are ignored.
+    /// The first track of an album with any image provides its URL, falling
+    /// back across sizes when `size` is missing.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to collect album art from
+    /// * `size` - Preferred image size
+    ///
+    /// # Returns
+    /// * `HashMap<String, String>` - Map of album names to image URLs
+    #[must_use]
+    pub fn collect_album_art(tracks: &[RecentTrack], size: ImageSize) -> HashMap<String, String> {
+        let mut album_art: HashMap<String, String> = HashMap::new();
+
+        for track in tracks {
+            if track.album.text.is_empty() || album_art.contains_key(&track.album.text) {
+                continue;
+            }
+
+            if let Some(url) = best_image(&track.image, size) {
+                album_art.insert(track.album.text.clone(), url.to_string());
+            }
+        }
+
+        album_art
+    }
+
+    /// Compare loved tracks with top tracks.
+    ///
+    /// Tracks are matched on artist and track name.
+    ///
+    /// # Arguments
+    /// * `loved` - Loved tracks
+    /// * `top` - Top tracks (usually all-time)
+    ///
+    /// # Returns
+    /// * `LovedTopReport` - Loved tracks split by whether they are top tracks, in their original order
+    #[must_use]
+    pub fn loved_vs_top(loved: &[LovedTrack], top: &[TopTrack]) -> LovedTopReport {
+        let top_playcounts: HashMap<String, u32> = top
+            .iter()
+            .map(|track| (track.get_track_identifier(), track.playcount))
+            .collect();
+
+        let mut in_top = Vec::new();
+        let mut underplayed = Vec::new();
+
+        for track in loved {
+            match top_playcounts.get(&track.get_track_identifier()) {
+                Some(&playcount) => in_top.push((track.clone(), playcount)),
+                None => underplayed.push(track.clone()),
+            }
+        }
+
+        LovedTopReport {
+            in_top,
+            underplayed,
+        }
+    }
+
+    /// Print analysis results in a formatted way
+    ///
+    /// # Arguments
+    /// * `stats` - `TrackStats` to print
+    pub fn print_analysis(stats: &TrackStats) {
+        println!("=== Track Analysis ===");
+        println!("Total tracks: {}", stats.total_tracks);
+
+        if let Some((artist, count)) = &stats.most_played_artist {
+            println!("\nMost played artist: {artist} ({count} plays)");
+        }
+
+        if let Some((track, count)) = &stats.most_played_track {
+            println!("Most played track: {track} ({count} plays)");
+        }
+
+        println!("\nTop 10 Artists:");
+        let mut artists: Vec<_> = stats.artist_play_counts.iter().collect();
+        artists.sort_by(|a, b| b.1.cmp(a.1));
+        for (artist, count) in artists.iter().take(10) {
+            println!("  {artist} - {count} plays");
+        }
+
+        println!("\nTop 10 Tracks:");
+        let mut tracks: Vec<_> = stats.track_play_counts.iter().collect();
+        tracks.sort_by(|a, b| b.1.cmp(a.1));
+        for (track, count) in tracks.iter().take(10) {
+            println!("  {track} - {count} plays");
+        }
+
+        println!(
+            "\nTracks below threshold: {}",
+            stats.tracks_below_threshold.len()
+        );
+
+        println!(
+            "\nTracks above threshold: {}",
+            stats.tracks_above_threshold.len()
+        );
+    }
+
+    /// Print genre analysis results in a formatted way
+    ///
+    /// # Arguments
+    /// * `stats` - `GenreStats` to print
+    pub fn print_genres(stats: &GenreStats) {
+        println!("=== Genre Analysis ===");
+        println!("Tags: {}", stats.tag_play_counts.len());
+
+        println!("\nTop {} Genres:", stats.top_genres.len());
+        for (tag, count) in &stats.top_genres {
+            println!("  {tag} - {count} plays");
+        }
+
+        println!("\nPlays without tags: {}", stats.untagged_plays);
+    }
+
+    /// Print listening patterns in a formatted way
+    ///
+    /// # Arguments
+    /// * `patterns` - `ListeningPatterns` to print
+    pub fn print_listening_patterns(patterns: &ListeningPatterns) {
+        println!("=== Listening Patterns ===");
+
+        if let Some(hour) = patterns.peak_hour {
+            println!("Peak hour: {hour:02}:00");
+        }
+        if let Some(weekday) = patterns.peak_weekday {
+            println!("Peak day: {weekday}");
+        }
+
+        println!("\nBy hour:");
+        for (hour, count) in patterns.by_hour.iter().enumerate() {
+            println!("  {hour:02}:00 - {count} plays");
+        }
+
+        println!("\nBy day of the week:");
+        let weekdays = std::iter::successors(Some(Weekday::Mon), |day| Some(day.succ()));
+        for (weekday, count) in weekdays.zip(patterns.by_weekday) {
+            println!("  {weekday} - {count} plays");
+        }
+    }
+
+    /// Save analysis results as pretty-printed JSON.
+    ///
+    /// The file can be read back with `serde_json` as a `TrackStats`, e.g.
+    /// by `analyze_file_since`.
+    ///
+    /// # Arguments
+    /// * `stats` - `TrackStats` to save
+    /// * `path` - Path to the JSON file to create (overwriting if it exists)
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be created or written to
+    pub fn save_analysis(stats: &TrackStats, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        FileHandler::save_single(stats, path)?;
+
+        Ok(())
+    }
+
+    /// Save the play count of every track as CSV, most played first.
+    ///
+    /// Unlike `print_analysis`, which only shows the top 10, every entry of
+    /// `track_play_counts` is written, as `track,play_count` rows under a
+    /// header. Tracks with the same count are sorted by identifier.
+    ///
+    /// # Arguments
+    /// * `stats` - `TrackStats` to save
+    /// * `file_path` - Path to the CSV file to create (overwriting if it exists)
+    ///
+    /// # Errors
+    /// * `csv::Error` - If the file cannot be created or written to
+    pub fn save_full_counts_csv(
+        stats: &TrackStats,
+        file_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut tracks: Vec<_> = stats.track_play_counts.iter().collect();
+        tracks.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+        let mut writer = csv::Writer::from_path(file_path)?;
+        writer.write_record(["track", "play_count"])?;
+        for (track, count) in tracks {
+            writer.write_record([track.as_str(), &count.to_string()])?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Build plays-per-day sparklines for the most played artists (UTC days).
+    ///
+    /// Every sparkline covers each UTC day from the first to the last scrobble
+    /// of the dataset, one character per day, scaled to the artist's busiest day.
+    /// Tracks without a timestamp are ignored.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to analyze
+    /// * `n` - Number of artists to include
+    ///
+    /// # Returns
+    /// * `Vec<(String, String)>` - (artist, sparkline) pairs, most played artist first
+    #[must_use]
+    pub fn artist_sparklines(tracks: &[RecentTrack], n: usize) -> Vec<(String, String)> {
+        Self::artist_sparklines_in(tracks, n, Tz::UTC)
+    }
+
+    /// Build plays-per-day sparklines for the most played artists, with days
+    /// bucketed in the given timezone.
+    ///
+    /// Every sparkline covers each day from the first to the last scrobble
+    /// of the dataset, one character per day, scaled to the artist's busiest day.
+    /// Tracks without a timestamp are ignored.
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to analyze
+    /// * `n` - Number of artists to include
+    /// * `tz` - Timezone defining day boundaries
+    ///
+    /// # Returns
+    /// * `Vec<(String, String)>` - (artist, sparkline) pairs, most played artist first
+    pub fn artist_sparklines_in(tracks: &[RecentTrack], n: usize, tz: Tz) -> Vec<(String, String)> {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+        let mut daily_plays: HashMap<String, HashMap<NaiveDate, usize>> = HashMap::new();
+        for track in tracks {
+            if let Some(played_at) = track.played_at() {
+                *daily_plays
+                    .entry(track.get_artist_name())
+                    .or_default()
+                    .entry(played_at.with_timezone(&tz).date_naive())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        let days = daily_plays.values().flat_map(HashMap::keys);
+        let (Some(first_day), Some(last_day)) = (days.clone().min(), days.max()) else {
+            return Vec::new();
+        };
+
+        let mut artists: Vec<(String, usize)> = daily_plays
+            .iter()
+            .map(|(artist, plays)| (artist.clone(), plays.values().sum()))
+            .collect();
+        artists.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        artists
+            .into_iter()
+            .take(n)
+            .map(|(artist, _)| {
+                let plays = &daily_plays[&artist];
+                let busiest = plays.values().copied().max().unwrap_or(1);
+
+                let sparkline = first_day
+                    .iter_days()
+                    .take_while(|day| *day <= *last_day)
+                    .map(|day| {
+                        let count = plays.get(&day).copied().unwrap_or(0);
+                        BARS[count * (BARS.len() - 1) / busiest]
+                    })
+                    .collect();
+
+                (artist, sparkline)
+            })
+            .collect()
+    }
+
+    /// Print plays-per-day sparklines for the most played artists
+    ///
+    /// # Arguments
+    /// * `tracks` - Tracks to analyze
+    /// * `n` - Number of artists to print
+    pub fn print_sparklines(tracks: &[RecentTrack], n: usize) {
+        println!("=== Top {n} Artists Trend ===");
+
+        let sparklines = Self::artist_sparklines(tracks, n);
+        let width = sparklines
+            .iter()
+            .map(|(artist, _)| artist.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        for (artist, sparkline) in sparklines {
+            println!("  {artist:<width$} {sparkline}");
+        }
+    }
+
+    /// Get the most recent timestamp from a JSON or NDJSON file.
+    ///
+    /// NDJSON files (`.ndjson`/`.jsonl`) are scanned line by line, so the
+    /// history is never held in memory at once. Other files are read as a
+    /// JSON array.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the JSON or NDJSON file
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or read
+    /// * `serde_json::Error` - If the JSON cannot be deserialized
+    ///
+    /// # Returns
+    /// * `Option<i64>` - Most recent timestamp
+    #[allow(dead_code)]
+    pub fn get_most_recent_timestamp<T: DeserializeOwned + Timestamped>(
         file_path: &Path,
     ) -> Result<Option<i64>, Box<dyn std::error::Error>> {
         let file = File::open(file_path)?;
         let reader = BufReader::new(file);
+
+        if matches!(FileFormat::from_path(file_path), Some(FileFormat::Ndjson)) {
+            let mut latest = None;
+            for line in reader.lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let track: T = serde_json::from_str(&line)?;
+                latest = latest.max(track.get_timestamp().map(i64::from));
+            }
+            return Ok(latest);
+        }
+
         let tracks: Vec<T> = serde_json::from_reader(reader)?;
 
-        Ok(tracks
-            .iter()
-            .filter_map(super::types::Timestamped::get_timestamp)
-            .map(i64::from)
-            .max())
+        Ok(tracks
+            .iter()
+            .filter_map(super::types::Timestamped::get_timestamp)
+            .map(i64::from)
+            .max())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file_handler::{DataStore, FileFormat};
+    use crate::test_utils::{create_recent_track, create_recent_track_at};
+    use crate::types::{Attributes, BaseObject, Date, RankAttr, Streamable, TrackImage};
+
+    fn create_loved_track(artist: &str, name: &str) -> LovedTrack {
+        LovedTrack {
+            artist: BaseObject {
+                mbid: String::new(),
+                url: String::new(),
+                name: artist.to_string(),
+            },
+            date: Some(Date {
+                uts: 0,
+                text: String::new(),
+            }),
+            image: Vec::new(),
+            streamable: Streamable {
+                fulltrack: String::new(),
+                text: String::new(),
+            },
+            name: name.to_string(),
+            mbid: String::new(),
+            url: String::new(),
+        }
+    }
+
+    fn create_top_track(artist: &str, name: &str, playcount: u32) -> TopTrack {
+        TopTrack {
+            streamable: Streamable {
+                fulltrack: String::new(),
+                text: String::new(),
+            },
+            mbid: String::new(),
+            name: name.to_string(),
+            image: Vec::new(),
+            artist: BaseObject {
+                mbid: String::new(),
+                url: String::new(),
+                name: artist.to_string(),
+            },
+            url: String::new(),
+            duration: 0,
+            attr: RankAttr {
+                rank: String::new(),
+            },
+            playcount,
+        }
+    }
+
+    /// Saves `tracks` with `DataStore::save` and reloads them with `analyze_file`
+    fn save_and_reload<T>(tracks: &[T], prefix: &str) -> TrackStats
+    where
+        T: Serialize + DeserializeOwned + TrackAnalyzable,
+    {
+        let root = std::env::temp_dir().join(format!("async_lastfm_test_{prefix}"));
+        let path = DataStore::new(&root)
+            .save(tracks, &FileFormat::Json, prefix)
+            .unwrap();
+        let stats = AnalysisHandler::analyze_file::<T>(Path::new(&path), 1);
+        std::fs::remove_dir_all(&root).unwrap();
+
+        stats.unwrap()
+    }
+
+    #[test]
+    fn test_saved_recent_tracks_reload() {
+        let mut now_playing = create_recent_track("Artist1", "Song2");
+        now_playing.attr = Some(Attributes {
+            nowplaying: "true".to_string(),
+        });
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", 1_700_000_000),
+            now_playing,
+        ];
+
+        let stats = save_and_reload(&tracks, "test_reload_recent");
+
+        assert_eq!(stats.total_tracks, 2);
+        assert_eq!(stats.artist_play_counts["Artist1"], 2);
+    }
+
+    #[test]
+    fn test_saved_loved_tracks_reload() {
+        let tracks = vec![create_loved_track("Artist1", "Song1")];
+
+        let stats = save_and_reload(&tracks, "test_reload_loved");
+
+        assert_eq!(stats.total_tracks, 1);
+    }
+
+    #[test]
+    fn test_saved_top_tracks_reload() {
+        let tracks = vec![
+            create_top_track("Artist1", "Song1", 10),
+            create_top_track("Artist2", "Song2", 5),
+        ];
+
+        let stats = save_and_reload(&tracks, "test_reload_top");
+
+        assert_eq!(stats.total_tracks, 2);
+        assert_eq!(stats.track_play_counts["Artist2 - Song2"], 1);
+    }
+
+    #[test]
+    fn test_analyze_file_since_merges_prior_stats() {
+        let old_tracks = vec![
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist2", "Song2"),
+        ];
+        let prior = AnalysisHandler::analyze_tracks(&old_tracks, 2);
+
+        let dir = std::env::temp_dir();
+        let stats_path = dir.join("async_lastfm_test_prior_stats.json");
+        let tracks_path = dir.join("async_lastfm_test_new_tracks.json");
+        std::fs::write(&stats_path, serde_json::to_string(&prior).unwrap()).unwrap();
+        let new_tracks = vec![
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist3", "Song3"),
+        ];
+        std::fs::write(&tracks_path, serde_json::to_string(&new_tracks).unwrap()).unwrap();
+
+        let stats =
+            AnalysisHandler::analyze_file_since::<RecentTrack>(&stats_path, &tracks_path, 2);
+        std::fs::remove_file(&stats_path).unwrap();
+        std::fs::remove_file(&tracks_path).unwrap();
+        let stats = stats.unwrap();
+
+        assert_eq!(stats.total_tracks, 4);
+        assert_eq!(stats.artist_play_counts["Artist1"], 2);
+        assert_eq!(stats.artist_play_counts["Artist3"], 1);
+        assert_eq!(
+            stats.most_played_track,
+            Some(("Artist1 - Song1".to_string(), 2))
+        );
+        assert_eq!(stats.tracks_above_threshold.len(), 1);
+        assert_eq!(stats.tracks_below_threshold.len(), 2);
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::types::{BaseMbidText, BaseObject, Date, Streamable};
+    #[test]
+    fn test_suspected_duplicates() {
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", 1_700_000_203),
+            create_recent_track_at("Artist1", "Song1", 1_700_000_200),
+            create_recent_track_at("Artist1", "Song2", 1_700_000_000),
+            create_recent_track_at("Artist1", "Song2", 1_700_000_100),
+            create_recent_track("Artist1", "Song1"),
+        ];
 
-    fn create_recent_track(artist: &str, name: &str) -> RecentTrack {
-        RecentTrack {
-            artist: BaseMbidText {
-                mbid: String::new(),
-                text: artist.to_string(),
-            },
-            streamable: false,
-            image: Vec::new(),
-            album: BaseMbidText {
-                mbid: String::new(),
-                text: String::new(),
-            },
-            attr: None,
-            date: None,
-            name: name.to_string(),
-            mbid: String::new(),
-            url: String::new(),
-        }
+        let duplicates = AnalysisHandler::suspected_duplicates(&tracks, 10);
+
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].0.name, "Song1");
+        assert_eq!(duplicates[0].0.get_timestamp(), Some(1_700_000_200));
+        assert_eq!(duplicates[0].1.get_timestamp(), Some(1_700_000_203));
     }
 
-    fn create_loved_track(artist: &str, name: &str) -> LovedTrack {
-        LovedTrack {
-            artist: BaseObject {
-                mbid: String::new(),
-                url: String::new(),
-                name: artist.to_string(),
-            },
-            date: Date {
-                uts: 0,
-                text: String::new(),
-            },
-            image: Vec::new(),
-            streamable: Streamable {
-                fulltrack: String::new(),
-                text: String::new(),
-            },
-            name: name.to_string(),
-            mbid: String::new(),
-            url: String::new(),
+    #[test]
+    fn test_normalize_recent_track() {
+        let mut track = create_recent_track_at("Artist1", "Song1", 1_700_000_000);
+        track.album.text = "Album1".to_string();
+
+        assert_eq!(
+            NormalizedTrack::from(track),
+            NormalizedTrack {
+                artist: "Artist1".to_string(),
+                track: "Song1".to_string(),
+                album: Some("Album1".to_string()),
+                timestamp: Some(1_700_000_000),
+                playcount: 1,
+            }
+        );
+        assert_eq!(
+            NormalizedTrack::from(create_recent_track("Artist1", "Song1")).album,
+            None
+        );
+    }
+
+    #[test]
+    fn test_normalize_loved_track() {
+        let track = create_loved_track("Artist1", "Song1");
+        let uts = track.get_timestamp();
+
+        assert_eq!(
+            NormalizedTrack::from(track),
+            NormalizedTrack {
+                artist: "Artist1".to_string(),
+                track: "Song1".to_string(),
+                album: None,
+                timestamp: uts,
+                playcount: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_normalize_top_track() {
+        assert_eq!(
+            NormalizedTrack::from(create_top_track("Artist1", "Song1", 42)),
+            NormalizedTrack {
+                artist: "Artist1".to_string(),
+                track: "Song1".to_string(),
+                album: None,
+                timestamp: None,
+                playcount: 42,
+            }
+        );
+    }
+
+    #[test]
+    fn test_analyze_mixed_normalized_tracks() {
+        let tracks: Vec<NormalizedTrack> = vec![
+            create_recent_track("Artist1", "Song1").into(),
+            create_top_track("Artist1", "Song1", 42).into(),
+            create_loved_track("Artist2", "Song2").into(),
+        ];
+
+        let stats = AnalysisHandler::analyze_tracks(&tracks, 2);
+
+        assert_eq!(stats.total_tracks, 3);
+        assert_eq!(stats.track_play_counts["Artist1 - Song1"], 2);
+    }
+
+    #[test]
+    fn test_collect_album_art() {
+        fn with_album(name: &str, album: &str, images: &[(&str, &str)]) -> RecentTrack {
+            let mut track = create_recent_track("Artist1", name);
+            track.album.text = album.to_string();
+            track.image = images
+                .iter()
+                .map(|(size, url)| TrackImage {
+                    size: (*size).to_string(),
+                    text: (*url).to_string(),
+                })
+                .collect();
+            track
         }
+
+        let tracks = vec![
+            with_album(
+                "Song1",
+                "Album1",
+                &[("small", "a1-s"), ("extralarge", "a1-xl")],
+            ),
+            with_album("Song2", "Album1", &[("extralarge", "other")]),
+            with_album("Song3", "Album2", &[("small", ""), ("medium", "a2-m")]),
+            with_album("Song4", "Album3", &[("extralarge", "")]),
+            with_album("Song5", "Album3", &[("large", "a3-l")]),
+            with_album("Song6", "", &[("large", "no-album")]),
+        ];
+
+        let art = AnalysisHandler::collect_album_art(&tracks, ImageSize::Large);
+
+        assert_eq!(art.len(), 3);
+        assert_eq!(art["Album1"], "a1-xl");
+        assert_eq!(art["Album2"], "a2-m");
+        assert_eq!(art["Album3"], "a3-l");
+    }
+
+    #[test]
+    fn test_loved_vs_top() {
+        let loved = vec![
+            create_loved_track("Artist1", "Song1"),
+            create_loved_track("Artist2", "Song2"),
+            create_loved_track("Artist3", "Song3"),
+        ];
+        let top = vec![
+            create_top_track("Artist1", "Song1", 42),
+            create_top_track("Artist3", "Song3", 7),
+            create_top_track("Artist4", "Song4", 3),
+        ];
+
+        let report = AnalysisHandler::loved_vs_top(&loved, &top);
+
+        assert_eq!(report.in_top.len(), 2);
+        assert_eq!(report.in_top[0].0.name, "Song1");
+        assert_eq!(report.in_top[0].1, 42);
+        assert_eq!(report.in_top[1].1, 7);
+        assert_eq!(report.underplayed.len(), 1);
+        assert_eq!(report.underplayed[0].name, "Song2");
     }
 
     #[test]
@@ -277,6 +1781,56 @@ mod tests {
         assert_eq!(stats.most_played_artist, Some(("Artist1".to_string(), 3)));
     }
 
+    #[test]
+    fn test_analyze_tracks_with_empty_names() {
+        let tracks = vec![
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist1", ""),
+            create_recent_track("", "Song2"),
+        ];
+
+        let kept = AnalysisHandler::analyze_tracks_with(&tracks, 2, EmptyNames::Keep);
+        assert_eq!(kept.total_tracks, 3);
+        assert_eq!(kept.track_play_counts["Artist1 - "], 1);
+
+        let skipped = AnalysisHandler::analyze_tracks_with(&tracks, 2, EmptyNames::Skip);
+        assert_eq!(skipped.total_tracks, 1);
+        assert_eq!(skipped.track_play_counts.len(), 1);
+        assert!(!skipped.artist_play_counts.contains_key(""));
+
+        let bucketed = AnalysisHandler::analyze_tracks_with(&tracks, 2, EmptyNames::Unknown);
+        assert_eq!(bucketed.total_tracks, 3);
+        assert_eq!(bucketed.track_play_counts["Artist1 - (unknown)"], 1);
+        assert_eq!(bucketed.track_play_counts["(unknown) - Song2"], 1);
+        assert_eq!(bucketed.artist_play_counts["Artist1"], 2);
+        assert_eq!(bucketed.artist_play_counts[UNKNOWN_NAME], 1);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_analyze_tracks_parallel_matches_sequential() {
+        let tracks: Vec<RecentTrack> = (0..100_000)
+            .map(|i| {
+                create_recent_track(&format!("Artist{}", i % 97), &format!("Song{}", i % 1_013))
+            })
+            .collect();
+
+        let parallel = AnalysisHandler::analyze_tracks_parallel(&tracks, 100);
+        let sequential = AnalysisHandler::analyze_tracks(&tracks, 100);
+
+        assert_eq!(parallel.total_tracks, sequential.total_tracks);
+        assert_eq!(parallel.artist_play_counts, sequential.artist_play_counts);
+        assert_eq!(parallel.track_play_counts, sequential.track_play_counts);
+        assert_eq!(
+            parallel.tracks_below_threshold,
+            sequential.tracks_below_threshold
+        );
+        assert_eq!(
+            parallel.tracks_above_threshold,
+            sequential.tracks_above_threshold
+        );
+    }
+
     #[test]
     fn test_analyze_loved_tracks() {
         let tracks = vec![
@@ -294,4 +1848,424 @@ mod tests {
         assert_eq!(stats.track_play_counts["Artist1 - Song1"], 2);
         assert_eq!(stats.most_played_artist, Some(("Artist1".to_string(), 3)));
     }
+
+    #[test]
+    fn test_analyze_ndjson() {
+        let path = std::env::temp_dir().join("async_lastfm_test_analyze.ndjson");
+        let mut file = File::create(&path).unwrap();
+
+        let mut tracks = Vec::new();
+        for i in 0..10_000 {
+            let track =
+                create_recent_track(&format!("Artist{}", i % 10), &format!("Song{}", i % 100));
+            serde_json::to_writer(&mut file, &track).unwrap();
+            std::io::Write::write_all(&mut file, b"\n").unwrap();
+            tracks.push(track);
+        }
+        drop(file);
+
+        let stats = AnalysisHandler::analyze_ndjson::<RecentTrack>(&path, 2).unwrap();
+        let expected = AnalysisHandler::analyze_tracks(&tracks, 2);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(stats.total_tracks, 10_000);
+        assert_eq!(stats.artist_play_counts, expected.artist_play_counts);
+        assert_eq!(stats.track_play_counts, expected.track_play_counts);
+        assert_eq!(stats.artist_play_counts["Artist0"], 1_000);
+    }
+
+    #[tokio::test]
+    async fn test_analyze_stream_matches_batch() {
+        let tracks: Vec<RecentTrack> = (0..500)
+            .map(|i| create_recent_track(&format!("Artist{}", i % 7), &format!("Song{}", i % 31)))
+            .collect();
+
+        let stats = AnalysisHandler::analyze_stream(futures::stream::iter(tracks.clone()), 2).await;
+        let expected = AnalysisHandler::analyze_tracks(&tracks, 2);
+
+        assert_eq!(stats.total_tracks, expected.total_tracks);
+        assert_eq!(stats.artist_play_counts, expected.artist_play_counts);
+        assert_eq!(stats.track_play_counts, expected.track_play_counts);
+        assert_eq!(
+            stats.tracks_below_threshold,
+            expected.tracks_below_threshold
+        );
+        assert_eq!(
+            stats.tracks_above_threshold,
+            expected.tracks_above_threshold
+        );
+    }
+
+    #[test]
+    fn test_daily_summary() {
+        // 2024-12-04 00:00:00 UTC
+        let day = 1_733_270_400;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", day + 10),
+            create_recent_track_at("Artist1", "Song2", day + 20),
+            create_recent_track_at("Artist1", "Song3", day + 30),
+            create_recent_track_at("Artist2", "Song1", day + 86_400 + 10),
+            create_recent_track_at("Artist2", "Song2", day + 2 * 86_400 + 10),
+            create_recent_track_at("Artist2", "Song3", day + 2 * 86_400 + 20),
+            create_recent_track("Artist3", "Now Playing"),
+        ];
+
+        let summary = AnalysisHandler::daily_summary(&tracks);
+
+        assert_eq!(summary.total_days_active, 3);
+        assert!((summary.average_scrobbles_per_day - 2.0).abs() < f64::EPSILON);
+        assert_eq!(
+            summary.busiest_day,
+            Some((NaiveDate::from_ymd_opt(2024, 12, 4).unwrap(), 3))
+        );
+        assert_eq!(
+            summary.quietest_day,
+            Some((NaiveDate::from_ymd_opt(2024, 12, 5).unwrap(), 1))
+        );
+    }
+
+    #[test]
+    fn test_diff_loved() {
+        let old = vec![
+            create_loved_track("Artist1", "Song1"),
+            create_loved_track("Artist1", "Song2"),
+        ];
+        let new = vec![
+            create_loved_track("Artist1", "Song1"),
+            create_loved_track("Artist2", "Song3"),
+        ];
+
+        let diff = AnalysisHandler::diff_loved(&old, &new);
+
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "Song3");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].name, "Song2");
+    }
+
+    #[test]
+    fn test_firsts() {
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song2", 300),
+            create_recent_track_at("Artist2", "Song3", 250),
+            create_recent_track_at("Artist1", "Song1", 200),
+            create_recent_track_at("Artist2", "Song4", 400),
+            create_recent_track("Artist3", "Now Playing"),
+        ];
+
+        let firsts = AnalysisHandler::firsts(&tracks);
+
+        assert_eq!(firsts.first_ever.unwrap().name, "Song1");
+        assert_eq!(firsts.first_per_artist.len(), 2);
+        assert_eq!(firsts.first_per_artist["Artist1"], 200);
+        assert_eq!(firsts.first_per_artist["Artist2"], 250);
+    }
+
+    #[test]
+    fn test_daily_summary_in_timezone() {
+        // 2024-12-05 02:00:00 UTC is still 2024-12-04 in New York
+        let day = 1_733_270_400;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", day + 12 * 3_600),
+            create_recent_track_at("Artist1", "Song2", day + 26 * 3_600),
+        ];
+
+        let utc = AnalysisHandler::daily_summary(&tracks);
+        let new_york = AnalysisHandler::daily_summary_in(&tracks, chrono_tz::America::New_York);
+
+        assert_eq!(utc.total_days_active, 2);
+        assert_eq!(new_york.total_days_active, 1);
+        assert_eq!(
+            new_york.busiest_day,
+            Some((NaiveDate::from_ymd_opt(2024, 12, 4).unwrap(), 2))
+        );
+    }
+
+    #[test]
+    fn test_listening_clock() {
+        let day = 1_733_270_400;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", day + 9 * 3_600),
+            create_recent_track_at("Artist1", "Song2", day + 9 * 3_600 + 120),
+            create_recent_track_at("Artist1", "Song3", day + 23 * 3_600),
+            create_recent_track("Artist1", "Song4"),
+        ];
+
+        let clock = AnalysisHandler::listening_clock(&tracks);
+
+        assert_eq!(clock.iter().map(|(_, count)| count).sum::<usize>(), 3);
+        assert_eq!(clock[9], (9, 2));
+        assert_eq!(clock[23], (23, 1));
+
+        let path = std::env::temp_dir().join("async_lastfm_test_clock.json");
+        AnalysisHandler::export_listening_clock(&clock, &path).unwrap();
+        let chart: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(chart["labels"][9], "09:00");
+        assert_eq!(chart["values"][9], 2);
+        assert_eq!(chart["values"].as_array().unwrap().len(), 24);
+    }
+
+    #[test]
+    fn test_analyze_listening_patterns() {
+        // Wednesday 2024-12-04 00:00 UTC
+        let day = 1_733_270_400;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", day + 9 * 3_600),
+            create_recent_track_at("Artist1", "Song2", day + 9 * 3_600 + 120),
+            create_recent_track_at("Artist1", "Song3", day + 86_400 + 23 * 3_600),
+            create_recent_track("Artist1", "Song4"),
+        ];
+
+        let utc = AnalysisHandler::analyze_listening_patterns(&tracks, Tz::UTC);
+
+        assert_eq!(utc.by_hour.iter().sum::<usize>(), 3);
+        assert_eq!(utc.by_hour[9], 2);
+        assert_eq!(utc.by_weekday, [0, 0, 2, 1, 0, 0, 0]);
+        assert_eq!(utc.peak_hour, Some(9));
+        assert_eq!(utc.peak_weekday, Some(Weekday::Wed));
+
+        // 09:00 UTC is 04:00 the same day in New York, 23:00 is 18:00
+        let new_york =
+            AnalysisHandler::analyze_listening_patterns(&tracks, chrono_tz::America::New_York);
+        assert_eq!(new_york.by_hour[4], 2);
+        assert_eq!(new_york.by_hour[18], 1);
+
+        let empty = AnalysisHandler::analyze_listening_patterns::<RecentTrack>(&[], Tz::UTC);
+        assert_eq!(empty.peak_hour, None);
+        assert_eq!(empty.peak_weekday, None);
+    }
+
+    #[test]
+    fn test_save_analysis_round_trip() {
+        let tracks = vec![
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist2", "Song2"),
+        ];
+        let stats = AnalysisHandler::analyze_tracks(&tracks, 2);
+
+        let path = std::env::temp_dir().join("async_lastfm_test_save_analysis.json");
+        AnalysisHandler::save_analysis(&stats, path.to_str().unwrap()).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(
+            json["most_played_artist"],
+            serde_json::json!({ "name": "Artist1", "count": 2 })
+        );
+        assert_eq!(json["most_played_track"]["name"], "Artist1 - Song1");
+
+        let reloaded: TrackStats = serde_json::from_str(&content).unwrap();
+        assert_eq!(reloaded.total_tracks, 3);
+        assert_eq!(reloaded.artist_play_counts, stats.artist_play_counts);
+        assert_eq!(reloaded.track_play_counts, stats.track_play_counts);
+        assert_eq!(reloaded.most_played_track, stats.most_played_track);
+
+        // Stats saved before the pairs had named fields
+        let mut legacy = json;
+        legacy["most_played_artist"] = serde_json::json!(["Artist1", 2]);
+        let legacy: TrackStats = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy.most_played_artist, Some(("Artist1".to_string(), 2)));
+    }
+
+    #[test]
+    fn test_analyze_genres() {
+        let tracks = vec![
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist2", "Song2"),
+            create_recent_track("Artist3", "Song3"),
+        ];
+        let tags_for = |artist: &str, _: &str| -> Vec<String> {
+            match artist {
+                "Artist1" => vec!["Rock".into(), "rock".into(), "seen live".into()],
+                "Artist2" => vec!["Indie".into(), "Rock".into(), "Électro".into()],
+                _ => vec!["Favorites".into()],
+            }
+        };
+        let options = GenreOptions {
+            blocklist: vec![
+                "Seen Live".to_string(),
+                "favorites".to_string(),
+                "électro".to_string(),
+            ],
+            top_n: 1,
+            ..GenreOptions::default()
+        };
+
+        let stats = AnalysisHandler::analyze_genres(&tracks, tags_for, &options);
+
+        assert_eq!(stats.tag_play_counts.len(), 2);
+        assert_eq!(stats.tag_play_counts["rock"], 3);
+        assert_eq!(stats.tag_play_counts["indie"], 1);
+        assert_eq!(stats.top_genres, vec![("rock".to_string(), 3)]);
+        assert_eq!(stats.untagged_plays, 1);
+
+        let case_sensitive = AnalysisHandler::analyze_genres(
+            &tracks,
+            tags_for,
+            &GenreOptions {
+                lowercase: false,
+                ..options
+            },
+        );
+        assert_eq!(case_sensitive.tag_play_counts["Rock"], 3);
+        assert_eq!(case_sensitive.tag_play_counts["rock"], 2);
+        assert!(!case_sensitive.tag_play_counts.contains_key("Électro"));
+    }
+
+    #[test]
+    fn test_analyze_genres_with_info() {
+        let tracks = vec![
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist2", "Song2"),
+        ];
+        let mut infos = HashMap::new();
+        infos.insert(
+            ("Artist1".to_string(), "Song1".to_string()),
+            Ok(TrackInfo {
+                name: "Song1".to_string(),
+                artist: "Artist1".to_string(),
+                url: String::new(),
+                listeners: 0,
+                playcount: 0,
+                duration_ms: None,
+                tags: vec!["Jazz".to_string()],
+            }),
+        );
+        infos.insert(
+            ("Artist2".to_string(), "Song2".to_string()),
+            Err(crate::error::LastFmError::Other("not found".to_string())),
+        );
+
+        let stats =
+            AnalysisHandler::analyze_genres_with_info(&tracks, &infos, &GenreOptions::default());
+
+        assert_eq!(stats.top_genres, vec![("jazz".to_string(), 1)]);
+        assert_eq!(stats.untagged_plays, 1);
+    }
+
+    #[test]
+    fn test_save_full_counts_csv() {
+        let tracks = vec![
+            create_recent_track("Artist1", "Song1"),
+            create_recent_track("Artist1", "Song2"),
+            create_recent_track("Artist1", "Song2"),
+            create_recent_track("Artist2", "Song3"),
+            create_recent_track("Artist2", "Song3"),
+            create_recent_track("Artist2", "Song3"),
+        ];
+        let stats = AnalysisHandler::analyze_tracks(&tracks, 2);
+
+        let path = std::env::temp_dir().join("async_lastfm_test_full_counts.csv");
+        AnalysisHandler::save_full_counts_csv(&stats, &path).unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), stats.track_play_counts.len() + 1);
+        assert_eq!(lines[0], "track,play_count");
+        assert_eq!(lines[1], "Artist2 - Song3,3");
+        assert_eq!(lines[3], "Artist1 - Song1,1");
+    }
+
+    #[test]
+    fn test_artist_sparklines() {
+        let day = 1_733_270_400;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", day),
+            create_recent_track_at("Artist1", "Song1", day + 60),
+            create_recent_track_at("Artist1", "Song2", day + 3 * 86_400),
+            create_recent_track_at("Artist2", "Song3", day + 86_400),
+            create_recent_track("Artist3", "Song4"),
+        ];
+
+        let sparklines = AnalysisHandler::artist_sparklines(&tracks, 1);
+
+        assert_eq!(sparklines.len(), 1);
+        assert_eq!(sparklines[0].0, "Artist1");
+        assert_eq!(sparklines[0].1.chars().count(), 4);
+        assert_eq!(sparklines[0].1, "█▁▁▄");
+    }
+
+    #[test]
+    fn test_artist_sparklines_in_timezone() {
+        // 2024-12-05 02:00:00 UTC is still 2024-12-04 in New York
+        let day = 1_733_270_400;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", day + 12 * 3_600),
+            create_recent_track_at("Artist1", "Song2", day + 26 * 3_600),
+        ];
+
+        let utc = AnalysisHandler::artist_sparklines(&tracks, 1);
+        let new_york =
+            AnalysisHandler::artist_sparklines_in(&tracks, 1, chrono_tz::America::New_York);
+
+        assert_eq!(utc[0].1, "██");
+        assert_eq!(new_york[0].1, "█");
+    }
+
+    #[test]
+    fn test_average_session_length() {
+        let tracks = vec![
+            // First session: 10 minutes
+            create_recent_track_at("Artist1", "Song1", 1_000),
+            create_recent_track_at("Artist1", "Song2", 1_300),
+            create_recent_track_at("Artist1", "Song3", 1_600),
+            // Second session: 20 minutes
+            create_recent_track_at("Artist2", "Song1", 10_000),
+            create_recent_track_at("Artist2", "Song2", 10_600),
+            create_recent_track_at("Artist2", "Song3", 11_200),
+            create_recent_track("Artist3", "Now Playing"),
+        ];
+
+        assert_eq!(
+            AnalysisHandler::average_session_length(&tracks, 1_800),
+            Some(Duration::from_secs(900))
+        );
+        assert_eq!(AnalysisHandler::average_session_length(&[], 1_800), None);
+    }
+
+    #[test]
+    fn test_longest_silence() {
+        let day = 86_400;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", 1_000),
+            create_recent_track_at("Artist1", "Song2", 1_000 + day),
+            // Three-week break
+            create_recent_track_at("Artist2", "Song1", 1_000 + 22 * day),
+            create_recent_track_at("Artist2", "Song2", 1_000 + 2 * day),
+            create_recent_track_at("Artist2", "Song3", 1_000 + 23 * day),
+            create_recent_track("Artist3", "Now Playing"),
+        ];
+
+        assert_eq!(
+            AnalysisHandler::longest_silence(&tracks),
+            Some((1_000 + 2 * day, 1_000 + 22 * day))
+        );
+        assert_eq!(AnalysisHandler::longest_silence(&tracks[..1]), None);
+    }
+
+    #[test]
+    fn test_neglected_favorites() {
+        let top = vec![
+            create_top_track("Artist1", "Song1", 100),
+            create_top_track("Artist2", "Song2", 80),
+            create_top_track("Artist3", "Song3", 60),
+        ];
+        let recent = vec![
+            create_recent_track_at("Artist1", "Song1", 2_000),
+            // Played, but before the cutoff
+            create_recent_track_at("Artist3", "Song3", 500),
+        ];
+
+        let neglected = AnalysisHandler::neglected_favorites(&top, &recent, 1_000);
+
+        let names: Vec<&str> = neglected.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["Song2", "Song3"]);
+    }
 }
@@ -0,0 +1,87 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+
+/// A small least-recently-used cache.
+///
+/// Recency is tracked in a `VecDeque`, so lookups are linear in the capacity;
+/// this is meant for caches of a few hundred entries.
+#[derive(Debug)]
+pub struct LruCache<K, V> {
+    capacity: usize,
+    entries: HashMap<K, V>,
+    order: VecDeque<K>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> LruCache<K, V> {
+    /// Create a cache holding at most `capacity` entries. A capacity of 0 disables caching.
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Get a clone of the value for `key`, marking it as most recently used.
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let value = self.entries.get(key)?.clone();
+        self.touch(key);
+        Some(value)
+    }
+
+    /// Insert a value, evicting the least recently used entry if the cache is full.
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.insert(key.clone(), value).is_some() {
+            self.touch(&key);
+            return;
+        }
+
+        self.order.push_back(key);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Move `key` to the most recently used position.
+    fn touch(&mut self, key: &K) {
+        if let Some(position) = self.order.iter().position(|k| k == key) {
+            if let Some(k) = self.order.remove(position) {
+                self.order.push_back(k);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert("a", 1);
+        cache.insert("b", 2);
+
+        // Touch "a" so "b" becomes the eviction candidate
+        assert_eq!(cache.get(&"a"), Some(1));
+        cache.insert("c", 3);
+
+        assert_eq!(cache.get(&"a"), Some(1));
+        assert_eq!(cache.get(&"b"), None);
+        assert_eq!(cache.get(&"c"), Some(3));
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_cache() {
+        let mut cache = LruCache::new(0);
+        cache.insert("a", 1);
+
+        assert_eq!(cache.get(&"a"), None);
+    }
+}
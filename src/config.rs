@@ -1,9 +1,59 @@
 use crate::error::{LastFmError, Result};
 use std::env;
+use std::time::Duration;
 
 /// Required environment variables for the application
 const REQUIRED_ENV_VARS: &[&str] = &["LAST_FM_API_KEY"];
 
+/// Explicit configuration for a `LastFMHandler`, independent of the environment
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The Last.fm API key
+    pub api_key: String,
+    /// The Last.fm username
+    pub username: String,
+    /// Base URL of the API, defaults to the public Last.fm endpoint
+    pub base_url: Option<String>,
+    /// User-Agent sent with every request, defaults to `async_lastfm/<version>`
+    pub user_agent: Option<String>,
+    /// Maximum number of tracks requested per page, defaults to 1000
+    pub max_page_limit: Option<u32>,
+    /// Timeout of each HTTP request, none by default
+    pub request_timeout: Option<Duration>,
+}
+
+impl Config {
+    /// Creates a configuration with default settings.
+    ///
+    /// # Arguments
+    /// * `api_key` - The Last.fm API key
+    /// * `username` - The Last.fm username
+    #[must_use]
+    pub fn new(api_key: &str, username: &str) -> Self {
+        Config {
+            api_key: api_key.to_string(),
+            username: username.to_string(),
+            base_url: None,
+            user_agent: None,
+            max_page_limit: None,
+            request_timeout: None,
+        }
+    }
+
+    /// Creates a configuration reading the API key from `LAST_FM_API_KEY`.
+    ///
+    /// # Arguments
+    /// * `username` - The Last.fm username
+    ///
+    /// # Errors
+    /// Returns `LastFmError::MissingEnvVar` if `LAST_FM_API_KEY` is not set
+    pub fn from_env(username: &str) -> Result<Self> {
+        let api_key = get_required_env_var("LAST_FM_API_KEY")?;
+
+        Ok(Config::new(&api_key, username))
+    }
+}
+
 /// Validates that all required environment variables are set
 ///
 /// # Errors
@@ -1,3 +1,4 @@
+use crate::url_builder::redact_url;
 use serde::{Deserialize, Serialize};
 use std::error::Error as StdError;
 use std::fmt;
@@ -8,6 +9,12 @@ pub struct LastFmErrorResponse {
     pub error: u32,
 }
 
+/// Errors returned by this crate.
+///
+/// `LastFmError` is `Send + Sync + 'static`: every variant holds owned data,
+/// and boxed errors are flattened into `Other` by their message. Results can
+/// therefore be returned from spawned tasks and converted into `anyhow::Error`
+/// or `Box<dyn Error + Send + Sync>` with `?`.
 #[derive(Debug)]
 pub enum LastFmError {
     /// Represents a Last.fm API error with code and message
@@ -20,11 +27,26 @@ pub enum LastFmError {
     Io(std::io::Error),
     /// Represents missing environment variable errors
     MissingEnvVar(String),
+    /// Represents an API key rejected by Last.fm
+    InvalidApiKey,
+    /// Represents a username unknown to Last.fm
+    UserNotFound(String),
+    /// Represents a response whose shape no longer matches the expected schema
+    SchemaMismatch(Vec<String>),
     /// Represents other errors
     Other(String),
 }
 
-impl std::error::Error for LastFmError {}
+impl StdError for LastFmError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            LastFmError::Http(e) => Some(e),
+            LastFmError::Parse(e) => Some(e),
+            LastFmError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
 
 impl fmt::Display for LastFmError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -38,13 +60,26 @@ impl fmt::Display for LastFmError {
                 "Missing required environment variable: {var}\n\
                  Please set it in your environment or .env file"
             ),
+            LastFmError::InvalidApiKey => write!(f, "Invalid Last.fm API key"),
+            LastFmError::UserNotFound(user) => write!(f, "Last.fm user not found: {user}"),
+            LastFmError::SchemaMismatch(issues) => {
+                write!(
+                    f,
+                    "Unexpected Last.fm response schema: {}",
+                    issues.join("; ")
+                )
+            }
             LastFmError::Other(e) => write!(f, "Error: {e}"),
         }
     }
 }
 
 impl From<reqwest::Error> for LastFmError {
-    fn from(err: reqwest::Error) -> Self {
+    /// Wrap a `reqwest` error, masking the API key in the URL it reports.
+    fn from(mut err: reqwest::Error) -> Self {
+        if let Some(url) = err.url_mut() {
+            redact_url(url);
+        }
         LastFmError::Http(err)
     }
 }
@@ -68,5 +103,49 @@ impl From<Box<dyn StdError>> for LastFmError {
     }
 }
 
+// Handle Box<dyn std::error::Error + Send + Sync>, as produced by most async code
+impl From<Box<dyn StdError + Send + Sync>> for LastFmError {
+    fn from(err: Box<dyn StdError + Send + Sync>) -> Self {
+        LastFmError::Other(err.to_string())
+    }
+}
+
 /// Helper type for Result with `LastFmError`
 pub type Result<T> = std::result::Result<T, LastFmError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_error_bounds<E: StdError + Send + Sync + 'static>() {}
+
+    #[test]
+    fn test_error_is_send_sync() {
+        assert_error_bounds::<LastFmError>();
+    }
+
+    #[tokio::test]
+    async fn test_error_crosses_spawned_task() {
+        let handle = tokio::spawn(async {
+            tokio::task::yield_now().await;
+            Err::<(), _>(LastFmError::UserNotFound("ghost".to_string()))
+        });
+
+        let result = handle.await.unwrap();
+
+        assert!(matches!(result, Err(LastFmError::UserNotFound(user)) if user == "ghost"));
+    }
+
+    #[test]
+    fn test_error_converts_to_boxed_send_sync_error() {
+        let err = LastFmError::from(std::io::Error::other("disk full"));
+
+        let boxed: Box<dyn StdError + Send + Sync> = err.into();
+
+        assert_eq!(boxed.to_string(), "I/O error: disk full");
+        assert!(boxed.source().is_some());
+
+        let back = LastFmError::from(boxed);
+        assert!(matches!(back, LastFmError::Other(_)));
+    }
+}
@@ -1,22 +1,575 @@
-use chrono::Local;
-use csv::Writer;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat};
+use chrono_tz::Tz;
+use csv::{Writer, WriterBuilder};
+use serde::de::DeserializeOwned;
 use serde::Serialize;
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::BTreeMap;
 use std::fs::{self, File, OpenOptions};
-use std::io::{prelude::*, Result};
+use std::io::{prelude::*, BufReader, Result};
+use std::path::{Path, PathBuf};
 
-use crate::lastfm_handler::TrackPlayInfo;
+use crate::types::{
+    LovedTrack, MinimalTrack, RecentTrack, Timestamped, TopAlbum, TopArtist, TopTrack,
+    TrackPlayInfo,
+};
+
+/// Directory saved files are written to by default
+pub const DATA_DIR: &str = "data";
+
+/// Format of the timestamp embedded in saved filenames
+const FILENAME_TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
 
 #[allow(dead_code)]
 pub enum FileFormat {
     Json,
     Csv,
+    Ndjson,
+}
+
+impl FileFormat {
+    /// Extension of files saved in this format
+    fn extension(&self) -> &'static str {
+        match self {
+            FileFormat::Json => "json",
+            FileFormat::Csv => "csv",
+            FileFormat::Ndjson => "ndjson",
+        }
+    }
+
+    /// Format of a file, guessed from its extension (`.jsonl` counts as NDJSON)
+    ///
+    /// # Returns
+    /// * `Option<FileFormat>` - The format, `None` for other extensions
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<FileFormat> {
+        let extension = path.extension()?.to_str()?;
+
+        if extension.eq_ignore_ascii_case("json") {
+            Some(FileFormat::Json)
+        } else if extension.eq_ignore_ascii_case("ndjson")
+            || extension.eq_ignore_ascii_case("jsonl")
+        {
+            Some(FileFormat::Ndjson)
+        } else if extension.eq_ignore_ascii_case("csv") {
+            Some(FileFormat::Csv)
+        } else {
+            None
+        }
+    }
+}
+
+/// Types that can be written as flat CSV rows
+pub trait CsvRow {
+    /// Column names, in the order used by `row`
+    fn headers() -> Vec<&'static str>;
+
+    /// Column values for this item
+    fn row(&self) -> Vec<String>;
+}
+
+/// Types that can be projected into a lighter output schema `P`
+pub trait TrackView<P> {
+    /// Build the projection of this track
+    fn project(&self) -> P;
+}
+
+impl TrackView<MinimalTrack> for RecentTrack {
+    fn project(&self) -> MinimalTrack {
+        MinimalTrack {
+            artist: self.artist.text.clone(),
+            name: self.name.clone(),
+            timestamp: self.get_timestamp(),
+        }
+    }
+}
+
+impl CsvRow for RecentTrack {
+    fn headers() -> Vec<&'static str> {
+        vec!["artist", "name", "album", "timestamp", "date", "url"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.artist.text.clone(),
+            self.name.clone(),
+            self.album.text.clone(),
+            self.get_timestamp()
+                .map_or_else(String::new, |ts| ts.to_string()),
+            self.date
+                .as_ref()
+                .map_or_else(String::new, |d| d.text.clone()),
+            self.url.clone(),
+        ]
+    }
+}
+
+impl CsvRow for LovedTrack {
+    fn headers() -> Vec<&'static str> {
+        vec!["artist", "name", "timestamp", "date", "url"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.artist.name.clone(),
+            self.name.clone(),
+            self.get_timestamp()
+                .map_or_else(String::new, |ts| ts.to_string()),
+            self.date
+                .as_ref()
+                .map_or_else(String::new, |d| d.text.clone()),
+            self.url.clone(),
+        ]
+    }
+}
+
+impl CsvRow for TopTrack {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "rank",
+            "name",
+            "artist_name",
+            "playcount",
+            "duration",
+            "url",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.attr.rank.clone(),
+            self.name.clone(),
+            self.artist.name.clone(),
+            self.playcount.to_string(),
+            self.duration.to_string(),
+            self.url.clone(),
+        ]
+    }
+}
+
+impl CsvRow for TopArtist {
+    fn headers() -> Vec<&'static str> {
+        vec!["rank", "name", "playcount", "url"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.attr.rank.clone(),
+            self.name.clone(),
+            self.playcount.to_string(),
+            self.url.clone(),
+        ]
+    }
+}
+
+impl CsvRow for TopAlbum {
+    fn headers() -> Vec<&'static str> {
+        vec!["rank", "name", "artist_name", "playcount", "url"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.attr.rank.clone(),
+            self.name.clone(),
+            self.artist.name.clone(),
+            self.playcount.to_string(),
+            self.url.clone(),
+        ]
+    }
+}
+
+impl CsvRow for TrackPlayInfo {
+    fn headers() -> Vec<&'static str> {
+        vec![
+            "name",
+            "artist",
+            "album",
+            "play_count",
+            "image_url",
+            "currently_playing",
+            "date",
+            "url",
+        ]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.artist.clone(),
+            self.album.clone().unwrap_or_default(),
+            self.play_count.to_string(),
+            self.image_url.clone().unwrap_or_default(),
+            self.currently_playing.to_string(),
+            self.date.map_or_else(String::new, |d| d.to_string()),
+            self.url.clone(),
+        ]
+    }
+}
+
+impl CsvRow for MinimalTrack {
+    fn headers() -> Vec<&'static str> {
+        vec!["artist", "name", "timestamp"]
+    }
+
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.artist.clone(),
+            self.name.clone(),
+            self.timestamp.map_or_else(String::new, |ts| ts.to_string()),
+        ]
+    }
+}
+
+/// A directory of saved track files.
+///
+/// Centralizes where files are written and how timestamped filenames are
+/// built, so callers only deal with prefixes and file names.
+#[derive(Debug, Clone)]
+pub struct DataStore {
+    root: PathBuf,
+}
+
+impl Default for DataStore {
+    fn default() -> Self {
+        DataStore::new(DATA_DIR)
+    }
+}
+
+impl DataStore {
+    /// Creates a store rooted at `root`, which is created on the first save.
+    ///
+    /// # Arguments
+    /// * `root` - Directory holding the saved files
+    #[must_use]
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        DataStore { root: root.into() }
+    }
+
+    /// The directory holding the saved files.
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path of a file in the store.
+    ///
+    /// # Arguments
+    /// * `file_name` - Name of the file, relative to the root
+    #[must_use]
+    pub fn path(&self, file_name: impl AsRef<Path>) -> PathBuf {
+        self.root.join(file_name)
+    }
+
+    /// Save data to a new timestamped file, named `<prefix>_<YYYYmmdd_HHMMSS>.<ext>`.
+    ///
+    /// Only JSON and NDJSON are written here; CSV needs a column mapping,
+    /// see `save_rows`.
+    ///
+    /// # Arguments
+    /// * `data` - Data to save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to, or `format` is CSV
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the saved file
+    pub fn save<T: Serialize>(
+        &self,
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String> {
+        let filename = FileHandler::timestamped_filename(&self.root, format, filename_prefix)?;
+
+        match format {
+            FileFormat::Json => FileHandler::save_as_json(data, &filename),
+            FileFormat::Ndjson => FileHandler::save_as_ndjson(data, &filename),
+            FileFormat::Csv => Err(FileHandler::csv_without_rows()),
+        }?;
+
+        Ok(filename)
+    }
+
+    /// Save rows to a new timestamped file in any format, CSV included.
+    ///
+    /// CSV goes through `save_csv`, the other formats through `save`.
+    ///
+    /// # Arguments
+    /// * `data` - Data to save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the saved file
+    pub fn save_rows<T: Serialize + CsvRow>(
+        &self,
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String> {
+        match format {
+            FileFormat::Csv => self.save_csv(data, filename_prefix),
+            _ => self.save(data, format, filename_prefix),
+        }
+    }
+
+    /// Save data to a new timestamped CSV file, with a header row.
+    ///
+    /// # Arguments
+    /// * `data` - Data to save
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the saved file
+    pub fn save_csv<T: CsvRow>(&self, data: &[T], filename_prefix: &str) -> Result<String> {
+        let filename =
+            FileHandler::timestamped_filename(&self.root, &FileFormat::Csv, filename_prefix)?;
+
+        FileHandler::save_as_csv(data, &filename)?;
+
+        Ok(filename)
+    }
+
+    /// Save data with empty string fields omitted.
+    ///
+    /// Large dumps carry many empty `mbid`/`#text` fields; dropping them
+    /// shrinks the output noticeably. Omitted fields deserialize back to
+    /// empty strings, so compact files load into the same types.
+    /// CSV rows always keep every column, so CSV goes through `save_csv` instead.
+    ///
+    /// # Arguments
+    /// * `data` - Data to save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to, or `format` is CSV
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the saved file
+    pub fn save_compact<T: Serialize>(
+        &self,
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String> {
+        if matches!(format, FileFormat::Csv) {
+            return Err(FileHandler::csv_without_rows());
+        }
+
+        self.save(&FileHandler::compact_values(data)?, format, filename_prefix)
+    }
+
+    /// Save timestamped tracks with an extra `played_at_iso` field.
+    ///
+    /// The field holds the scrobble time as an RFC3339 UTC string
+    /// (e.g. `2024-12-04T13:20:00Z`), so consumers don't have to convert
+    /// `date.uts` themselves. It is left out for tracks without a timestamp,
+    /// and written as a trailing column in CSV output.
+    ///
+    /// # Arguments
+    /// * `data` - Tracks to save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the saved file
+    pub fn save_with_iso_dates<T: Serialize + CsvRow + Timestamped>(
+        &self,
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String> {
+        let filename = FileHandler::timestamped_filename(&self.root, format, filename_prefix)?;
+
+        match format {
+            FileFormat::Json => {
+                let values = FileHandler::iso_dated_values(data)?;
+                FileHandler::save_as_json(&values, &filename)
+            }
+            FileFormat::Ndjson => {
+                let values = FileHandler::iso_dated_values(data)?;
+                FileHandler::save_as_ndjson(&values, &filename)
+            }
+            FileFormat::Csv => {
+                let mut writer = Writer::from_path(&filename)?;
+
+                let mut headers = T::headers();
+                headers.push("played_at_iso");
+                writer.write_record(headers)?;
+                for item in data {
+                    let mut row = item.row();
+                    row.push(FileHandler::iso_timestamp(item).unwrap_or_default());
+                    writer.write_record(row)?;
+                }
+
+                writer.flush()
+            }
+        }?;
+
+        Ok(filename)
+    }
+
+    /// Save a projection of the data instead of the full tracks.
+    ///
+    /// The output schema is chosen with `P`, e.g.
+    /// `store.save_projected::<_, MinimalTrack>(&tracks, &format, "recent")`.
+    ///
+    /// # Arguments
+    /// * `data` - Tracks to project and save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the saved file
+    pub fn save_projected<T, P>(
+        &self,
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String>
+    where
+        T: TrackView<P>,
+        P: Serialize + CsvRow,
+    {
+        let projected: Vec<P> = data.iter().map(TrackView::project).collect();
+
+        self.save_rows(&projected, format, filename_prefix)
+    }
+
+    /// Load every item of a JSON or NDJSON file in the store.
+    ///
+    /// # Arguments
+    /// * `file_name` - Name of the file, relative to the root
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be read, has an unsupported
+    ///   extension, or cannot be deserialized
+    ///
+    /// # Returns
+    /// * `Result<Vec<T>>` - The loaded items
+    pub fn load<T: DeserializeOwned>(&self, file_name: impl AsRef<Path>) -> Result<Vec<T>> {
+        let path = self.path(file_name);
+        let reader = BufReader::new(File::open(&path)?);
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(serde_json::from_reader(reader)?),
+            Some(ext)
+                if ext.eq_ignore_ascii_case("ndjson") || ext.eq_ignore_ascii_case("jsonl") =>
+            {
+                reader
+                    .lines()
+                    .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+                    .map(|line| Ok(serde_json::from_str(&line?)?))
+                    .collect()
+            }
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Unsupported file format",
+            )),
+        }
+    }
+
+    /// Append data to a JSON or NDJSON file in the store, see `FileHandler::append`.
+    ///
+    /// # Arguments
+    /// * `data` - Data to append
+    /// * `file_name` - Name of the file, relative to the root
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If an I/O error occurs
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the updated file
+    pub fn append<T: Serialize + for<'de> serde::Deserialize<'de> + Clone>(
+        &self,
+        data: &[T],
+        file_name: impl AsRef<Path>,
+    ) -> Result<String> {
+        FileHandler::append(data, self.path(file_name))
+    }
+
+    /// Append rows to a CSV file in the store, see `FileHandler::append_csv`.
+    ///
+    /// # Arguments
+    /// * `data` - Data to append
+    /// * `file_name` - Name of the file, relative to the root
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If an I/O error occurs
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the updated file
+    pub fn append_csv<T: CsvRow>(&self, data: &[T], file_name: impl AsRef<Path>) -> Result<String> {
+        FileHandler::append_csv(data, self.path(file_name))
+    }
+
+    /// Find the most recently saved file for a prefix.
+    ///
+    /// Files are ordered by the timestamp embedded in their name by `save`.
+    /// Files whose name doesn't follow that pattern are ignored.
+    ///
+    /// # Arguments
+    /// * `filename_prefix` - Prefix the file was saved with
+    ///
+    /// # Returns
+    /// * `Option<PathBuf>` - The newest file, `None` if there is none or the root doesn't exist
+    #[must_use]
+    pub fn latest(&self, filename_prefix: &str) -> Option<PathBuf> {
+        fs::read_dir(&self.root)
+            .ok()?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let timestamp = Self::filename_timestamp(&path, filename_prefix)?;
+                Some((timestamp, path))
+            })
+            .max_by_key(|(timestamp, _)| *timestamp)
+            .map(|(_, path)| path)
+    }
+
+    /// Parse the timestamp of a file saved with `filename_prefix`.
+    fn filename_timestamp(path: &Path, filename_prefix: &str) -> Option<NaiveDateTime> {
+        let extension = path.extension()?.to_str()?;
+        if ![FileFormat::Json, FileFormat::Csv, FileFormat::Ndjson]
+            .iter()
+            .any(|format| format.extension() == extension)
+        {
+            return None;
+        }
+
+        let timestamp = path
+            .file_stem()?
+            .to_str()?
+            .strip_prefix(filename_prefix)?
+            .strip_prefix('_')?;
+
+        NaiveDateTime::parse_from_str(timestamp, FILENAME_TIMESTAMP_FORMAT).ok()
+    }
 }
 
 pub struct FileHandler;
 
 impl FileHandler {
-    /// Save data to a file in the data directory.
+    /// Save data to a JSON or NDJSON file in the data directory.
     ///
     /// # Arguments
     /// * `data` - Data to save
@@ -24,7 +577,7 @@ impl FileHandler {
     /// * `filename_prefix` - Prefix for the filename
     ///
     /// # Errors
-    /// * `std::io::Error` - If the file cannot be opened or written to
+    /// * `std::io::Error` - If the file cannot be opened or written to, or `format` is CSV
     /// * `serde_json::Error` - If the JSON cannot be serialized
     ///
     /// # Returns
@@ -33,41 +586,282 @@ impl FileHandler {
         data: &[T],
         format: &FileFormat,
         filename_prefix: &str,
+    ) -> Result<String> {
+        DataStore::default().save(data, format, filename_prefix)
+    }
+
+    /// Save rows to a file in the data directory in any format, CSV included.
+    ///
+    /// # Arguments
+    /// * `data` - Data to save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Filename of the saved file
+    pub fn save_rows<T: Serialize + CsvRow>(
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String> {
+        DataStore::default().save_rows(data, format, filename_prefix)
+    }
+
+    /// Save data to a CSV file in the data directory, with a header row.
+    ///
+    /// # Arguments
+    /// * `data` - Data to save
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to
+    ///
+    /// # Returns
+    /// * `Result<String>` - Filename of the saved file
+    pub fn save_csv<T: CsvRow>(data: &[T], filename_prefix: &str) -> Result<String> {
+        DataStore::default().save_csv(data, filename_prefix)
+    }
+
+    /// Find the most recently saved file for a prefix in the data directory.
+    ///
+    /// Useful to pick up the previous export, e.g. the newest
+    /// `recent_tracks_*.json` to pass to `update_tracks_file`.
+    /// See `DataStore::latest` for other directories.
+    ///
+    /// # Arguments
+    /// * `filename_prefix` - Prefix the file was saved with
+    ///
+    /// # Returns
+    /// * `Option<PathBuf>` - The newest file, `None` if there is none or the directory is missing
+    #[must_use]
+    pub fn latest(filename_prefix: &str) -> Option<PathBuf> {
+        DataStore::default().latest(filename_prefix)
+    }
+
+    /// Save data with empty string fields omitted in the data directory.
+    ///
+    /// See `DataStore::save_compact` for details and other directories.
+    ///
+    /// # Arguments
+    /// * `data` - Data to save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to, or `format` is CSV
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Filename of the saved file
+    pub fn save_compact<T: Serialize>(
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String> {
+        DataStore::default().save_compact(data, format, filename_prefix)
+    }
+
+    /// Save timestamped tracks with an extra `played_at_iso` field in the data directory.
+    ///
+    /// See `DataStore::save_with_iso_dates` for details and other directories.
+    ///
+    /// # Arguments
+    /// * `data` - Tracks to save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Filename of the saved file
+    pub fn save_with_iso_dates<T: Serialize + CsvRow + Timestamped>(
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String> {
+        DataStore::default().save_with_iso_dates(data, format, filename_prefix)
+    }
+
+    /// Save scrobbles to one JSON file per UTC calendar day, named `YYYY-MM-DD.json`.
+    ///
+    /// Tracks without a timestamp (currently playing) are skipped. A day's
+    /// file is replaced if it already exists, so pass every scrobble of the
+    /// days being written.
+    ///
+    /// # Arguments
+    /// * `tracks` - Scrobbles to save, in any order
+    /// * `dir` - Directory to write the files to, created if needed
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the directory or a file cannot be written
+    ///
+    /// # Returns
+    /// * `Result<Vec<PathBuf>>` - Paths of the written files, oldest day first
+    pub fn save_partitioned_by_day(
+        tracks: &[RecentTrack],
+        dir: impl AsRef<Path>,
+    ) -> Result<Vec<PathBuf>> {
+        Self::save_partitioned_by_day_in(tracks, dir, Tz::UTC)
+    }
+
+    /// Save scrobbles to one JSON file per calendar day in the given timezone,
+    /// named `YYYY-MM-DD.json`.
+    ///
+    /// Tracks without a timestamp (currently playing) are skipped. A day's
+    /// file is replaced if it already exists, so pass every scrobble of the
+    /// days being written.
+    ///
+    /// # Arguments
+    /// * `tracks` - Scrobbles to save, in any order
+    /// * `dir` - Directory to write the files to, created if needed
+    /// * `tz` - Timezone defining day boundaries
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the directory or a file cannot be written
+    ///
+    /// # Returns
+    /// * `Result<Vec<PathBuf>>` - Paths of the written files, oldest day first
+    pub fn save_partitioned_by_day_in(
+        tracks: &[RecentTrack],
+        dir: impl AsRef<Path>,
+        tz: Tz,
+    ) -> Result<Vec<PathBuf>> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut days: BTreeMap<NaiveDate, Vec<&RecentTrack>> = BTreeMap::new();
+        for track in tracks {
+            if let Some(played_at) = track.played_at() {
+                days.entry(played_at.with_timezone(&tz).date_naive())
+                    .or_default()
+                    .push(track);
+            }
+        }
+
+        days.into_iter()
+            .map(|(day, tracks)| {
+                let path = dir.join(format!("{}.json", day.format("%Y-%m-%d")));
+                Self::save_as_json(&tracks, &path)?;
+                Ok(path)
+            })
+            .collect()
+    }
+
+    /// Save a projection of the data in the data directory.
+    ///
+    /// See `DataStore::save_projected` for details and other directories.
+    ///
+    /// # Arguments
+    /// * `data` - Tracks to project and save
+    /// * `format` - File format to save as
+    /// * `filename_prefix` - Prefix for the filename
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be opened or written to
+    /// * `serde_json::Error` - If the JSON cannot be serialized
+    ///
+    /// # Returns
+    /// * `Result<String>` - Filename of the saved file
+    pub fn save_projected<T, P>(
+        data: &[T],
+        format: &FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String>
+    where
+        T: TrackView<P>,
+        P: Serialize + CsvRow,
+    {
+        DataStore::default().save_projected(data, format, filename_prefix)
+    }
+
+    /// Build a timestamped filename in `root`, creating the directory if needed.
+    ///
+    /// # Arguments
+    /// * `root` - Directory the file goes in
+    /// * `format` - File format, used for the extension
+    /// * `filename_prefix` - Prefix for the filename
+    fn timestamped_filename(
+        root: &Path,
+        format: &FileFormat,
+        filename_prefix: &str,
     ) -> Result<String> {
         // Create data directory if it doesn't exist
-        fs::create_dir_all("data")?;
+        fs::create_dir_all(root)?;
+
+        // Generate timestamp
+        let timestamp = Local::now().format(FILENAME_TIMESTAMP_FORMAT);
+
+        // Create filename with timestamp
+        Ok(root
+            .join(format!(
+                "{filename_prefix}_{timestamp}.{}",
+                format.extension()
+            ))
+            .to_string_lossy()
+            .into_owned())
+    }
+
+    /// Error returned when CSV is requested from a method without a column mapping.
+    fn csv_without_rows() -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "CSV output needs a CsvRow mapping, use save_rows, save_csv or append_csv",
+        )
+    }
+
+    /// Serialize items to JSON values with empty string fields removed.
+    ///
+    /// # Arguments
+    /// * `data` - Data to convert
+    fn compact_values<T: Serialize>(data: &[T]) -> Result<Vec<Value>> {
+        data.iter()
+            .map(|item| {
+                let mut value = serde_json::to_value(item)?;
+                Self::strip_empty_strings(&mut value);
+                Ok(value)
+            })
+            .collect()
+    }
 
-        // Generate timestamp
-        let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    /// Serialize items to JSON values carrying a `played_at_iso` field.
+    ///
+    /// # Arguments
+    /// * `data` - Data to convert
+    fn iso_dated_values<T: Serialize + Timestamped>(data: &[T]) -> Result<Vec<Value>> {
+        data.iter()
+            .map(|item| {
+                let mut value = serde_json::to_value(item)?;
+                if let (Value::Object(map), Some(iso)) = (&mut value, Self::iso_timestamp(item)) {
+                    map.insert("played_at_iso".to_string(), Value::String(iso));
+                }
+                Ok(value)
+            })
+            .collect()
+    }
 
-        // Create filename with timestamp
-        let filename = format!(
-            "data/{}_{}.{}",
-            filename_prefix,
-            timestamp,
-            match format {
-                FileFormat::Json => "json",
-                FileFormat::Csv => "csv",
-            }
-        );
+    /// Format an item's timestamp as an RFC3339 UTC string.
+    fn iso_timestamp<T: Timestamped>(item: &T) -> Option<String> {
+        item.get_timestamp()
+            .and_then(|ts| DateTime::from_timestamp(i64::from(ts), 0))
+            .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+    }
 
-        match format {
-            FileFormat::Json => {
-                // Special case: if T is a HashMap with track info
-                if std::any::type_name::<T>()
-                    == std::any::type_name::<HashMap<String, TrackPlayInfo>>()
-                {
-                    if let Some(single_item) = data.first() {
-                        Self::save_single(single_item, &filename)?;
-                        return Ok(filename);
-                    }
-                }
-                Self::save_as_json(data, &filename)
+    /// Recursively remove object fields holding an empty string.
+    fn strip_empty_strings(value: &mut Value) {
+        match value {
+            Value::Object(map) => {
+                map.retain(|_, v| !matches!(v, Value::String(s) if s.is_empty()));
+                map.values_mut().for_each(Self::strip_empty_strings);
             }
-            FileFormat::Csv => Self::save_as_csv(data, &filename),
-        }?;
-
-        Ok(filename)
+            Value::Array(items) => items.iter_mut().for_each(Self::strip_empty_strings),
+            _ => {}
+        }
     }
 
     /// Save data to a JSON file.
@@ -76,7 +870,7 @@ impl FileHandler {
     /// * `data` - Data to save
     /// * `filename` - Filename to save as
     #[allow(dead_code)]
-    fn save_as_json<T: Serialize>(data: &[T], filename: &str) -> Result<()> {
+    fn save_as_json<T: Serialize>(data: &[T], filename: impl AsRef<Path>) -> Result<()> {
         let json = serde_json::to_string_pretty(data)?;
         let mut file = File::create(filename)?;
 
@@ -85,90 +879,344 @@ impl FileHandler {
         Ok(())
     }
 
-    /// Save data to a CSV file.
+    /// Save data to a newline-delimited JSON (NDJSON) file, one item per line.
     ///
     /// # Arguments
     /// * `data` - Data to save
     /// * `filename` - Filename to save as
-    fn save_as_csv<T: Serialize>(data: &[T], filename: &str) -> Result<()> {
-        let mut writer = Writer::from_path(filename)?;
+    fn save_as_ndjson<T: Serialize>(data: &[T], filename: impl AsRef<Path>) -> Result<()> {
+        let mut writer = std::io::BufWriter::new(File::create(filename)?);
 
         for item in data {
-            writer.serialize(item)?;
+            serde_json::to_writer(&mut writer, item)?;
+            writer.write_all(b"\n")?;
         }
 
         writer.flush()?;
         Ok(())
     }
 
-    /// Append data to an existing file.
+    /// Save data to a CSV file, with a header row.
     ///
     /// # Arguments
-    /// * `data` - Data to append
-    /// * `file_path` - Path to the file to append to
-    ///
-    /// # Returns
-    /// * `Result<String>` - Path of the updated file
+    /// * `data` - Data to save
+    /// * `filename` - Filename to save as
+    fn save_as_csv<T: CsvRow>(data: &[T], filename: impl AsRef<Path>) -> Result<()> {
+        let mut writer = Writer::from_path(filename)?;
+
+        writer.write_record(T::headers())?;
+        for item in data {
+            writer.write_record(item.row())?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Append data to a JSON or NDJSON file.
     ///
-    /// Append data to an existing file.
+    /// The file is created if missing. The path does not need to be valid
+    /// UTF-8. CSV files go through `append_csv`, which knows their columns.
     ///
     /// # Arguments
     /// * `data` - Data to append
     /// * `file_path` - Path to the file to append to
     ///
     /// # Errors
-    /// * `std::io::Error` - If an I/O error occurs
+    /// * `std::io::Error` - If an I/O error occurs, or the file is not JSON or NDJSON
     ///
     /// # Returns
-    /// * `Result<String>` - Path of the updated file
+    /// * `Result<String>` - Path of the updated file, lossily converted to UTF-8
     #[allow(dead_code)]
     pub fn append<T: Serialize + for<'de> serde::Deserialize<'de> + Clone>(
         data: &[T],
-        file_path: &str,
+        file_path: impl AsRef<Path>,
     ) -> Result<String> {
-        // Determine file format from extension
-        let format = if std::path::Path::new(file_path)
-            .extension()
-            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
-        {
-            FileFormat::Json
-        } else if std::path::Path::new(file_path)
-            .extension()
-            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
-        {
-            FileFormat::Csv
-        } else {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Unsupported file format",
-            ));
-        };
+        let file_path = file_path.as_ref();
 
-        match format {
-            FileFormat::Json => {
+        match FileFormat::from_path(file_path) {
+            Some(FileFormat::Json) => {
                 // For JSON, we need to read the existing data, combine it, and write it back
-                let file = File::open(file_path)?;
-                let mut existing_data: Vec<T> = serde_json::from_reader(file)?;
+                let mut existing_data: Vec<T> = match File::open(file_path) {
+                    Ok(file) => serde_json::from_reader(file)?,
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                    Err(e) => return Err(e),
+                };
 
                 existing_data.extend(data.iter().cloned());
 
                 Self::save_as_json(&existing_data, file_path)?;
             }
-            FileFormat::Csv => {
-                // For CSV, we can simply append to the file
-                let mut writer =
-                    Writer::from_writer(OpenOptions::new().append(true).open(file_path)?);
+            Some(FileFormat::Ndjson) => {
+                // Each item is one line, so new lines go at the end without touching the rest
+                let mut file = OpenOptions::new()
+                    .read(true)
+                    .create(true)
+                    .append(true)
+                    .open(file_path)?;
 
+                let missing_final_newline = Self::missing_final_newline(&mut file)?;
+
+                let mut writer = std::io::BufWriter::new(file);
+                if missing_final_newline {
+                    writer.write_all(b"\n")?;
+                }
                 for item in data {
-                    writer.serialize(item)?;
+                    serde_json::to_writer(&mut writer, item)?;
+                    writer.write_all(b"\n")?;
                 }
                 writer.flush()?;
             }
+            Some(FileFormat::Csv) => return Err(Self::csv_without_rows()),
+            None => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Unsupported file format",
+                ))
+            }
+        }
+
+        Ok(file_path.to_string_lossy().into_owned())
+    }
+
+    /// Append rows to a CSV file.
+    ///
+    /// The file is created if missing, and the header is only written when
+    /// the file is empty. The path does not need to be valid UTF-8.
+    ///
+    /// # Arguments
+    /// * `data` - Data to append
+    /// * `file_path` - Path to the file to append to
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If an I/O error occurs
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the updated file, lossily converted to UTF-8
+    pub fn append_csv<T: CsvRow>(data: &[T], file_path: impl AsRef<Path>) -> Result<String> {
+        let file_path = file_path.as_ref();
+
+        // Rows go at the end, the header only when the file has none yet
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file_path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = WriterBuilder::new().has_headers(false).from_writer(file);
+
+        if is_empty {
+            writer.write_record(T::headers())?;
+        }
+        for item in data {
+            writer.write_record(item.row())?;
+        }
+        writer.flush()?;
+
+        Ok(file_path.to_string_lossy().into_owned())
+    }
+
+    /// Replace a stored now-playing entry with its finalized scrobble.
+    ///
+    /// If the last entry of a JSON or NDJSON file is a track without a date
+    /// and one of `new_tracks` is a scrobble of the same artist and track,
+    /// that entry is overwritten in place by the (oldest such) scrobble.
+    /// For NDJSON only the last line is read and rewritten. CSV files and
+    /// files of other track types are left untouched.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to reconcile
+    /// * `new_tracks` - Newly fetched tracks, about to be appended
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be read or written
+    ///
+    /// # Returns
+    /// * `Result<Option<usize>>` - Index in `new_tracks` of the scrobble written
+    ///   to the file, which should not be appended again
+    pub fn reconcile_now_playing(
+        new_tracks: &[RecentTrack],
+        file_path: impl AsRef<Path>,
+    ) -> Result<Option<usize>> {
+        let file_path = file_path.as_ref();
+
+        match FileFormat::from_path(file_path) {
+            Some(FileFormat::Json) => {
+                let mut entries: Vec<Value> =
+                    serde_json::from_reader(BufReader::new(File::open(file_path)?))?;
+
+                let Some(index) = entries
+                    .last()
+                    .and_then(|entry| serde_json::from_value::<RecentTrack>(entry.clone()).ok())
+                    .and_then(|pending| Self::finalized_index(&pending, new_tracks))
+                else {
+                    return Ok(None);
+                };
+
+                if let Some(last) = entries.last_mut() {
+                    *last = serde_json::to_value(&new_tracks[index])?;
+                }
+                Self::save_as_json(&entries, file_path)?;
+
+                Ok(Some(index))
+            }
+            Some(FileFormat::Ndjson) => {
+                // Only the last line can be pending, so it's the only one read and rewritten
+                let mut file = OpenOptions::new().read(true).write(true).open(file_path)?;
+
+                let Some((offset, line)) = Self::read_last_line(&mut file)? else {
+                    return Ok(None);
+                };
+                let Some(index) = serde_json::from_str::<RecentTrack>(&line)
+                    .ok()
+                    .and_then(|pending| Self::finalized_index(&pending, new_tracks))
+                else {
+                    return Ok(None);
+                };
+
+                let mut scrobble = serde_json::to_vec(&new_tracks[index])?;
+                scrobble.push(b'\n');
+                file.set_len(offset)?;
+                file.seek(std::io::SeekFrom::Start(offset))?;
+                file.write_all(&scrobble)?;
+
+                Ok(Some(index))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Index of the oldest of `new_tracks` finalizing `pending`, if it is a now-playing entry.
+    fn finalized_index(pending: &RecentTrack, new_tracks: &[RecentTrack]) -> Option<usize> {
+        if pending.date.is_some() {
+            return None;
+        }
+
+        new_tracks
+            .iter()
+            .enumerate()
+            .filter(|(_, track)| {
+                track.date.is_some()
+                    && track.artist.text == pending.artist.text
+                    && track.name == pending.name
+            })
+            .min_by_key(|(_, track)| track.get_timestamp())
+            .map(|(index, _)| index)
+    }
+
+    /// Read the last non-blank line of a file, without reading the rest of it.
+    ///
+    /// # Returns
+    /// * `Result<Option<(u64, String)>>` - Offset where the line starts and its
+    ///   contents, `None` if the file is blank
+    fn read_last_line(file: &mut File) -> Result<Option<(u64, String)>> {
+        const CHUNK: u64 = 4096;
+
+        let mut start = file.metadata()?.len();
+        let mut tail: Vec<u8> = Vec::new();
+
+        loop {
+            let end = tail
+                .iter()
+                .rposition(|byte| !byte.is_ascii_whitespace())
+                .map_or(0, |last| last + 1);
+            let line_start = tail[..end]
+                .iter()
+                .rposition(|&byte| byte == b'\n')
+                .map(|newline| newline + 1);
+
+            if end > 0 && (line_start.is_some() || start == 0) {
+                let line_start = line_start.unwrap_or(0);
+                let line = String::from_utf8_lossy(&tail[line_start..end]).into_owned();
+                return Ok(Some((start + line_start as u64, line)));
+            }
+            if start == 0 {
+                return Ok(None);
+            }
+
+            let read = CHUNK.min(start);
+            start -= read;
+            let mut chunk = vec![0; usize::try_from(read).unwrap_or(usize::MAX)];
+            file.seek(std::io::SeekFrom::Start(start))?;
+            file.read_exact(&mut chunk)?;
+            chunk.extend_from_slice(&tail);
+            tail = chunk;
+        }
+    }
+
+    /// Whether a non-empty file lacks a trailing newline, reading only its last byte.
+    fn missing_final_newline(file: &mut File) -> Result<bool> {
+        if file.metadata()?.len() == 0 {
+            return Ok(false);
+        }
+
+        let mut last_byte = [0; 1];
+        file.seek(std::io::SeekFrom::End(-1))?;
+        file.read_exact(&mut last_byte)?;
+
+        Ok(last_byte[0] != b'\n')
+    }
+
+    /// Export scrobbles in the tab-separated format accepted by scrobble importers.
+    ///
+    /// Each line holds `artist`, `track`, `album` and the UNIX timestamp of the
+    /// scrobble, without a header row. Tracks without a timestamp (currently
+    /// playing) are skipped.
+    ///
+    /// # Arguments
+    /// * `tracks` - Scrobbles to export
+    /// * `file_path` - Path to the TSV file to create (overwriting if it exists)
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the file cannot be created or written to
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path of the exported file
+    pub fn export_scrobble_tsv(tracks: &[RecentTrack], file_path: &str) -> Result<String> {
+        let mut writer = WriterBuilder::new()
+            .delimiter(b'\t')
+            .has_headers(false)
+            .from_path(file_path)?;
+
+        for track in tracks {
+            if let Some(timestamp) = track.get_timestamp() {
+                writer.write_record([
+                    track.artist.text.as_str(),
+                    track.name.as_str(),
+                    track.album.text.as_str(),
+                    &timestamp.to_string(),
+                ])?;
+            }
         }
 
+        writer.flush()?;
         Ok(file_path.to_string())
     }
 
+    /// Replace a file's contents atomically.
+    ///
+    /// The contents are written to a temporary file next to `file_path`, which
+    /// is then renamed over it, so readers never see a partially written file
+    /// and a failed write leaves the previous contents intact.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to replace
+    /// * `contents` - New contents of the file
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the temporary file cannot be written or renamed
+    pub fn write_atomic(file_path: &str, contents: &[u8]) -> Result<()> {
+        let tmp_path = format!("{file_path}.tmp");
+
+        if let Err(e) =
+            fs::write(&tmp_path, contents).and_then(|()| fs::rename(&tmp_path, file_path))
+        {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
     /// Save a single item to a JSON file
     ///
     /// # Errors
@@ -185,3 +1233,614 @@ impl FileHandler {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_recent_track_at;
+    use crate::types::RecentTrack;
+
+    const SCROBBLED_AT: u32 = 1_733_318_400;
+
+    #[test]
+    fn test_data_store_save_load_append() {
+        let root = std::env::temp_dir().join("async_lastfm_test_data_store");
+        let store = DataStore::new(&root);
+
+        let path = store
+            .save(
+                &[create_recent_track_at("Artist1", "Song1", SCROBBLED_AT)],
+                &FileFormat::Ndjson,
+                "recent",
+            )
+            .unwrap();
+        let file_name = Path::new(&path).file_name().unwrap().to_owned();
+        store
+            .append(
+                &[create_recent_track_at("Artist2", "Song2", SCROBBLED_AT)],
+                &file_name,
+            )
+            .unwrap();
+        let loaded: Vec<RecentTrack> = store.load(&file_name).unwrap();
+        let latest = store.latest("recent");
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(Path::new(&path).starts_with(&root));
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].artist.text, "Artist2");
+        assert_eq!(latest, Some(PathBuf::from(&path)));
+    }
+
+    #[test]
+    fn test_data_store_saves_types_without_csv_rows() {
+        let root = std::env::temp_dir().join("async_lastfm_test_data_store_json_only");
+        let store = DataStore::new(&root);
+        let data = [serde_json::json!({"name": "Artist1", "playcount": 3})];
+
+        let path = store.save(&data, &FileFormat::Json, "artists").unwrap();
+        let loaded: Vec<Value> = store.load(Path::new(&path).file_name().unwrap()).unwrap();
+        let csv = store.save(&data, &FileFormat::Csv, "artists");
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(loaded, data);
+        assert_eq!(csv.unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_data_store_save_rows_writes_every_format() {
+        let root = std::env::temp_dir().join("async_lastfm_test_data_store_save_rows");
+        let store = DataStore::new(&root);
+        let tracks = [create_recent_track_at("Artist1", "Song1", SCROBBLED_AT)];
+
+        let csv = store
+            .save_rows(&tracks, &FileFormat::Csv, "recent")
+            .unwrap();
+        let csv = fs::read_to_string(&csv).unwrap();
+        let json = store
+            .save_rows(&tracks, &FileFormat::Json, "recent")
+            .unwrap();
+        let loaded: Vec<RecentTrack> = store.load(Path::new(&json).file_name().unwrap()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let csv_lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(csv_lines.len(), 2);
+        assert_eq!(csv_lines[0], RecentTrack::headers().join(","));
+        assert_eq!(loaded.len(), 1);
+    }
+
+    #[test]
+    fn test_data_store_latest_picks_newest_timestamp() {
+        let root = std::env::temp_dir().join("async_lastfm_test_data_store_latest");
+        fs::create_dir_all(&root).unwrap();
+        for name in [
+            "recent_20240101_120000.json",
+            "recent_20241231_080000.ndjson",
+            "recent_20240601_000000.csv",
+            "recent_not_a_date.json",
+            "recent_20991231_000000.txt",
+            "recently_20991231_000000.json",
+        ] {
+            fs::write(root.join(name), "[]").unwrap();
+        }
+
+        let store = DataStore::new(&root);
+        let latest = store.latest("recent");
+        let missing = store.latest("loved");
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(latest, Some(root.join("recent_20241231_080000.ndjson")));
+        assert_eq!(missing, None);
+        assert_eq!(DataStore::new(&root).latest("recent"), None);
+    }
+
+    #[test]
+    fn test_latest_finds_newest_file_of_prefix() {
+        let root = std::env::temp_dir().join("async_lastfm_test_latest");
+        let prefix = "top_tracks";
+        let names = [
+            format!("{prefix}_20230105_093000.json"),
+            format!("{prefix}_20240310_221500.json"),
+            format!("{prefix}_20240310_080000.csv"),
+            format!("{prefix}_2024-03-11.json"),
+        ];
+        fs::create_dir_all(&root).unwrap();
+        for name in &names {
+            fs::write(root.join(name), "[]").unwrap();
+        }
+
+        let store = DataStore::new(&root);
+        let latest = store.latest(prefix);
+        let missing = store.latest("no_such_prefix");
+        fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(latest, Some(root.join(&names[1])));
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn test_save_partitioned_by_day() {
+        let at = |name: &str, uts: u32| {
+            let mut track = create_recent_track_at("Artist1", name, SCROBBLED_AT);
+            track.date.as_mut().unwrap().uts = uts;
+            track
+        };
+        let mut now_playing = create_recent_track_at("Artist1", "Now Playing", SCROBBLED_AT);
+        now_playing.date = None;
+        // 2024-12-04 13:20 UTC, 2024-12-04 23:59 UTC and 2024-12-05 00:01 UTC
+        let tracks = vec![
+            at("Song1", 1_733_318_400),
+            now_playing,
+            at("Song3", 1_733_356_860),
+            at("Song2", 1_733_356_740),
+        ];
+
+        let dir = std::env::temp_dir().join("async_lastfm_test_partitioned");
+        let paths = FileHandler::save_partitioned_by_day(&tracks, &dir).unwrap();
+        let store = DataStore::new(&dir);
+        let first: Vec<RecentTrack> = store.load("2024-12-04.json").unwrap();
+        let second: Vec<RecentTrack> = store.load("2024-12-05.json").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            paths,
+            vec![dir.join("2024-12-04.json"), dir.join("2024-12-05.json")]
+        );
+        let names = |tracks: &[RecentTrack]| -> Vec<String> {
+            tracks.iter().map(|track| track.name.clone()).collect()
+        };
+        assert_eq!(names(&first), vec!["Song1", "Song2"]);
+        assert_eq!(names(&second), vec!["Song3"]);
+    }
+
+    #[test]
+    fn test_save_partitioned_by_day_in_timezone() {
+        let mut evening = create_recent_track_at("Artist1", "Song2", SCROBBLED_AT);
+        // 2024-12-05 01:00 UTC, still the evening of 2024-12-04 in New York
+        evening.date.as_mut().unwrap().uts = 1_733_360_400;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", SCROBBLED_AT),
+            evening,
+        ];
+
+        let dir = std::env::temp_dir().join("async_lastfm_test_partitioned_tz");
+        let paths =
+            FileHandler::save_partitioned_by_day_in(&tracks, &dir, chrono_tz::America::New_York)
+                .unwrap();
+        let saved: Vec<RecentTrack> = DataStore::new(&dir).load("2024-12-04.json").unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(paths, vec![dir.join("2024-12-04.json")]);
+        assert_eq!(saved.len(), 2);
+    }
+
+    #[test]
+    fn test_compact_values_drop_empty_strings() {
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", SCROBBLED_AT),
+            create_recent_track_at("Artist2", "Song2", SCROBBLED_AT),
+        ];
+
+        let full = serde_json::to_string(&tracks).unwrap();
+        let compact =
+            serde_json::to_string(&FileHandler::compact_values(&tracks).unwrap()).unwrap();
+
+        assert!(compact.len() < full.len());
+        assert!(!compact.contains("\"mbid\""));
+        assert!(!compact.contains("\"url\""));
+        assert!(compact.contains("\"Artist1\""));
+
+        let reloaded: Vec<RecentTrack> = serde_json::from_str(&compact).unwrap();
+        assert_eq!(reloaded.len(), 2);
+        assert_eq!(reloaded[0].artist.text, "Artist1");
+        assert_eq!(reloaded[0].mbid, "");
+        assert_eq!(reloaded[1].date.as_ref().unwrap().uts, 1_733_318_400);
+    }
+
+    #[test]
+    fn test_data_store_compact_and_iso_saves_use_root() {
+        let root = std::env::temp_dir().join("async_lastfm_test_store_variants");
+        let store = DataStore::new(&root);
+        let tracks = vec![create_recent_track_at("Artist1", "Song1", SCROBBLED_AT)];
+
+        let compact = store
+            .save_compact(&tracks, &FileFormat::Ndjson, "compact")
+            .unwrap();
+        let iso = store
+            .save_with_iso_dates(&tracks, &FileFormat::Csv, "iso")
+            .unwrap();
+        let iso_header = fs::read_to_string(&iso)
+            .unwrap()
+            .lines()
+            .next()
+            .map(str::to_string);
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(Path::new(&compact).starts_with(&root));
+        assert!(Path::new(&iso).starts_with(&root));
+        assert_eq!(
+            iso_header.as_deref(),
+            Some("artist,name,album,timestamp,date,url,played_at_iso")
+        );
+    }
+
+    #[test]
+    fn test_iso_dated_values_match_uts() {
+        let mut now_playing = create_recent_track_at("Artist2", "Song2", SCROBBLED_AT);
+        now_playing.date = None;
+        let tracks = vec![
+            create_recent_track_at("Artist1", "Song1", SCROBBLED_AT),
+            now_playing,
+        ];
+
+        let values = FileHandler::iso_dated_values(&tracks).unwrap();
+
+        assert_eq!(values[0]["played_at_iso"], "2024-12-04T13:20:00Z");
+        assert_eq!(
+            DateTime::parse_from_rfc3339(values[0]["played_at_iso"].as_str().unwrap())
+                .unwrap()
+                .timestamp(),
+            i64::from(tracks[0].get_timestamp().unwrap())
+        );
+        assert!(values[1].get("played_at_iso").is_none());
+
+        let reloaded: Vec<RecentTrack> = serde_json::from_value(Value::Array(values)).unwrap();
+        assert_eq!(reloaded[0].date.as_ref().unwrap().uts, 1_733_318_400);
+    }
+
+    fn csv_lines<T: CsvRow>(data: &[T], name: &str) -> Vec<String> {
+        let path = std::env::temp_dir().join(name);
+        FileHandler::save_as_csv(data, path.to_str().unwrap()).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        content.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_save_projected_minimal_track() {
+        let root = std::env::temp_dir().join("async_lastfm_test_projected");
+        let store = DataStore::new(&root);
+        let tracks = vec![create_recent_track_at("Artist1", "Song1", SCROBBLED_AT)];
+
+        let path = store
+            .save_projected::<_, MinimalTrack>(&tracks, &FileFormat::Json, "projected")
+            .unwrap();
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        assert!(Path::new(&path).starts_with(&root));
+        assert_eq!(
+            saved,
+            serde_json::json!([{
+                "artist": "Artist1",
+                "name": "Song1",
+                "timestamp": 1_733_318_400
+            }])
+        );
+    }
+
+    #[test]
+    fn test_append_csv_writes_header_once() {
+        let path = std::env::temp_dir().join("async_lastfm_test_append.csv");
+        let _ = fs::remove_file(&path);
+
+        FileHandler::append_csv(
+            &[create_recent_track_at("Artist1", "Song1", SCROBBLED_AT)],
+            &path,
+        )
+        .unwrap();
+        FileHandler::append_csv(
+            &[create_recent_track_at("Artist2", "Song2", SCROBBLED_AT)],
+            &path,
+        )
+        .unwrap();
+        FileHandler::append_csv(
+            &[create_recent_track_at("Artist3", "Song3", SCROBBLED_AT)],
+            &path,
+        )
+        .unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let rejected = FileHandler::append(
+            &[create_recent_track_at("Artist4", "Song4", SCROBBLED_AT)],
+            &path,
+        );
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            rejected.unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], RecentTrack::headers().join(","));
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with("artist,"))
+                .count(),
+            1
+        );
+        assert!(lines[3].starts_with("Artist3,Song3,"));
+    }
+
+    #[test]
+    fn test_append_ndjson_only_adds_lines() {
+        let path = std::env::temp_dir().join("async_lastfm_test_append.ndjson");
+        FileHandler::save_as_ndjson(
+            &[
+                create_recent_track_at("Artist1", "Song1", SCROBBLED_AT),
+                create_recent_track_at("Artist2", "Song2", SCROBBLED_AT),
+            ],
+            path.to_str().unwrap(),
+        )
+        .unwrap();
+        let before = fs::read(&path).unwrap();
+
+        FileHandler::append(
+            &[create_recent_track_at("Artist3", "Song3", SCROBBLED_AT)],
+            &path,
+        )
+        .unwrap();
+        let after = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(after.starts_with(&before));
+        let lines: Vec<&str> = std::str::from_utf8(&after).unwrap().lines().collect();
+        assert_eq!(lines.len(), 3);
+        let appended: RecentTrack = serde_json::from_str(lines[2]).unwrap();
+        assert_eq!(appended.name, "Song3");
+    }
+
+    #[test]
+    fn test_reconcile_now_playing_replaces_pending_entry() {
+        let mut now_playing = create_recent_track_at("Artist2", "Song2", SCROBBLED_AT);
+        now_playing.date = None;
+        let stored = vec![
+            create_recent_track_at("Artist1", "Song1", SCROBBLED_AT),
+            now_playing,
+        ];
+
+        let mut scrobble = create_recent_track_at("Artist2", "Song2", SCROBBLED_AT);
+        scrobble.date.as_mut().unwrap().uts = 1_733_318_700;
+        let mut newer = create_recent_track_at("Artist3", "Song3", SCROBBLED_AT);
+        newer.date.as_mut().unwrap().uts = 1_733_319_000;
+        let fetched = vec![newer, scrobble];
+
+        for name in [
+            "async_lastfm_test_reconcile.json",
+            "async_lastfm_test_reconcile.ndjson",
+        ] {
+            let path = std::env::temp_dir().join(name);
+            if name.ends_with(".ndjson") {
+                FileHandler::save_as_ndjson(&stored, &path).unwrap();
+            } else {
+                FileHandler::save_as_json(&stored, &path).unwrap();
+            }
+
+            let index = FileHandler::reconcile_now_playing(&fetched, &path).unwrap();
+            let again = FileHandler::reconcile_now_playing(&fetched, &path).unwrap();
+            let saved: Vec<RecentTrack> = DataStore::new(std::env::temp_dir()).load(name).unwrap();
+            fs::remove_file(&path).unwrap();
+
+            assert_eq!(index, Some(1));
+            assert_eq!(again, None);
+            assert_eq!(saved.len(), 2);
+            assert_eq!(saved[1].name, "Song2");
+            assert_eq!(saved[1].get_timestamp(), Some(1_733_318_700));
+        }
+    }
+
+    #[test]
+    fn test_reconcile_now_playing_only_rewrites_last_ndjson_line() {
+        let mut stored: Vec<RecentTrack> = (0..200)
+            .map(|i| create_recent_track_at("Artist1", &format!("Song{i}"), SCROBBLED_AT))
+            .collect();
+        let mut now_playing = create_recent_track_at("Artist2", "Pending", SCROBBLED_AT);
+        now_playing.date = None;
+        stored.push(now_playing);
+        let path = std::env::temp_dir().join("async_lastfm_test_reconcile_tail.ndjson");
+        FileHandler::save_as_ndjson(&stored, &path).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"\n\n")
+            .unwrap();
+        let before = fs::read_to_string(&path).unwrap();
+        let kept = &before[..before.trim_end().rfind('\n').unwrap() + 1];
+
+        let scrobble = create_recent_track_at("Artist2", "Pending", SCROBBLED_AT);
+        let index = FileHandler::reconcile_now_playing(&[scrobble], &path).unwrap();
+        let after = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(index, Some(0));
+        assert!(after.starts_with(kept));
+        let last: RecentTrack = serde_json::from_str(&after[kept.len()..]).unwrap();
+        assert_eq!(last.name, "Pending");
+        assert_eq!(last.get_timestamp(), Some(1_733_318_400));
+        assert_eq!(after.lines().count(), 201);
+    }
+
+    #[test]
+    fn test_append_jsonl_without_final_newline() {
+        let path = std::env::temp_dir().join("async_lastfm_test_append.jsonl");
+        let first =
+            serde_json::to_string(&create_recent_track_at("Artist1", "Song1", SCROBBLED_AT))
+                .unwrap();
+        fs::write(&path, &first).unwrap();
+
+        FileHandler::append(
+            &[create_recent_track_at("Artist2", "Song2", SCROBBLED_AT)],
+            &path,
+        )
+        .unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], first);
+    }
+
+    #[test]
+    fn test_export_scrobble_tsv() {
+        let mut scrobble = create_recent_track_at("Artist1", "Song1", SCROBBLED_AT);
+        scrobble.album.text = "Album1".to_string();
+        let mut now_playing = create_recent_track_at("Artist2", "Song2", SCROBBLED_AT);
+        now_playing.date = None;
+
+        let path = std::env::temp_dir().join("async_lastfm_test_scrobbles.tsv");
+        let path = path.to_str().unwrap();
+        FileHandler::export_scrobble_tsv(&[now_playing, scrobble], path).unwrap();
+        let content = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(content, "Artist1\tSong1\tAlbum1\t1733318400\n");
+    }
+
+    #[test]
+    fn test_recent_track_csv_row() {
+        let lines = csv_lines(
+            &[create_recent_track_at("Artist1", "Song1", SCROBBLED_AT)],
+            "async_lastfm_test_recent.csv",
+        );
+
+        assert_eq!(lines[0], "artist,name,album,timestamp,date,url");
+        assert_eq!(lines[1], "Artist1,Song1,,1733318400,,");
+    }
+
+    #[test]
+    fn test_loved_track_csv_row() {
+        use crate::types::{BaseObject, Date, Streamable};
+
+        let track = LovedTrack {
+            artist: BaseObject {
+                mbid: String::new(),
+                url: String::new(),
+                name: "Artist1".to_string(),
+            },
+            date: Some(Date {
+                uts: 1_733_318_400,
+                text: "04 Dec 2024, 13:20".to_string(),
+            }),
+            image: Vec::new(),
+            streamable: Streamable {
+                fulltrack: String::new(),
+                text: String::new(),
+            },
+            name: "Song1".to_string(),
+            mbid: String::new(),
+            url: "https://www.last.fm/music/Artist1/_/Song1".to_string(),
+        };
+
+        let lines = csv_lines(&[track], "async_lastfm_test_loved.csv");
+
+        assert_eq!(lines[0], "artist,name,timestamp,date,url");
+        assert_eq!(
+            lines[1],
+            "Artist1,Song1,1733318400,\"04 Dec 2024, 13:20\",https://www.last.fm/music/Artist1/_/Song1"
+        );
+    }
+
+    #[test]
+    fn test_top_track_csv_row() {
+        use crate::types::{BaseObject, RankAttr, Streamable};
+
+        let track = TopTrack {
+            streamable: Streamable {
+                fulltrack: String::new(),
+                text: String::new(),
+            },
+            mbid: String::new(),
+            name: "Song1".to_string(),
+            image: Vec::new(),
+            artist: BaseObject {
+                mbid: String::new(),
+                url: String::new(),
+                name: "Artist".to_string(),
+            },
+            url: "https://www.last.fm/music/Artist/_/Song1".to_string(),
+            duration: 200,
+            attr: RankAttr {
+                rank: "1".to_string(),
+            },
+            playcount: 10,
+        };
+
+        let lines = csv_lines(&[track], "async_lastfm_test_top.csv");
+
+        assert_eq!(lines[0], "rank,name,artist_name,playcount,duration,url");
+        assert_eq!(
+            lines[1],
+            "1,Song1,Artist,10,200,https://www.last.fm/music/Artist/_/Song1"
+        );
+    }
+
+    #[test]
+    fn test_track_play_info_csv_row() {
+        let info = TrackPlayInfo {
+            name: "Song1".to_string(),
+            play_count: 3,
+            artist: "Artist1".to_string(),
+            album: Some("Album1".to_string()),
+            image_url: None,
+            currently_playing: false,
+            date: Some(1_733_318_400),
+            url: String::new(),
+        };
+
+        let lines = csv_lines(&[info], "async_lastfm_test_play_info.csv");
+
+        assert_eq!(
+            lines[0],
+            "name,artist,album,play_count,image_url,currently_playing,date,url"
+        );
+        assert_eq!(lines[1], "Song1,Artist1,Album1,3,,false,1733318400,");
+    }
+
+    #[test]
+    fn test_top_artist_and_album_csv_rows() {
+        use crate::types::{BaseObject, RankAttr};
+
+        let artist = TopArtist {
+            mbid: String::new(),
+            name: "Artist1".to_string(),
+            url: "https://www.last.fm/music/Artist1".to_string(),
+            image: Vec::new(),
+            attr: RankAttr {
+                rank: "1".to_string(),
+            },
+            playcount: 42,
+        };
+        let album = TopAlbum {
+            artist: BaseObject {
+                mbid: String::new(),
+                url: String::new(),
+                name: "Artist1".to_string(),
+            },
+            mbid: String::new(),
+            name: "Album1".to_string(),
+            url: "https://www.last.fm/music/Artist1/Album1".to_string(),
+            image: Vec::new(),
+            attr: RankAttr {
+                rank: "2".to_string(),
+            },
+            playcount: 7,
+        };
+
+        let artist_lines = csv_lines(&[artist], "async_lastfm_test_top_artist.csv");
+        let album_lines = csv_lines(&[album], "async_lastfm_test_top_album.csv");
+
+        assert_eq!(artist_lines[0], "rank,name,playcount,url");
+        assert_eq!(
+            artist_lines[1],
+            "1,Artist1,42,https://www.last.fm/music/Artist1"
+        );
+        assert_eq!(album_lines[0], "rank,name,artist_name,playcount,url");
+        assert_eq!(
+            album_lines[1],
+            "2,Album1,Artist1,7,https://www.last.fm/music/Artist1/Album1"
+        );
+    }
+}
@@ -1,26 +1,76 @@
 use crate::analytics::AnalysisHandler;
-use crate::config;
+use crate::cache::LruCache;
+use crate::config::{get_required_env_var, Config};
 use crate::error::{LastFmError, LastFmErrorResponse, Result};
-use crate::file_handler::{FileFormat, FileHandler};
+use crate::file_handler::{DataStore, FileFormat, FileHandler};
+use crate::rate_limiter::RateLimiter;
+use crate::store::TrackStore;
 use crate::types::{
-    ApiRecentTrack, LovedTrack, RecentTrack, Timestamped, TopTrack, UserLovedTracks,
-    UserRecentTracks, UserTopTracks,
+    best_image, sort_by_timestamp, ApiRecentTrack, ArtistInfoResponse, ImageSize, LovedTrack,
+    RecentTrack, Timestamped, TopAlbum, TopArtist, TopTrack, TrackInfo, TrackInfoResponse,
+    UserArtistTracks, UserLovedTracks, UserRecentTracks, UserTopAlbums, UserTopArtists,
+    UserTopTracks, UserWeeklyAlbumChart, UserWeeklyArtistChart, UserWeeklyChartList,
+    UserWeeklyTrackChart, WeeklyAlbum, WeeklyArtist, WeeklyTrack,
 };
-use crate::url_builder::{QueryParams, Url};
+use crate::url_builder::{redact_params, ParamBuilder, QueryParams, Url, REDACTED};
 
+pub use crate::types::TrackPlayInfo;
+
+use chrono::{NaiveDate, Utc};
 use futures::future::join_all;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
 use std::path::Path;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Default Last.fm API endpoint
+pub const BASE_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+
+const DEFAULT_USER_AGENT: &str = concat!("async_lastfm/", env!("CARGO_PKG_VERSION"));
+
+const DEFAULT_TRACK_INFO_CACHE_SIZE: usize = 512;
+
+/// Maximum number of tracks Last.fm returns per page
+pub const API_MAX_LIMIT: u32 = 1000;
+
+/// Number of pages fetched concurrently in each chunk
+pub const CHUNK_MULTIPLIER: u32 = 5;
+
+/// Span of each request when `update_tracks_file` or `update_tracks_store` backfills a large gap
+pub const BACKFILL_WINDOW_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Longest delay between `watch_now_playing` polls after failures, as a multiple of the interval
+const WATCH_MAX_BACKOFF: u32 = 8;
 
-const BASE_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+/// Pace of `love_tracks` when no rate limit is configured on the handler
+const LOVE_REQUESTS_PER_SECOND: u32 = 5;
 
-const API_MAX_LIMIT: u32 = 1000;
+/// Number of response body bytes logged per request in verbose mode.
+const VERBOSE_BODY_BYTES: usize = 512;
 
-const CHUNK_MULTIPLIER: u32 = 5;
-const CHUNK_SIZE: u32 = API_MAX_LIMIT * CHUNK_MULTIPLIER;
+/// Retries of a failed request before giving up, unless configured otherwise.
+const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Delay before the first retry, doubled for each later one.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// HTTP statuses worth retrying: rate limiting and transient server failures.
+const RETRYABLE_STATUSES: [u16; 5] = [429, 500, 502, 503, 504];
+
+/// Last.fm error codes worth retrying: operation failed, service offline,
+/// temporarily unavailable and rate limit exceeded.
+const RETRYABLE_API_ERRORS: [u32; 4] = [8, 11, 16, 29];
 
 /// Period options for Last.fm time range filters
 #[derive(Debug, Clone, Copy)]
@@ -46,12 +96,32 @@ impl Period {
     }
 }
 
+/// Where play count exports take a track's `image_url` from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageSource {
+    /// The album art attached to the track
+    #[default]
+    Album,
+    /// The artist picture, looked up with `artist.getInfo`
+    Artist,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum TrackLimit {
     Limited(u32),
     Unlimited,
 }
 
+impl TrackLimit {
+    /// Number of tracks to fetch when `total` tracks are available.
+    fn resolve(self, total: u32) -> u32 {
+        match self {
+            TrackLimit::Limited(l) => l.min(total),
+            TrackLimit::Unlimited => total,
+        }
+    }
+}
+
 impl From<Option<u32>> for TrackLimit {
     fn from(opt: Option<u32>) -> Self {
         match opt {
@@ -61,21 +131,59 @@ impl From<Option<u32>> for TrackLimit {
     }
 }
 
+impl FromStr for TrackLimit {
+    type Err = LastFmError;
+
+    /// Parse a track limit from a string.
+    ///
+    /// `"all"` and `"unlimited"` (case-insensitive) map to `TrackLimit::Unlimited`,
+    /// and a non-negative integer maps to `TrackLimit::Limited`.
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+
+        if s.eq_ignore_ascii_case("all") || s.eq_ignore_ascii_case("unlimited") {
+            return Ok(TrackLimit::Unlimited);
+        }
+
+        s.parse::<u32>()
+            .map(TrackLimit::Limited)
+            .map_err(|_| LastFmError::Other(format!("Invalid track limit: {s}")))
+    }
+}
+
 trait TrackContainer {
     type ApiTrackType;
     type StorageTrackType: From<Self::ApiTrackType>;
 
+    /// Key of the object wrapping the tracks in the API response
+    const ROOT_KEY: &'static str;
+
+    /// Key of the list of items inside `ROOT_KEY`
+    const ITEM_KEY: &'static str = "track";
+
     fn total_tracks(&self) -> u32;
     fn tracks(self) -> Vec<Self::ApiTrackType>;
+
+    /// Key identifying a single play, used to spot tracks repeated because pages shifted.
+    ///
+    /// Lists without a stable per-play key return `None` and are left untouched.
+    fn page_shift_key(_track: &Self::StorageTrackType) -> Option<(Option<u32>, String)> {
+        None
+    }
 }
 
 impl TrackContainer for UserLovedTracks {
     type ApiTrackType = LovedTrack; // No change needed for LovedTracks
     type StorageTrackType = LovedTrack; // No change needed for LovedTracks
+    const ROOT_KEY: &'static str = "lovedtracks";
 
     fn total_tracks(&self) -> u32 {
         self.lovedtracks.attr.total
     }
+
+    fn page_shift_key(track: &LovedTrack) -> Option<(Option<u32>, String)> {
+        Some((track.get_timestamp(), track.name.clone()))
+    }
     fn tracks(self) -> Vec<Self::ApiTrackType> {
         self.lovedtracks.track
     }
@@ -84,18 +192,41 @@ impl TrackContainer for UserLovedTracks {
 impl TrackContainer for UserRecentTracks {
     type ApiTrackType = ApiRecentTrack;
     type StorageTrackType = RecentTrack;
+    const ROOT_KEY: &'static str = "recenttracks";
 
     fn total_tracks(&self) -> u32 {
         self.recenttracks.attr.total
     }
+
+    fn page_shift_key(track: &RecentTrack) -> Option<(Option<u32>, String)> {
+        Some((track.get_timestamp(), track.name.clone()))
+    }
     fn tracks(self) -> Vec<Self::ApiTrackType> {
         self.recenttracks.track
     }
 }
 
+impl TrackContainer for UserArtistTracks {
+    type ApiTrackType = ApiRecentTrack;
+    type StorageTrackType = RecentTrack;
+    const ROOT_KEY: &'static str = "artisttracks";
+
+    fn total_tracks(&self) -> u32 {
+        self.artisttracks.attr.total
+    }
+
+    fn page_shift_key(track: &RecentTrack) -> Option<(Option<u32>, String)> {
+        Some((track.get_timestamp(), track.name.clone()))
+    }
+    fn tracks(self) -> Vec<Self::ApiTrackType> {
+        self.artisttracks.track
+    }
+}
+
 impl TrackContainer for UserTopTracks {
     type ApiTrackType = TopTrack;
     type StorageTrackType = TopTrack;
+    const ROOT_KEY: &'static str = "toptracks";
 
     fn total_tracks(&self) -> u32 {
         self.toptracks.attr.total
@@ -105,23 +236,263 @@ impl TrackContainer for UserTopTracks {
     }
 }
 
-/// Represents a track's play count information
-#[derive(Debug, Serialize)]
-pub struct TrackPlayInfo {
-    pub name: String,
-    pub play_count: u32,
-    pub artist: String,
-    pub album: Option<String>,
-    pub image_url: Option<String>,
-    pub currently_playing: bool,
-    pub date: Option<u32>,
-    pub url: String,
+impl TrackContainer for UserTopArtists {
+    type ApiTrackType = TopArtist;
+    type StorageTrackType = TopArtist;
+    const ROOT_KEY: &'static str = "topartists";
+    const ITEM_KEY: &'static str = "artist";
+
+    fn total_tracks(&self) -> u32 {
+        self.topartists.attr.total
+    }
+    fn tracks(self) -> Vec<Self::ApiTrackType> {
+        self.topartists.artist
+    }
+}
+
+impl TrackContainer for UserTopAlbums {
+    type ApiTrackType = TopAlbum;
+    type StorageTrackType = TopAlbum;
+    const ROOT_KEY: &'static str = "topalbums";
+    const ITEM_KEY: &'static str = "album";
+
+    fn total_tracks(&self) -> u32 {
+        self.topalbums.attr.total
+    }
+    fn tracks(self) -> Vec<Self::ApiTrackType> {
+        self.topalbums.album
+    }
+}
+
+/// The now-playing state served by `serve_now_playing`
+#[cfg(feature = "server")]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct NowPlayingStatus {
+    /// The track being played, or `None` if nothing is playing
+    pub track: Option<RecentTrack>,
+    /// UNIX timestamp of the last successful refresh, `None` before the first one
+    pub updated_at: Option<i64>,
+}
+
+#[cfg(feature = "server")]
+type SharedNowPlaying = Arc<tokio::sync::RwLock<NowPlayingStatus>>;
+
+/// A backup of a user's library
+#[derive(Debug, Clone, Serialize)]
+pub struct LibrarySnapshot {
+    pub recent_tracks: Vec<RecentTrack>,
+    pub loved_tracks: Vec<LovedTrack>,
+    pub top_tracks: Vec<TopTrack>,
+}
+
+/// Estimated cost of fetching tracks, computed before any track is fetched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FetchPlan {
+    /// Number of tracks that would be fetched
+    pub total_tracks: u32,
+    /// Number of API requests needed, including the initial total request
+    pub api_calls: u32,
+    /// Number of chunks of concurrent requests
+    pub chunks: u32,
+}
+
+/// Progress information emitted while fetching tracks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressEvent {
+    /// Number of tracks fetched so far
+    pub fetched: u32,
+    /// Total number of tracks to fetch
+    pub total: u32,
+    /// Index (1-based) of the chunk that just completed
+    pub chunk: u32,
+    /// Total number of chunks
+    pub total_chunks: u32,
+    /// Time elapsed since the fetch started
+    pub elapsed: Duration,
+    /// Estimated time remaining, `None` until some tracks have been fetched
+    pub eta: Option<Duration>,
+}
+
+impl ProgressEvent {
+    /// Create a progress event, estimating the remaining time from the fetch rate so far.
+    ///
+    /// # Arguments
+    /// * `fetched` - Number of tracks fetched so far
+    /// * `total` - Total number of tracks to fetch
+    /// * `chunk` - Index (1-based) of the chunk that just completed
+    /// * `total_chunks` - Total number of chunks
+    /// * `elapsed` - Time elapsed since the fetch started
+    ///
+    /// # Returns
+    /// * `ProgressEvent` - The progress event
+    #[must_use]
+    pub fn new(fetched: u32, total: u32, chunk: u32, total_chunks: u32, elapsed: Duration) -> Self {
+        let eta = (fetched > 0).then(|| {
+            let remaining = total.saturating_sub(fetched);
+            elapsed.mul_f64(f64::from(remaining) / f64::from(fetched))
+        });
+
+        ProgressEvent {
+            fetched,
+            total,
+            chunk,
+            total_chunks,
+            elapsed,
+            eta,
+        }
+    }
+
+    /// Percentage of tracks fetched, between 0 and 100.
+    #[must_use]
+    pub fn percent_complete(&self) -> f64 {
+        if self.total == 0 {
+            return 100.0;
+        }
+
+        (f64::from(self.fetched) / f64::from(self.total) * 100.0).min(100.0)
+    }
 }
 
+/// Outcome of a fetch that can be cancelled
 #[derive(Debug, Clone)]
+pub enum FetchOutcome<T> {
+    /// Every requested track was fetched
+    Complete(Vec<T>),
+    /// The fetch was cancelled; holds the tracks fetched before cancellation
+    Cancelled(Vec<T>),
+    /// The fetch stopped before exceeding the handler's API call budget;
+    /// holds the tracks fetched so far
+    BudgetExceeded(Vec<T>),
+}
+
+impl<T> FetchOutcome<T> {
+    /// Whether the fetch was cancelled before completion.
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, FetchOutcome::Cancelled(_))
+    }
+
+    /// Get the fetched tracks, whether or not the fetch completed.
+    #[must_use]
+    pub fn into_tracks(self) -> Vec<T> {
+        match self {
+            FetchOutcome::Complete(tracks)
+            | FetchOutcome::Cancelled(tracks)
+            | FetchOutcome::BudgetExceeded(tracks) => tracks,
+        }
+    }
+}
+
+/// What fetching the next chunk of a paginated list produced
+enum ChunkStep<T> {
+    /// The tracks of the chunk
+    Tracks(Vec<T>),
+    /// The next chunk would exceed the call budget; `fetched` tracks came before it
+    BudgetExceeded { fetched: usize },
+    /// The fetch was cancelled before the next chunk
+    Cancelled,
+}
+
+/// Progress of `track_chunks` between two chunks
+struct ChunkState {
+    params: Arc<QueryParams>,
+    started_at: Instant,
+    /// Number of tracks to fetch, once resolved
+    final_limit: Option<u32>,
+    chunk_index: u32,
+    api_calls: u32,
+    fetched: usize,
+    /// Page-shift keys of the tracks yielded so far
+    seen: HashSet<(Option<u32>, String)>,
+    done: bool,
+}
+
+/// Answers API requests in place of Last.fm, to test fetches without a network.
+///
+/// Install one with `LastFMHandler::with_transport`. Requests reach the
+/// transport in the order the handler issues them, so it can record and
+/// check which pages a fetch asks for.
+#[cfg(feature = "testing")]
+pub trait HttpTransport: Send + Sync {
+    /// Return the response body of a request.
+    ///
+    /// # Arguments
+    /// * `params` - The full query parameters, including `method`, `page` and `limit`
+    ///
+    /// # Errors
+    /// Any error is returned as-is by the fetch that made the request.
+    fn get(&self, params: &QueryParams) -> Result<String>;
+}
+
+// Lets callers keep a handle on the transport to inspect it after a fetch
+#[cfg(feature = "testing")]
+impl<T: HttpTransport + ?Sized> HttpTransport for Arc<T> {
+    fn get(&self, params: &QueryParams) -> Result<String> {
+        (**self).get(params)
+    }
+}
+
+/// Callback invoked with a `ProgressEvent` after each fetched chunk
+pub type ProgressCallback = Arc<Mutex<dyn FnMut(ProgressEvent) + Send>>;
+
+/// Cache of `track.getInfo` results keyed on (artist, track)
+type TrackInfoCache = Arc<Mutex<LruCache<(String, String), TrackInfo>>>;
+
+/// Cache of discovered track totals keyed on the request, with the time they were fetched
+// Timed on tokio's clock so that tests can pause and advance it
+type TotalCache = Arc<Mutex<HashMap<String, (u32, tokio::time::Instant)>>>;
+
+/// Credentials of an authenticated Last.fm session, needed for write methods
+#[derive(Clone)]
+struct Session {
+    api_secret: String,
+    session_key: String,
+}
+
+#[derive(Clone)]
 pub struct LastFMHandler {
     url: Url,
     base_options: QueryParams,
+    client: reqwest::Client,
+    user_agent: String,
+    max_page_limit: u32,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    max_api_calls: Option<u32>,
+    max_retries: u32,
+    retry_base_delay: Duration,
+    strict_schema: bool,
+    dedup_page_shifts: bool,
+    verbose: bool,
+    preferred_image_size: ImageSize,
+    session: Option<Session>,
+    api_keys: Arc<[String]>,
+    next_api_key: Arc<AtomicUsize>,
+    progress_callback: ProgressCallback,
+    track_info_cache: TrackInfoCache,
+    total_cache_ttl: Duration,
+    total_cache: TotalCache,
+    #[cfg(feature = "testing")]
+    transport: Option<Arc<dyn HttpTransport>>,
+}
+
+impl fmt::Debug for LastFMHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LastFMHandler")
+            .field("url", &self.url)
+            .field("base_options", &redact_params(&self.base_options))
+            .field("user_agent", &self.user_agent)
+            .field("max_page_limit", &self.max_page_limit)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("max_api_calls", &self.max_api_calls)
+            .field("max_retries", &self.max_retries)
+            .field("strict_schema", &self.strict_schema)
+            .field("dedup_page_shifts", &self.dedup_page_shifts)
+            .field("verbose", &self.verbose)
+            .field("preferred_image_size", &self.preferred_image_size)
+            .field("total_cache_ttl", &self.total_cache_ttl)
+            .field("authenticated", &self.session.is_some())
+            .finish_non_exhaustive()
+    }
 }
 
 impl LastFMHandler {
@@ -136,519 +507,5518 @@ impl LastFMHandler {
     /// # Returns
     /// * `Result<Self>` - The created `LastFMHandler` instance.
     pub fn new(username: &str) -> Result<Self> {
-        let api_key = config::get_required_env_var("LAST_FM_API_KEY")?;
+        Self::from_config(&Config::from_env(username)?)
+    }
+
+    /// Start building a `LastFMHandler`, see `LastFMHandlerBuilder`.
+    #[must_use]
+    pub fn builder() -> LastFMHandlerBuilder {
+        LastFMHandlerBuilder::default()
+    }
+
+    /// Creates a new `LastFMHandler` instance from an explicit configuration.
+    ///
+    /// Unlike `new`, this never reads the environment.
+    ///
+    /// # Arguments
+    /// * `config` - The handler configuration.
+    ///
+    /// # Errors
+    /// Returns `LastFmError::Other` if the API key or username is empty,
+    /// or `LastFmError::Http` if the HTTP client cannot be built.
+    ///
+    /// # Returns
+    /// * `Result<Self>` - The created `LastFMHandler` instance.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        if config.api_key.is_empty() {
+            return Err(LastFmError::Other("API key must not be empty".to_string()));
+        }
+        if config.username.is_empty() {
+            return Err(LastFmError::Other("Username must not be empty".to_string()));
+        }
 
         let mut base_options = QueryParams::new();
-        base_options.insert("api_key".to_string(), api_key);
+        base_options.insert("api_key".to_string(), config.api_key.clone());
         base_options.insert("limit".to_string(), API_MAX_LIMIT.to_string());
         base_options.insert("format".to_string(), "json".to_string());
-        base_options.insert("user".to_string(), username.to_string());
+        base_options.insert("user".to_string(), config.username.clone());
 
-        let url = Url::new(BASE_URL);
+        let url = Url::new(config.base_url.as_deref().unwrap_or(BASE_URL));
 
-        Ok(LastFMHandler { url, base_options })
+        let handler = LastFMHandler {
+            url,
+            base_options,
+            client: build_client(config.request_timeout)?,
+            user_agent: config
+                .user_agent
+                .clone()
+                .unwrap_or_else(|| DEFAULT_USER_AGENT.to_string()),
+            max_page_limit: API_MAX_LIMIT,
+            rate_limiter: None,
+            max_api_calls: None,
+            max_retries: DEFAULT_MAX_RETRIES,
+            retry_base_delay: RETRY_BASE_DELAY,
+            strict_schema: false,
+            dedup_page_shifts: false,
+            verbose: false,
+            preferred_image_size: ImageSize::ExtraLarge,
+            session: None,
+            api_keys: Arc::from([]),
+            next_api_key: Arc::new(AtomicUsize::new(0)),
+            progress_callback: Arc::new(Mutex::new(|_| {})),
+            track_info_cache: Arc::new(Mutex::new(LruCache::new(DEFAULT_TRACK_INFO_CACHE_SIZE))),
+            total_cache_ttl: Duration::ZERO,
+            total_cache: Arc::default(),
+            #[cfg(feature = "testing")]
+            transport: None,
+        };
+
+        Ok(match config.max_page_limit {
+            Some(limit) => handler.with_max_page_limit(limit),
+            None => handler,
+        })
     }
 
-    /// Get loved tracks for a user.
+    /// The username tracks are fetched for.
+    #[must_use]
+    pub fn username(&self) -> &str {
+        self.base_options.get("user").map_or("", String::as_str)
+    }
+
+    /// The API endpoint requests are sent to.
+    #[must_use]
+    pub fn base_url(&self) -> &str {
+        self.url.base()
+    }
+
+    /// The maximum number of tracks requested per page.
+    #[must_use]
+    pub fn max_limit(&self) -> u32 {
+        self.max_page_limit
+    }
+
+    /// Create a `MultiUserHandler` fetching data for several users.
     ///
-    /// # Arguments
-    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// Every per-user handler shares this handler's configuration and HTTP client.
     ///
-    /// # Errors
-    /// Returns an error if the API request fails.
+    /// # Arguments
+    /// * `usernames` - The Last.fm usernames.
     ///
     /// # Returns
-    /// * `Result<Vec<LovedTrack>, Error>` - The fetched tracks.
-    pub async fn get_user_loved_tracks(
-        &self,
-        limit: impl Into<TrackLimit>,
-    ) -> Result<Vec<LovedTrack>> {
-        self.get_user_tracks::<UserLovedTracks>("user.getlovedtracks", limit.into(), None)
-            .await
+    /// * `MultiUserHandler` - The handler for all the given users.
+    #[must_use]
+    pub fn for_users(&self, usernames: &[&str]) -> MultiUserHandler {
+        let handlers = usernames
+            .iter()
+            .map(|username| {
+                let mut handler = self.clone();
+                handler
+                    .base_options
+                    .insert("user".to_string(), (*username).to_string());
+                ((*username).to_string(), handler)
+            })
+            .collect();
+
+        MultiUserHandler { handlers }
     }
 
-    /// Get recent tracks for a user.
+    /// Override the base URL used for API requests.
     ///
     /// # Arguments
-    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
-    ///
-    /// # Errors
-    /// Returns an error if the API request fails.
+    /// * `base_url` - The base URL to send requests to.
     ///
     /// # Returns
-    /// * `Result<Vec<RecentTrack>, Error>` - The fetched tracks.
-    pub async fn get_user_recent_tracks(
-        &self,
-        limit: impl Into<TrackLimit>,
-    ) -> Result<Vec<RecentTrack>> {
-        self.get_user_tracks::<UserRecentTracks>("user.getrecenttracks", limit.into(), None)
-            .await
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.url = Url::new(base_url);
+        self
     }
 
-    /// Get top tracks for a user.
+    /// Set the User-Agent header sent with every request.
     ///
-    /// # Arguments
-    /// * `limit` - The number of tracks to fetch. If None, fetch all available top tracks.
-    /// * `period` - Optional period filter
-    ///   (`Period::Overall`, `Period::SevenDay`, `Period::OneMonth`,
-    ///   `Period::ThreeMonth`, `Period::SixMonth`, `Period::TwelveMonth`)
+    /// Last.fm asks API clients to identify themselves; defaults to `async_lastfm/<version>`.
     ///
-    /// # Errors
-    /// Returns an error if the API request fails.
+    /// # Arguments
+    /// * `user_agent` - The User-Agent to send.
     ///
     /// # Returns
-    /// * `Result<Vec<TopTrack>>` - The fetched tracks.
-    pub async fn get_user_top_tracks(
-        &self,
-        limit: impl Into<TrackLimit>,
-        period: Option<Period>,
-    ) -> Result<Vec<TopTrack>> {
-        let mut params = QueryParams::new();
-        if let Some(p) = period {
-            params.insert("period".to_string(), p.as_api_str().to_string());
-        }
-
-        self.get_user_tracks::<UserTopTracks>("user.gettoptracks", limit.into(), Some(params))
-            .await
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = user_agent.to_string();
+        self
     }
 
-    /// Get tracks for a user.
+    /// Set the maximum number of tracks requested per page.
+    ///
+    /// Defaults to 1000, the current Last.fm cap. Values above it (or 0) are
+    /// clamped into `1..=1000` with a warning.
     ///
     /// # Arguments
-    /// * `method` - The method to call.
-    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// * `limit` - The maximum number of tracks per page.
     ///
     /// # Returns
-    /// * `Result<Vec<T::TrackType>, Error>` - The fetched tracks.
-    async fn get_user_tracks<T: DeserializeOwned + TrackContainer>(
-        &self,
-        method: &str,
-        limit: TrackLimit,
-        additional_params: Option<QueryParams>,
-    ) -> Result<Vec<T::StorageTrackType>> {
-        let mut params = self.base_options.clone();
-        if let Some(additional_params) = additional_params {
-            params.extend(additional_params);
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_max_page_limit(mut self, limit: u32) -> Self {
+        let clamped = limit.clamp(1, API_MAX_LIMIT);
+        if clamped != limit {
+            tracing::warn!(limit, clamped, "page limit is out of range, clamping it");
         }
 
-        // Make an initial request to get the total number of tracks
-        let mut base_params: QueryParams = HashMap::new();
-        base_params.insert("limit".to_string(), "1".to_string());
-        base_params.insert("page".to_string(), "1".to_string());
-        base_params.extend(params.clone());
-
-        let initial_response: T = self.fetch(method, &base_params).await?;
-        let total_tracks = initial_response.total_tracks();
-
-        let final_limit = match limit {
-            TrackLimit::Limited(l) => l.min(total_tracks),
-            TrackLimit::Unlimited => total_tracks,
-        };
-
-        println!("Need to fetch {final_limit} tracks");
-
-        if final_limit <= API_MAX_LIMIT {
-            // If we need less than the API limit, just make a single request
-            let mut base_params: QueryParams = HashMap::new();
-            base_params.insert("limit".to_string(), final_limit.to_string());
-            base_params.insert("page".to_string(), "1".to_string());
-            base_params.extend(params);
-
-            let response: T = self.fetch(method, &base_params).await?;
-            return Ok(response
-                .tracks()
-                .into_iter()
-                .take(final_limit as usize)
-                .map(T::StorageTrackType::from)
-                .collect());
-        }
-
-        let chunk_nb = final_limit.div_ceil(CHUNK_SIZE);
-
-        let mut all_tracks = Vec::new();
-
-        // Process chunks sequentially
-        for chunk_index in 0..chunk_nb {
-            println!("Processing chunk {}/{}", chunk_index + 1, chunk_nb);
-            let chunk_params = params.clone();
-
-            // Calculate how many API calls we need for this chunk
-            let chunk_api_calls = if chunk_index == chunk_nb - 1 {
-                // Last chunk
-                final_limit % CHUNK_SIZE / API_MAX_LIMIT + 1
-            } else {
-                CHUNK_SIZE / API_MAX_LIMIT
-            };
-
-            // Create futures for concurrent API calls within this chunk
-            let api_call_futures: Vec<_> = (0..chunk_api_calls)
-                .map(|call_index| {
-                    let mut call_params = chunk_params.clone();
-                    let call_limit =
-                        (final_limit - chunk_index * CHUNK_SIZE - call_index * API_MAX_LIMIT)
-                            .min(API_MAX_LIMIT);
-
-                    let page = chunk_index * CHUNK_SIZE / API_MAX_LIMIT + call_index + 1;
-
-                    call_params.insert("limit".to_string(), call_limit.to_string());
-                    call_params.insert("page".to_string(), page.to_string());
-
-                    async move {
-                        let response: T = self.fetch(method, &call_params).await?;
-                        Ok::<_, LastFmError>(
-                            response
-                                .tracks()
-                                .into_iter()
-                                .take(call_limit as usize)
-                                .map(T::StorageTrackType::from)
-                                .collect::<Vec<_>>(),
-                        )
-                    }
-                })
-                .collect();
-
-            // Process all API calls in this chunk concurrently
-            let chunk_results = join_all(api_call_futures).await;
-
-            // Collect results from this chunk
-            for result in chunk_results {
-                all_tracks.extend(result?);
-            }
-        }
-
-        Ok(all_tracks)
+        self.max_page_limit = clamped;
+        self.base_options
+            .insert("limit".to_string(), clamped.to_string());
+        self
     }
 
-    /// Fetch data from the `LastFM` API.
+    /// Limit the number of requests sent per second.
+    ///
+    /// The limit is shared by every clone of this handler, including the
+    /// per-user handlers of `for_users`. Last.fm allows around 5 requests per second.
     ///
     /// # Arguments
-    /// * `method` - The method to call.
-    /// * `params` - The parameters to pass to the API.
+    /// * `requests_per_second` - The maximum number of requests per second.
     ///
     /// # Returns
-    /// * `Result<T, Error>` - The fetched data.
-    async fn fetch<T: DeserializeOwned>(&self, method: &str, params: &QueryParams) -> Result<T> {
-        let mut final_params = self.base_options.clone();
-        final_params.insert("method".to_string(), method.to_string());
-        final_params.extend(params.clone());
-
-        let base_url = self.url.clone().add_args(final_params).build();
-
-        let response = reqwest::get(&base_url).await?;
-
-        // Check if the response is an error
-        if !response.status().is_success() {
-            let error: LastFmErrorResponse = response.json().await?;
-            return Err(LastFmError::Api(error));
-        }
-
-        // Try to parse the successful response
-        let parsed_response = response.json::<T>().await?;
-        Ok(parsed_response)
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_rate_limit(mut self, requests_per_second: u32) -> Self {
+        self.rate_limiter = Some(Arc::new(RateLimiter::new(requests_per_second)));
+        self
     }
 
-    /// Get and save recent tracks to a file.
+    /// Cap the number of API calls a single fetch may make.
     ///
-    /// # Arguments
-    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
-    /// * `format` - The file format to save the tracks in.
+    /// A fetch that would go over the budget stops before sending the
+    /// offending requests: the cancellable methods return
+    /// `FetchOutcome::BudgetExceeded` with the tracks fetched so far, and the
+    /// other methods return a `LastFmError::Other` error.
     ///
-    /// # Errors
-    /// * `LastFmError::Api` - If the API returns an error.
-    /// * `LastFmError::Io` - If there is an error saving the file.
+    /// # Arguments
+    /// * `max_api_calls` - The maximum number of requests per fetch, including the initial total request.
     ///
     /// # Returns
-    /// * `Result<String, Box<dyn std::error::Error>>` - The filename of the saved file.
-    pub async fn get_and_save_recent_tracks(
-        &self,
-        limit: impl Into<TrackLimit>,
-        format: FileFormat,
-        filename_prefix: &str,
-    ) -> Result<String> {
-        let tracks = self.get_user_recent_tracks(limit).await?;
-        println!("Saving {} tracks to file", tracks.len());
-        let filename =
-            FileHandler::save(&tracks, &format, filename_prefix).map_err(LastFmError::Io)?;
-        Ok(filename)
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_max_api_calls(mut self, max_api_calls: u32) -> Self {
+        self.max_api_calls = Some(max_api_calls);
+        self
     }
 
-    /// Get and save loved tracks to a file.
+    /// Set how many times a request failing transiently is retried.
     ///
-    /// # Arguments
-    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
-    /// * `format` - The file format to save the tracks in.
+    /// Rate limiting (HTTP 429 or Last.fm error 29, even with a 200 status),
+    /// server errors (500, 502, 503, 504, or Last.fm errors 8, 11 and 16) and
+    /// connection failures are retried with exponential backoff and jitter,
+    /// starting at half a second. Other errors, such as an invalid API key or
+    /// an unknown user, fail immediately. Defaults to 3; 0 disables retries.
     ///
-    /// # Errors
-    /// * `FileError` - If there was an error reading or writing the file
-    /// * `InvalidUtf8` - If the file path is not valid UTF-8
+    /// # Arguments
+    /// * `max_retries` - The maximum number of retries per request.
     ///
     /// # Returns
-    /// * `Result<String, Box<dyn std::error::Error>>` - The filename of the saved file.
-    pub async fn get_and_save_loved_tracks(
-        &self,
-        limit: impl Into<TrackLimit>,
-        format: FileFormat,
-    ) -> Result<String> {
-        let tracks = self.get_user_loved_tracks(limit).await?;
-        let filename =
-            FileHandler::save(&tracks, &format, "loved_tracks").map_err(LastFmError::Io)?;
-        Ok(filename)
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
     }
 
-    /// Get recent tracks for a user since a given timestamp.
+    /// Fail fetches whose first response doesn't match the expected schema.
     ///
-    /// # Arguments
-    /// * `timestamp` - The timestamp to fetch tracks since.
-    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// The first response of every multi-page fetch is checked for the fields
+    /// the crate relies on before the remaining pages are requested. By
+    /// default a mismatch is only logged as a warning; in strict mode the fetch
+    /// returns `LastFmError::SchemaMismatch` instead.
     ///
-    /// # Errors
-    /// * `FileError` - If there was an error reading or writing the file
-    /// * `InvalidUtf8` - If the file path is not valid UTF-8
+    /// # Arguments
+    /// * `strict_schema` - Whether a schema mismatch aborts the fetch.
     ///
     /// # Returns
-    /// * `Vec<RecentTrack>` - The fetched tracks.
-    #[allow(dead_code)]
-    pub async fn get_user_recent_tracks_since(
-        &self,
-        timestamp: i64,
-        limit: impl Into<TrackLimit>,
-    ) -> Result<Vec<RecentTrack>> {
-        let mut params = QueryParams::new();
-        params.insert("from".to_string(), timestamp.to_string());
-
-        self.get_user_tracks::<UserRecentTracks>("user.getrecenttracks", limit.into(), Some(params))
-            .await
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_strict_schema(mut self, strict_schema: bool) -> Self {
+        self.strict_schema = strict_schema;
+        self
     }
 
-    /// Get loved tracks for a user since a given timestamp.
+    /// Remove tracks duplicated by scrobbles arriving during a multi-page fetch.
+    ///
+    /// A scrobble recorded while pages are being fetched shifts every later
+    /// page by one, so the last track of a page shows up again at the top of
+    /// the next. When enabled, recent, artist and loved tracks are deduplicated
+    /// on their timestamp and name once all pages are in, and a warning is
+    /// logged if any duplicate was found. The track pushed off the last page
+    /// by the shift is still missing; fetching again recovers it.
     ///
     /// # Arguments
-    /// * `timestamp` - The timestamp to fetch tracks since.
-    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// * `dedup_page_shifts` - Whether to deduplicate multi-page fetches.
     ///
-    /// # Errors
-    /// * `FileError` - If there was an error reading or writing the file
-    /// * `InvalidUtf8` - If the file path is not valid UTF-8
+    /// # Returns
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_page_shift_dedup(mut self, dedup_page_shifts: bool) -> Self {
+        self.dedup_page_shifts = dedup_page_shifts;
+        self
+    }
+
+    /// Log every request and the start of its response at `trace` level.
+    ///
+    /// Each outgoing URL is logged through `tracing` with the API key
+    /// redacted, followed by the first bytes of the response body, so the
+    /// output can be attached to bug reports about parse failures.
+    ///
+    /// # Arguments
+    /// * `verbose` - Whether to log requests and responses.
     ///
     /// # Returns
-    /// * `Vec<LovedTrack>` - The fetched tracks.
-    #[allow(dead_code)]
-    pub async fn get_user_loved_tracks_since(
-        &self,
-        timestamp: u32,
-        limit: impl Into<TrackLimit>,
-    ) -> Result<Vec<LovedTrack>> {
-        let tracks = self.get_user_loved_tracks(limit).await?;
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_verbose_logging(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
 
-        Ok(tracks
-            .into_iter()
-            .filter(|track| track.date.uts > timestamp)
-            .collect())
+    /// Set the image size picked for exported tracks and artist pictures.
+    ///
+    /// Every method choosing among the sizes Last.fm returns uses this size,
+    /// falling back to larger sizes first, then smaller ones (see `best_image`).
+    /// Defaults to `ImageSize::ExtraLarge`.
+    ///
+    /// # Arguments
+    /// * `size` - The preferred image size.
+    ///
+    /// # Returns
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_preferred_image_size(mut self, size: ImageSize) -> Self {
+        self.preferred_image_size = size;
+        self
     }
 
-    /// Update a tracks file with new tracks.
+    /// Authenticate the handler so it can call write methods such as `track.love`.
+    ///
+    /// Write requests are signed with `api_secret` and always use the primary
+    /// API key, since a session key is bound to the key it was issued for.
     ///
     /// # Arguments
-    /// * `file_path` - Path to the file to update.
-    /// * `fetch_since` - Function to fetch tracks since a given timestamp.
+    /// * `api_secret` - The shared secret of the API account.
+    /// * `session_key` - The session key obtained through `auth.getSession`.
     ///
-    /// # Errors
-    /// * `FileError` - If there was an error reading or writing the file
+    /// # Returns
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_session(mut self, api_secret: &str, session_key: &str) -> Self {
+        self.session = Some(Session {
+            api_secret: api_secret.to_string(),
+            session_key: session_key.to_string(),
+        });
+        self
+    }
+
+    /// Spread requests over several API keys.
+    ///
+    /// Each request uses the next key in order, wrapping around, which
+    /// multiplies the per-key rate budget. The rotation is shared by every
+    /// clone of this handler. An empty list keeps the configured key.
     ///
-    /// # Panics
-    /// * If the file path is not valid UTF-8
+    /// # Arguments
+    /// * `api_keys` - The API keys to rotate through.
     ///
     /// # Returns
-    /// * `Result<String, Box<dyn std::error::Error>>` - The filename of the updated file.
-    #[allow(dead_code)]
-    pub async fn update_tracks_file<T: DeserializeOwned + Serialize + Timestamped>(
-        &self,
-        file_path: &Path,
-    ) -> Result<String> {
-        // Get the most recent timestamp from the file
-        let last_timestamp =
-            AnalysisHandler::get_most_recent_timestamp::<T>(file_path)?.unwrap_or(0);
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_api_keys(mut self, api_keys: &[&str]) -> Self {
+        self.api_keys = api_keys.iter().map(|key| (*key).to_string()).collect();
+        self.next_api_key = Arc::new(AtomicUsize::new(0));
+        self
+    }
 
-        // Find the recent tracks in the file
-        let recent_tracks = self
-            .get_user_recent_tracks_since(last_timestamp, None)
-            .await?;
+    /// The API key to use for the next request, if several keys are configured.
+    fn next_api_key(&self) -> Option<&str> {
+        if self.api_keys.is_empty() {
+            return None;
+        }
+
+        let index = self.next_api_key.fetch_add(1, Ordering::Relaxed) % self.api_keys.len();
+        Some(&self.api_keys[index])
+    }
 
-        let file_path_str = file_path.to_str().unwrap();
+    /// The API key the next request will use, without advancing the rotation.
+    fn upcoming_api_key(&self) -> Option<&str> {
+        if self.api_keys.is_empty() {
+            return None;
+        }
 
-        // Append the new tracks to the file
-        let updated_file = FileHandler::append(&recent_tracks, file_path_str)?;
+        let index = self.next_api_key.load(Ordering::Relaxed) % self.api_keys.len();
+        Some(&self.api_keys[index])
+    }
 
-        Ok(updated_file)
+    /// Whether making `calls` API calls stays within the configured budget.
+    fn within_budget(&self, calls: u32) -> bool {
+        self.max_api_calls.is_none_or(|max| calls <= max)
     }
 
-    /// Export play counts for the last X songs with additional track information
+    /// Set how many `track.getInfo` results are kept in memory.
+    ///
+    /// A size of 0 disables caching.
     ///
     /// # Arguments
-    /// * `limit` - Number of recent tracks to analyze
-    /// * `file_path` - Path to the file to save the play counts to
+    /// * `size` - The maximum number of cached tracks.
     ///
-    /// # Errors
-    /// * `FileError` - If there was an error reading or writing the file
+    /// # Returns
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_track_info_cache_size(mut self, size: usize) -> Self {
+        self.track_info_cache = Arc::new(Mutex::new(LruCache::new(size)));
+        self
+    }
+
+    /// Reuse discovered track totals for `ttl`.
+    ///
+    /// Every fetch first requests a single track to learn how many there are.
+    /// With a non-zero `ttl`, that total is remembered per method and
+    /// parameters (period, time range, ...), and fetches repeated within `ttl`
+    /// skip the discovery request. Tracks scrobbled in the meantime are only
+    /// picked up once the total expires. A zero `ttl`, the default, disables
+    /// the cache.
+    ///
+    /// # Arguments
+    /// * `ttl` - How long a discovered total stays valid.
     ///
     /// # Returns
-    /// * `Result<String>` - Path to the saved JSON file containing play counts
-    pub async fn export_recent_play_counts(&self, limit: impl Into<TrackLimit>) -> Result<String> {
-        // Get recent tracks
-        let tracks = self.get_user_recent_tracks(limit.into()).await?;
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_total_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.total_cache_ttl = ttl;
+        self.total_cache = Arc::default();
+        self
+    }
 
-        // Count plays and collect track info
-        let mut play_counts: HashMap<String, TrackPlayInfo> = HashMap::new();
-
-        for track in tracks {
-            let entry = play_counts
-                .entry(track.name.clone())
-                .or_insert(TrackPlayInfo {
-                    name: track.name.clone(),
-                    play_count: 0,
-                    artist: track.artist.text.clone(),
-                    album: Some(track.album.text.clone()),
-                    image_url: track
-                        .image
-                        .iter()
-                        .find(|img| img.size == "large")
-                        .map(|img| img.text.clone())
-                        .or_else(|| track.image.first().map(|img| img.text.clone())),
-                    currently_playing: track.attr.is_some_and(|attr| attr.nowplaying == "true"),
-                    date: track.date.map(|date| date.uts),
-                    url: track.url,
-                });
+    /// Send requests to `transport` instead of Last.fm.
+    ///
+    /// Everything else, from pagination to rate limiting and parsing, behaves
+    /// as with real requests.
+    ///
+    /// # Arguments
+    /// * `transport` - The transport answering requests.
+    ///
+    /// # Returns
+    /// * `Self` - The updated handler.
+    #[cfg(feature = "testing")]
+    #[must_use]
+    pub fn with_transport(mut self, transport: impl HttpTransport + 'static) -> Self {
+        self.transport = Some(Arc::new(transport));
+        self
+    }
+
+    /// Set a callback that receives a `ProgressEvent` after each fetched chunk.
+    ///
+    /// # Arguments
+    /// * `callback` - The function to call with progress updates.
+    ///
+    /// # Returns
+    /// * `Self` - The updated handler.
+    #[must_use]
+    pub fn with_progress_callback<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(ProgressEvent) + Send + 'static,
+    {
+        self.progress_callback = Arc::new(Mutex::new(callback));
+        self
+    }
+
+    /// Number of API calls needed for the chunk at `chunk_index`.
+    ///
+    /// # Arguments
+    /// * `final_limit` - The total number of tracks to fetch.
+    /// * `chunk_index` - The zero-based index of the chunk.
+    fn chunk_api_calls(&self, final_limit: u32, chunk_index: u32) -> u32 {
+        let chunk_size = self.max_page_limit * CHUNK_MULTIPLIER;
+        let chunk_tracks = (final_limit - chunk_index * chunk_size).min(chunk_size);
+
+        chunk_tracks.div_ceil(self.max_page_limit)
+    }
+
+    /// Compute how many requests fetching `final_limit` tracks takes.
+    ///
+    /// # Arguments
+    /// * `final_limit` - The total number of tracks to fetch.
+    fn fetch_plan(&self, final_limit: u32) -> FetchPlan {
+        if final_limit <= self.max_page_limit {
+            return FetchPlan {
+                total_tracks: final_limit,
+                api_calls: 2,
+                chunks: 1,
+            };
+        }
+
+        let chunks = final_limit.div_ceil(self.max_page_limit * CHUNK_MULTIPLIER);
+        let pages: u32 = (0..chunks)
+            .map(|chunk_index| self.chunk_api_calls(final_limit, chunk_index))
+            .sum();
 
-            entry.play_count += 1;
+        FetchPlan {
+            total_tracks: final_limit,
+            api_calls: pages + 1,
+            chunks,
         }
+    }
 
-        // Convert HashMap values into a Vec
-        let play_counts_vec: Vec<TrackPlayInfo> = play_counts.into_values().collect();
+    /// Estimate the cost of fetching recent tracks without fetching them.
+    ///
+    /// Only the request discovering the total number of tracks is made, which
+    /// lets callers warn before a large `TrackLimit::Unlimited` pull.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks that would be fetched.
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<FetchPlan>` - The number of tracks, requests and chunks involved.
+    pub async fn plan_fetch(&self, limit: impl Into<TrackLimit>) -> Result<FetchPlan> {
+        let params = ParamBuilder::new().limit(1).page(1).build();
 
-        // Save to file
-        let filename = FileHandler::save(&[play_counts_vec], &FileFormat::Json, "play_counts")
-            .map_err(LastFmError::Io)?;
+        let response: UserRecentTracks = self.fetch("user.getrecenttracks", &params).await?;
 
-        Ok(filename)
+        Ok(self.fetch_plan(limit.into().resolve(response.total_tracks())))
     }
 
-    /// Update or create a file with play counts for the last X songs with additional track information
+    /// Report progress to the configured callback.
+    fn report_progress(&self, event: ProgressEvent) {
+        if let Ok(mut callback) = self.progress_callback.lock() {
+            callback(event);
+        }
+    }
+
+    /// Get loved tracks for a user.
     ///
     /// # Arguments
-    /// * `limit` - Number of recent tracks to analyze
-    /// * `file_path` - Path to the file to update/create
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
     ///
     /// # Errors
-    /// * `LastFmError::Api` - If the API returns an error
-    /// * `LastFmError::Io` - If there is an error reading or writing the file
+    /// Returns an error if the API request fails.
     ///
     /// # Returns
-    /// * `Result<String>` - Path to the updated/created JSON file containing play counts
-    pub async fn update_recent_play_counts(
+    /// * `Result<Vec<LovedTrack>, Error>` - The fetched tracks, newest first.
+    pub async fn get_user_loved_tracks(
         &self,
         limit: impl Into<TrackLimit>,
-        file_path: &str,
-    ) -> Result<String> {
-        // Get recent tracks
-        let tracks = self.get_user_recent_tracks(limit.into()).await?;
-
-        // Count plays and collect track info
-        let mut play_counts: HashMap<String, TrackPlayInfo> = HashMap::new();
-
-        for track in tracks {
-            let entry = play_counts
-                .entry(track.name.clone())
-                .or_insert(TrackPlayInfo {
-                    name: track.name.clone(),
-                    play_count: 0,
-                    artist: track.artist.text.clone(),
-                    album: Some(track.album.text.clone()),
-                    image_url: track
-                        .image
-                        .iter()
-                        .find(|img| img.size == "extralarge") // Best size for album art
-                        .map(|img| img.text.clone())
-                        .or_else(|| track.image.first().map(|img| img.text.clone())),
-                    currently_playing: track
-                        .attr
-                        .as_ref()
-                        .is_some_and(|val| val.nowplaying == "true"),
-                    date: track.date.map(|date| date.uts),
-                    url: track.url,
-                });
+    ) -> Result<Vec<LovedTrack>> {
+        let mut tracks = self
+            .get_user_tracks::<UserLovedTracks>("user.getlovedtracks", limit.into(), None)
+            .await?;
 
-            entry.play_count += 1;
-        }
+        // Pages are fetched concurrently; make the newest-first order explicit
+        tracks.sort_by_key(|track| std::cmp::Reverse(track.get_timestamp()));
+        Ok(tracks)
+    }
 
-        // Convert HashMap values into a Vec
-        let play_counts_vec: Vec<TrackPlayInfo> = play_counts.into_values().collect();
+    /// Get the number of tracks a user has loved.
+    ///
+    /// Only a single one-track page is requested.
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<u32>` - The total number of loved tracks.
+    pub async fn get_user_loved_tracks_total(&self) -> Result<u32> {
+        let params = ParamBuilder::new().limit(1).page(1).build();
 
-        // Create the file (overwriting if it exists)
-        let file = File::create(file_path).map_err(LastFmError::Io)?;
-        serde_json::to_writer_pretty(file, &play_counts_vec).map_err(LastFmError::Parse)?;
+        let response: UserLovedTracks = self.fetch("user.getlovedtracks", &params).await?;
 
-        Ok(file_path.to_string())
+        Ok(response.total_tracks())
     }
 
-    /// Check if the user is currently playing a track
+    /// Get recent tracks for a user.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
     ///
     /// # Errors
-    /// * `LastFmError` - If there was an error communicating with Last.fm
+    /// Returns an error if the API request fails.
     ///
     /// # Returns
-    /// * `Result<Option<RecentTrack>>` - The currently playing track if any
-    pub async fn is_currently_playing(&self) -> Result<Option<RecentTrack>> {
-        let mut params = QueryParams::new();
-        params.insert("limit".to_string(), "1".to_string());
-
-        let tracks = self
-            .get_user_tracks::<UserRecentTracks>(
-                "user.getrecenttracks",
-                TrackLimit::Limited(1),
-                Some(params),
-            )
-            .await?;
+    /// * `Result<Vec<RecentTrack>, Error>` - The fetched tracks.
+    pub async fn get_user_recent_tracks(
+        &self,
+        limit: impl Into<TrackLimit>,
+    ) -> Result<Vec<RecentTrack>> {
+        self.get_user_tracks::<UserRecentTracks>("user.getrecenttracks", limit.into(), None)
+            .await
+    }
 
-        // Check if the first track has the "now playing" attribute
-        Ok(tracks.first().and_then(|track| {
-            if track
-                .attr
-                .as_ref()
-                .is_some_and(|val| val.nowplaying == "true")
-            {
-                Some(track.clone())
-            } else {
-                None
-            }
-        }))
+    /// Stream recent tracks for a user as they are fetched.
+    ///
+    /// Unlike `get_user_recent_tracks`, tracks are not collected in memory:
+    /// pages are fetched one chunk at a time, as the stream is consumed, and
+    /// their tracks yielded newest first. An error ends the stream.
+    /// `get_user_recent_tracks` collects this same stream, so call budgets,
+    /// progress callbacks and page-shift deduplication apply to both; to
+    /// cancel, stop consuming the stream.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    ///
+    /// # Returns
+    /// * `impl Stream<Item = Result<RecentTrack>>` - The fetched tracks.
+    pub fn recent_tracks_stream(
+        &self,
+        limit: impl Into<TrackLimit>,
+    ) -> impl Stream<Item = Result<RecentTrack>> + '_ {
+        self.tracks_stream::<UserRecentTracks>("user.getrecenttracks", limit.into(), None)
     }
 
-    /// Update a file with the currently playing track information
+    /// Get loved tracks for a user, stopping early if `cancel` is triggered.
+    ///
+    /// Cancellation is checked between chunks, so tracks fetched before
+    /// cancellation are returned in `FetchOutcome::Cancelled`.
     ///
     /// # Arguments
-    /// * `file_path` - Path to the file to update
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// * `cancel` - Token used to stop the fetch.
     ///
     /// # Errors
-    /// * `LastFmError::Api` - If the API returns an error
-    /// * `LastFmError::Io` - If there is an error reading or writing the file
-    /// * `LastFmError::Parse` - If there is an error parsing the JSON
+    /// Returns an error if the API request fails.
     ///
     /// # Returns
-    /// * `Result<Option<RecentTrack>>` - The currently playing track if any
-    pub async fn update_currently_listening(&self, file_path: &str) -> Result<Option<RecentTrack>> {
-        let current_track = self.is_currently_playing().await?;
+    /// * `Result<FetchOutcome<LovedTrack>>` - The fetched tracks.
+    pub async fn get_user_loved_tracks_cancellable(
+        &self,
+        limit: impl Into<TrackLimit>,
+        cancel: &CancellationToken,
+    ) -> Result<FetchOutcome<LovedTrack>> {
+        self.get_user_tracks_cancellable::<UserLovedTracks>(
+            "user.getlovedtracks",
+            limit.into(),
+            None,
+            Some(cancel),
+        )
+        .await
+    }
 
-        // Create or overwrite the file
-        let file = File::create(file_path).map_err(LastFmError::Io)?;
+    /// Get recent tracks for a user, stopping early if `cancel` is triggered.
+    ///
+    /// Cancellation is checked between chunks, so tracks fetched before
+    /// cancellation are returned in `FetchOutcome::Cancelled`.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// * `cancel` - Token used to stop the fetch.
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<FetchOutcome<RecentTrack>>` - The fetched tracks.
+    pub async fn get_user_recent_tracks_cancellable(
+        &self,
+        limit: impl Into<TrackLimit>,
+        cancel: &CancellationToken,
+    ) -> Result<FetchOutcome<RecentTrack>> {
+        self.get_user_tracks_cancellable::<UserRecentTracks>(
+            "user.getrecenttracks",
+            limit.into(),
+            None,
+            Some(cancel),
+        )
+        .await
+    }
 
-        if let Some(track) = &current_track {
-            serde_json::to_writer_pretty(file, track).map_err(LastFmError::Parse)?;
-        } else {
-            // Write an empty object when no track is playing
-            serde_json::to_writer_pretty(file, &serde_json::json!({}))
-                .map_err(LastFmError::Parse)?;
-        }
+    /// Get recent tracks for a user, oldest first.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<Vec<RecentTrack>>` - The fetched tracks, sorted by ascending timestamp.
+    pub async fn get_user_recent_tracks_chronological(
+        &self,
+        limit: impl Into<TrackLimit>,
+    ) -> Result<Vec<RecentTrack>> {
+        let mut tracks = self.get_user_recent_tracks(limit).await?;
+        sort_by_timestamp(&mut tracks);
+        Ok(tracks)
+    }
 
-        Ok(current_track)
+    /// Get a single page of recent tracks.
+    ///
+    /// Unlike `get_user_recent_tracks`, no request is made to discover the
+    /// total number of tracks: exactly one request is sent for `page`.
+    ///
+    /// # Arguments
+    /// * `page` - The page to fetch, starting at 1.
+    /// * `per_page` - The number of tracks per page (1 to 1000).
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `page` is 0 or `per_page` is out of range.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Result<Vec<RecentTrack>>` - The tracks of that page, newest first.
+    pub async fn get_recent_tracks_page(
+        &self,
+        page: u32,
+        per_page: u32,
+    ) -> Result<Vec<RecentTrack>> {
+        if page == 0 || !(1..=API_MAX_LIMIT).contains(&per_page) {
+            return Err(LastFmError::Other(format!(
+                "Invalid page {page} with {per_page} tracks per page"
+            )));
+        }
+
+        let params = ParamBuilder::new().page(page).limit(per_page).build();
+
+        let response: UserRecentTracks = self.fetch("user.getrecenttracks", &params).await?;
+
+        Ok(response
+            .tracks()
+            .into_iter()
+            .map(RecentTrack::from)
+            .collect())
+    }
+
+    /// Get top tracks for a user.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks to fetch. If None, fetch all available top tracks.
+    /// * `period` - Optional period filter
+    ///   (`Period::Overall`, `Period::SevenDay`, `Period::OneMonth`,
+    ///   `Period::ThreeMonth`, `Period::SixMonth`, `Period::TwelveMonth`)
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<Vec<TopTrack>>` - The fetched tracks.
+    pub async fn get_user_top_tracks(
+        &self,
+        limit: impl Into<TrackLimit>,
+        period: Option<Period>,
+    ) -> Result<Vec<TopTrack>> {
+        let mut params = QueryParams::new();
+        if let Some(p) = period {
+            params.insert("period".to_string(), p.as_api_str().to_string());
+        }
+
+        self.get_user_tracks::<UserTopTracks>("user.gettoptracks", limit.into(), Some(params))
+            .await
+    }
+
+    /// Get top artists for a user.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of artists to fetch. If None, fetch all available top artists.
+    /// * `period` - Optional period filter
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<Vec<TopArtist>>` - The fetched artists, most played first.
+    pub async fn get_user_top_artists(
+        &self,
+        limit: impl Into<TrackLimit>,
+        period: Option<Period>,
+    ) -> Result<Vec<TopArtist>> {
+        let mut params = QueryParams::new();
+        if let Some(p) = period {
+            params.insert("period".to_string(), p.as_api_str().to_string());
+        }
+
+        self.get_user_tracks::<UserTopArtists>("user.gettopartists", limit.into(), Some(params))
+            .await
+    }
+
+    /// Get top albums for a user.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of albums to fetch. If None, fetch all available top albums.
+    /// * `period` - Optional period filter
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<Vec<TopAlbum>>` - The fetched albums, most played first.
+    pub async fn get_user_top_albums(
+        &self,
+        limit: impl Into<TrackLimit>,
+        period: Option<Period>,
+    ) -> Result<Vec<TopAlbum>> {
+        let mut params = QueryParams::new();
+        if let Some(p) = period {
+            params.insert("period".to_string(), p.as_api_str().to_string());
+        }
+
+        self.get_user_tracks::<UserTopAlbums>("user.gettopalbums", limit.into(), Some(params))
+            .await
+    }
+
+    /// Fetch the user's whole library: every recent, loved and all-time top track.
+    ///
+    /// The endpoints are fetched concurrently; configure `with_rate_limit` to
+    /// keep the combined request rate within Last.fm's limits.
+    ///
+    /// # Errors
+    /// Returns an error if any of the API requests fails.
+    ///
+    /// # Returns
+    /// * `Result<LibrarySnapshot>` - The fetched library.
+    pub async fn snapshot_library(&self) -> Result<LibrarySnapshot> {
+        let (recent_tracks, loved_tracks, top_tracks) = futures::try_join!(
+            self.get_user_recent_tracks(TrackLimit::Unlimited),
+            self.get_user_loved_tracks(TrackLimit::Unlimited),
+            self.get_user_top_tracks(TrackLimit::Unlimited, Some(Period::Overall)),
+        )?;
+
+        Ok(LibrarySnapshot {
+            recent_tracks,
+            loved_tracks,
+            top_tracks,
+        })
+    }
+
+    /// Get a user's scrobbles of a specific artist.
+    ///
+    /// # Arguments
+    /// * `artist` - The artist name.
+    /// * `from` - Optional start timestamp (UNIX seconds) of the window.
+    /// * `to` - Optional end timestamp (UNIX seconds) of the window.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `from` or `to` is negative.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Result<Vec<RecentTrack>>` - The fetched scrobbles.
+    pub async fn get_user_artist_tracks(
+        &self,
+        artist: &str,
+        from: Option<i64>,
+        to: Option<i64>,
+        limit: impl Into<TrackLimit>,
+    ) -> Result<Vec<RecentTrack>> {
+        let mut params = QueryParams::new();
+        params.insert("artist".to_string(), artist.to_string());
+        if let Some(from) = from {
+            check_timestamp(from)?;
+            params.insert("startTimestamp".to_string(), from.to_string());
+        }
+        if let Some(to) = to {
+            check_timestamp(to)?;
+            params.insert("endTimestamp".to_string(), to.to_string());
+        }
+
+        self.get_user_tracks::<UserArtistTracks>("user.getartisttracks", limit.into(), Some(params))
+            .await
+    }
+
+    /// Get the week boundaries Last.fm computed charts for.
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Result<Vec<(u32, u32)>>` - The (from, to) timestamps of each week, oldest first.
+    pub async fn get_weekly_chart_list(&self) -> Result<Vec<(u32, u32)>> {
+        let response: UserWeeklyChartList = self
+            .fetch("user.getweeklychartlist", &QueryParams::new())
+            .await?;
+
+        Ok(response
+            .weeklychartlist
+            .chart
+            .into_iter()
+            .map(|range| (range.from, range.to))
+            .collect())
+    }
+
+    /// Get the tracks a user listened to during a week.
+    ///
+    /// Like the other weekly charts, the whole chart comes in a single
+    /// request, without pagination.
+    ///
+    /// # Arguments
+    /// * `from` - Start timestamp (UNIX seconds) of the week.
+    /// * `to` - End timestamp (UNIX seconds) of the week.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `from` or `to` is negative.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Result<Vec<WeeklyTrack>>` - The tracks of the week, by rank.
+    pub async fn get_weekly_track_chart(&self, from: i64, to: i64) -> Result<Vec<WeeklyTrack>> {
+        check_timestamp(from)?;
+        check_timestamp(to)?;
+
+        let params = ParamBuilder::new()
+            .param("from", from)
+            .param("to", to)
+            .build();
+
+        let response: UserWeeklyTrackChart =
+            self.fetch("user.getweeklytrackchart", &params).await?;
+
+        Ok(response.weeklytrackchart.track)
+    }
+
+    /// Get the albums a user listened to during a week.
+    ///
+    /// Last.fm only computes charts for the ranges returned by its weekly
+    /// chart list, so `from` and `to` should be one of those boundaries.
+    ///
+    /// # Arguments
+    /// * `from` - Start timestamp (UNIX seconds) of the week.
+    /// * `to` - End timestamp (UNIX seconds) of the week.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `from` or `to` is negative.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Result<Vec<WeeklyAlbum>>` - The albums of the week, by rank.
+    pub async fn get_weekly_album_chart(&self, from: i64, to: i64) -> Result<Vec<WeeklyAlbum>> {
+        check_timestamp(from)?;
+        check_timestamp(to)?;
+
+        let params = ParamBuilder::new()
+            .param("from", from)
+            .param("to", to)
+            .build();
+
+        let response: UserWeeklyAlbumChart =
+            self.fetch("user.getweeklyalbumchart", &params).await?;
+
+        Ok(response.weeklyalbumchart.album)
+    }
+
+    /// Get the artists a user listened to during a week.
+    ///
+    /// # Arguments
+    /// * `from` - Start timestamp (UNIX seconds) of the week.
+    /// * `to` - End timestamp (UNIX seconds) of the week.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `from` or `to` is negative.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Result<Vec<WeeklyArtist>>` - The artists of the week, by rank.
+    pub async fn get_weekly_artist_chart(&self, from: i64, to: i64) -> Result<Vec<WeeklyArtist>> {
+        check_timestamp(from)?;
+        check_timestamp(to)?;
+
+        let params = ParamBuilder::new()
+            .param("from", from)
+            .param("to", to)
+            .build();
+
+        let response: UserWeeklyArtistChart =
+            self.fetch("user.getweeklyartistchart", &params).await?;
+
+        Ok(response.weeklyartistchart.artist)
+    }
+
+    /// Get global information about a track.
+    ///
+    /// Results are cached in memory, so repeated lookups of the same track
+    /// don't hit the API again.
+    ///
+    /// # Arguments
+    /// * `artist` - The artist name.
+    /// * `track` - The track name.
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<TrackInfo>` - The track information.
+    pub async fn get_track_info(&self, artist: &str, track: &str) -> Result<TrackInfo> {
+        let key = (artist.to_string(), track.to_string());
+        if let Some(info) = self.cached_track_info(&key) {
+            return Ok(info);
+        }
+
+        let mut params = QueryParams::new();
+        params.insert("artist".to_string(), artist.to_string());
+        params.insert("track".to_string(), track.to_string());
+
+        let response: TrackInfoResponse = self.fetch("track.getinfo", &params).await?;
+        let info = TrackInfo::from(response.track);
+
+        if let Ok(mut cache) = self.track_info_cache.lock() {
+            cache.insert(key, info.clone());
+        }
+
+        Ok(info)
+    }
+
+    /// Get global information for a batch of tracks.
+    ///
+    /// Tracks are looked up one after another so duplicates are served from the cache.
+    ///
+    /// # Arguments
+    /// * `tracks` - The (artist, track) pairs to look up.
+    ///
+    /// # Returns
+    /// * `Vec<Result<TrackInfo>>` - The information for each track, in input order.
+    pub async fn get_tracks_info(&self, tracks: &[(String, String)]) -> Vec<Result<TrackInfo>> {
+        let mut infos = Vec::with_capacity(tracks.len());
+
+        for (artist, track) in tracks {
+            infos.push(self.get_track_info(artist, track).await);
+        }
+
+        infos
+    }
+
+    /// Get global information for the tracks of a scrobble history, concurrently.
+    ///
+    /// Each distinct (artist, track) pair is looked up once, all at the same
+    /// time; configure `with_rate_limit` to keep large histories within
+    /// Last.fm's limits. Now-playing entries are looked up like scrobbles.
+    ///
+    /// # Arguments
+    /// * `tracks` - The tracks to enrich.
+    ///
+    /// # Returns
+    /// * `HashMap<(String, String), Result<TrackInfo>>` - The information for each (artist, track) pair.
+    pub async fn enrich_recent_tracks(
+        &self,
+        tracks: &[RecentTrack],
+    ) -> HashMap<(String, String), Result<TrackInfo>> {
+        let keys: HashSet<(String, String)> = tracks
+            .iter()
+            .map(|track| (track.artist.text.clone(), track.name.clone()))
+            .collect();
+
+        join_all(keys.into_iter().map(|(artist, track)| async move {
+            let info = self.get_track_info(&artist, &track).await;
+            ((artist, track), info)
+        }))
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Get the picture of an artist.
+    ///
+    /// # Arguments
+    /// * `artist` - The artist name.
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails.
+    ///
+    /// # Returns
+    /// * `Result<Option<String>>` - The URL of the picture closest to the preferred image size, if any.
+    pub async fn get_artist_image(&self, artist: &str) -> Result<Option<String>> {
+        let params = ParamBuilder::new().param("artist", artist).build();
+
+        let response: ArtistInfoResponse = self.fetch("artist.getinfo", &params).await?;
+
+        Ok(best_image(&response.artist.image, self.preferred_image_size).map(str::to_string))
+    }
+
+    /// Replace each entry's `image_url` according to `source`.
+    ///
+    /// Artist pictures are fetched once per distinct artist.
+    async fn apply_image_source(
+        &self,
+        play_counts: &mut [TrackPlayInfo],
+        source: ImageSource,
+    ) -> Result<()> {
+        if source == ImageSource::Album {
+            return Ok(());
+        }
+
+        let mut artist_images: HashMap<String, Option<String>> = HashMap::new();
+        for info in play_counts.iter_mut() {
+            if !artist_images.contains_key(&info.artist) {
+                let image = self.get_artist_image(&info.artist).await?;
+                artist_images.insert(info.artist.clone(), image);
+            }
+
+            info.image_url.clone_from(&artist_images[&info.artist]);
+        }
+
+        Ok(())
+    }
+
+    /// Look up a track in the `track.getInfo` cache.
+    fn cached_track_info(&self, key: &(String, String)) -> Option<TrackInfo> {
+        self.track_info_cache
+            .lock()
+            .ok()
+            .and_then(|mut cache| cache.get(key))
+    }
+
+    /// Get tracks for a user.
+    ///
+    /// # Arguments
+    /// * `method` - The method to call.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    ///
+    /// # Returns
+    /// * `Result<Vec<T::TrackType>, Error>` - The fetched tracks.
+    async fn get_user_tracks<T: DeserializeOwned + TrackContainer>(
+        &self,
+        method: &str,
+        limit: TrackLimit,
+        additional_params: Option<QueryParams>,
+    ) -> Result<Vec<T::StorageTrackType>> {
+        self.tracks_stream::<T>(method, limit, additional_params)
+            .try_collect()
+            .await
+    }
+
+    /// Resolve how many tracks a fetch returns, from the cache or a discovery request.
+    ///
+    /// # Returns
+    /// * `Result<(u32, u32)>` - The number of tracks to fetch, and the API calls made.
+    async fn resolve_limit<T: DeserializeOwned + TrackContainer>(
+        &self,
+        method: &str,
+        params: &QueryParams,
+        limit: TrackLimit,
+    ) -> Result<(u32, u32)> {
+        let cache_key = Url::new(method).add_args(params.clone()).build();
+        let (total_tracks, api_calls) = if let Some(total) = self.cached_total(&cache_key) {
+            (total, 0)
+        } else {
+            let total = self.discover_total::<T>(method, params).await?;
+            self.cache_total(cache_key, total);
+            (total, 1)
+        };
+
+        // Last.fm occasionally reports a total of 0 while still returning tracks,
+        // so a single page is fetched anyway instead of trusting the total
+        let final_limit = if total_tracks == 0 {
+            limit.resolve(self.max_page_limit)
+        } else {
+            limit.resolve(total_tracks)
+        };
+
+        Ok((final_limit, api_calls))
+    }
+
+    /// Fetch the pages of chunk `chunk_index` concurrently.
+    ///
+    /// # Returns
+    /// * `Result<Vec<T::StorageTrackType>>` - The tracks of the chunk, in page order.
+    async fn fetch_chunk<T: DeserializeOwned + TrackContainer>(
+        &self,
+        method: &str,
+        params: &QueryParams,
+        final_limit: u32,
+        chunk_index: u32,
+    ) -> Result<Vec<T::StorageTrackType>> {
+        // A fetch that fits in a single page asks for exactly the tracks it needs
+        let page_limit = self.max_page_limit.min(final_limit);
+        let chunk_size = self.max_page_limit * CHUNK_MULTIPLIER;
+
+        // Create futures for concurrent API calls within this chunk
+        let api_call_futures: Vec<_> = (0..self.chunk_api_calls(final_limit, chunk_index))
+            .map(|call_index| {
+                let call_limit = (final_limit - chunk_index * chunk_size - call_index * page_limit)
+                    .min(page_limit);
+
+                let page = chunk_index * CHUNK_MULTIPLIER + call_index + 1;
+
+                // Pages are offsets of `page_limit`, so every page must use the same
+                // limit; the last page is trimmed to `call_limit` instead
+                let call_params = ParamBuilder::new()
+                    .extend(params.clone())
+                    .limit(page_limit)
+                    .page(page)
+                    .build();
+
+                async move {
+                    let response: T = self.fetch(method, &call_params).await?;
+                    Ok::<_, LastFmError>(
+                        response
+                            .tracks()
+                            .into_iter()
+                            .take(call_limit as usize)
+                            .map(T::StorageTrackType::from)
+                            .collect::<Vec<_>>(),
+                    )
+                }
+            })
+            .collect();
+
+        // Process all API calls in this chunk concurrently
+        let mut tracks = Vec::new();
+        for result in join_all(api_call_futures).await {
+            tracks.extend(result?);
+        }
+
+        Ok(tracks)
+    }
+
+    /// Stream tracks chunk by chunk instead of collecting them all.
+    ///
+    /// Exceeding the call budget ends the stream with an error.
+    fn tracks_stream<'a, T: DeserializeOwned + TrackContainer + 'a>(
+        &'a self,
+        method: &'a str,
+        limit: TrackLimit,
+        additional_params: Option<QueryParams>,
+    ) -> impl Stream<Item = Result<T::StorageTrackType>> + 'a {
+        self.track_chunks::<T>(method, limit, additional_params, None)
+            .map(|step| {
+                stream::iter(match step {
+                    Ok(ChunkStep::Tracks(tracks)) => tracks.into_iter().map(Ok).collect(),
+                    Ok(ChunkStep::BudgetExceeded { fetched }) => {
+                        vec![Err(LastFmError::Other(format!(
+                            "call budget exceeded after fetching {fetched} tracks"
+                        )))]
+                    }
+                    Ok(ChunkStep::Cancelled) => Vec::new(),
+                    Err(e) => vec![Err(e)],
+                })
+            })
+            .flatten()
+    }
+
+    /// Fetch tracks one chunk at a time, as the stream is consumed.
+    ///
+    /// Every fetch goes through here, so call budgets, progress callbacks,
+    /// cancellation and page-shift deduplication apply to the collecting
+    /// methods and to streams alike. The total is resolved on first poll,
+    /// then each chunk is fetched only once the previous one has been consumed.
+    ///
+    /// # Arguments
+    /// * `method` - The method to call.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// * `cancel` - Optional token used to stop the fetch between chunks.
+    fn track_chunks<'a, T: DeserializeOwned + TrackContainer + 'a>(
+        &'a self,
+        method: &'a str,
+        limit: TrackLimit,
+        additional_params: Option<QueryParams>,
+        cancel: Option<&'a CancellationToken>,
+    ) -> impl Stream<Item = Result<ChunkStep<T::StorageTrackType>>> + 'a {
+        let mut params = self.base_options.clone();
+        if let Some(additional_params) = additional_params {
+            params.extend(additional_params);
+        }
+        let state = ChunkState {
+            params: Arc::new(params),
+            started_at: Instant::now(),
+            final_limit: None,
+            chunk_index: 0,
+            api_calls: 0,
+            fetched: 0,
+            seen: HashSet::new(),
+            done: false,
+        };
+
+        stream::try_unfold(state, move |mut state| async move {
+            if state.done {
+                return Ok(None);
+            }
+
+            let final_limit = if let Some(final_limit) = state.final_limit {
+                final_limit
+            } else {
+                if !self.within_budget(1) {
+                    state.done = true;
+                    return Ok(Some((ChunkStep::BudgetExceeded { fetched: 0 }, state)));
+                }
+                let (final_limit, api_calls) = self
+                    .resolve_limit::<T>(method, &state.params, limit)
+                    .await?;
+                tracing::debug!(
+                    method,
+                    final_limit,
+                    "resolved the number of tracks to fetch"
+                );
+                state.final_limit = Some(final_limit);
+                state.api_calls = api_calls;
+                final_limit
+            };
+
+            let chunk_index = state.chunk_index;
+            let chunk_size = self.max_page_limit * CHUNK_MULTIPLIER;
+            if chunk_index * chunk_size >= final_limit {
+                return Ok(None);
+            }
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                state.done = true;
+                return Ok(Some((ChunkStep::Cancelled, state)));
+            }
+
+            state.api_calls += self.chunk_api_calls(final_limit, chunk_index);
+            if !self.within_budget(state.api_calls) {
+                state.done = true;
+                let fetched = state.fetched;
+                return Ok(Some((ChunkStep::BudgetExceeded { fetched }, state)));
+            }
+
+            let mut tracks = self
+                .fetch_chunk::<T>(method, &state.params, final_limit, chunk_index)
+                .await?;
+            state.fetched += tracks.len();
+            state.chunk_index += 1;
+
+            self.report_progress(ProgressEvent::new(
+                u32::try_from(state.fetched).unwrap_or(u32::MAX),
+                final_limit,
+                chunk_index + 1,
+                final_limit.div_ceil(chunk_size),
+                state.started_at.elapsed(),
+            ));
+
+            if self.dedup_page_shifts {
+                let before = tracks.len();
+                tracks.retain(|track| {
+                    T::page_shift_key(track).is_none_or(|key| state.seen.insert(key))
+                });
+                let removed = before - tracks.len();
+                if removed > 0 {
+                    tracing::warn!(
+                        method,
+                        removed,
+                        "pages shifted during the fetch, dropped duplicate tracks"
+                    );
+                }
+            }
+
+            Ok(Some((ChunkStep::Tracks(tracks), state)))
+        })
+    }
+
+    /// Request a single track to learn how many tracks a request would return.
+    ///
+    /// The response is checked against the expected schema on the way.
+    ///
+    /// # Errors
+    /// * `LastFmError::SchemaMismatch` - In strict mode, if the response doesn't match the schema.
+    async fn discover_total<T: DeserializeOwned + TrackContainer>(
+        &self,
+        method: &str,
+        params: &QueryParams,
+    ) -> Result<u32> {
+        let base_params = ParamBuilder::new()
+            .extend(params.clone())
+            .limit(1)
+            .page(1)
+            .build();
+
+        let initial_response: serde_json::Value = self.fetch(method, &base_params).await?;
+        let issues = probe_schema(&initial_response, T::ROOT_KEY, T::ITEM_KEY);
+        if !issues.is_empty() {
+            if self.strict_schema {
+                return Err(LastFmError::SchemaMismatch(issues));
+            }
+            tracing::warn!(method, issues = %issues.join("; "), "unexpected response schema");
+        }
+
+        Ok(T::deserialize(&initial_response)?.total_tracks())
+    }
+
+    /// The total cached for `key`, if it is younger than the cache TTL.
+    fn cached_total(&self, key: &str) -> Option<u32> {
+        if self.total_cache_ttl.is_zero() {
+            return None;
+        }
+
+        self.total_cache
+            .lock()
+            .ok()?
+            .get(key)
+            .filter(|(_, fetched_at)| fetched_at.elapsed() < self.total_cache_ttl)
+            .map(|(total, _)| *total)
+    }
+
+    /// Remember the total discovered for `key`, if the cache is enabled.
+    fn cache_total(&self, key: String, total: u32) {
+        if self.total_cache_ttl.is_zero() {
+            return;
+        }
+
+        if let Ok(mut cache) = self.total_cache.lock() {
+            cache.retain(|_, (_, fetched_at)| fetched_at.elapsed() < self.total_cache_ttl);
+            cache.insert(key, (total, tokio::time::Instant::now()));
+        }
+    }
+
+    /// Get tracks for a user, checking `cancel` between chunks.
+    ///
+    /// # Arguments
+    /// * `method` - The method to call.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// * `cancel` - Optional token used to stop the fetch between chunks.
+    ///
+    /// # Returns
+    /// * `Result<FetchOutcome<T::StorageTrackType>>` - The fetched tracks.
+    async fn get_user_tracks_cancellable<T: DeserializeOwned + TrackContainer>(
+        &self,
+        method: &str,
+        limit: TrackLimit,
+        additional_params: Option<QueryParams>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<FetchOutcome<T::StorageTrackType>> {
+        let chunks = self.track_chunks::<T>(method, limit, additional_params, cancel);
+        futures::pin_mut!(chunks);
+
+        let mut all_tracks = Vec::new();
+        while let Some(step) = chunks.try_next().await? {
+            match step {
+                ChunkStep::Tracks(tracks) => all_tracks.extend(tracks),
+                ChunkStep::BudgetExceeded { .. } => {
+                    return Ok(FetchOutcome::BudgetExceeded(all_tracks))
+                }
+                ChunkStep::Cancelled => return Ok(FetchOutcome::Cancelled(all_tracks)),
+            }
+        }
+
+        Ok(FetchOutcome::Complete(all_tracks))
+    }
+
+    /// Check that the API key and username are accepted by Last.fm.
+    ///
+    /// Makes a single `user.getinfo` request, which is cheap enough to run at startup.
+    ///
+    /// # Errors
+    /// * `LastFmError::InvalidApiKey` - If the API key is rejected.
+    /// * `LastFmError::UserNotFound` - If the configured user does not exist.
+    /// * `LastFmError` - If the request fails for any other reason.
+    pub async fn check_connection(&self) -> Result<()> {
+        match self
+            .fetch::<serde_json::Value>("user.getinfo", &QueryParams::new())
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(LastFmError::Api(e)) if e.error == 10 => Err(LastFmError::InvalidApiKey),
+            Err(LastFmError::Api(e)) if e.error == 6 => {
+                Err(LastFmError::UserNotFound(self.username().to_string()))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Call any Last.fm API method and return the raw JSON response.
+    ///
+    /// The API key, format and username are added like for every other request,
+    /// which makes this an escape hatch for endpoints not modelled by this crate.
+    ///
+    /// # Arguments
+    /// * `method` - The method to call (e.g. `user.getinfo`).
+    /// * `params` - Additional parameters to pass to the API.
+    ///
+    /// # Errors
+    /// Returns an error if the API request fails or the response is not JSON.
+    ///
+    /// # Returns
+    /// * `Result<serde_json::Value>` - The raw response.
+    pub async fn fetch_raw(
+        &self,
+        method: &str,
+        params: &[(&str, &str)],
+    ) -> Result<serde_json::Value> {
+        let params: QueryParams = params
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect();
+
+        self.fetch(method, &params).await
+    }
+
+    /// Love a track on behalf of the authenticated user.
+    ///
+    /// Loving a track that is already loved succeeds without changing anything.
+    ///
+    /// # Arguments
+    /// * `artist` - The artist name.
+    /// * `track` - The track name.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If the handler has no session (see `with_session`).
+    /// * `LastFmError::Api` - If the API returns an error.
+    pub async fn love_track(&self, artist: &str, track: &str) -> Result<()> {
+        let params = ParamBuilder::new()
+            .param("artist", artist)
+            .param("track", track)
+            .build();
+
+        self.post_signed("track.love", &params).await
+    }
+
+    /// Love a batch of tracks on behalf of the authenticated user.
+    ///
+    /// Tracks are loved one after another, paced by the handler's rate limit or
+    /// `LOVE_REQUESTS_PER_SECOND` when none is set. A failure doesn't stop the
+    /// batch. Pairs repeated in the input (ignoring case) are only sent once and
+    /// share the outcome of their first occurrence.
+    ///
+    /// # Arguments
+    /// * `tracks` - The (artist, track) pairs to love.
+    ///
+    /// # Returns
+    /// * `Vec<Result<()>>` - The outcome for each pair, in input order.
+    pub async fn love_tracks(&self, tracks: &[(String, String)]) -> Vec<Result<()>> {
+        let throttle = self
+            .rate_limiter
+            .is_none()
+            .then(|| RateLimiter::new(LOVE_REQUESTS_PER_SECOND));
+        let mut loved: HashMap<(String, String), bool> = HashMap::new();
+        let mut results = Vec::with_capacity(tracks.len());
+
+        for (artist, track) in tracks {
+            let key = (artist.to_lowercase(), track.to_lowercase());
+            if let Some(&succeeded) = loved.get(&key) {
+                results.push(if succeeded {
+                    Ok(())
+                } else {
+                    Err(LastFmError::Other(format!(
+                        "{artist} - {track} could not be loved, see its first occurrence"
+                    )))
+                });
+                continue;
+            }
+
+            if let Some(throttle) = &throttle {
+                throttle.acquire().await;
+            }
+            let result = self.love_track(artist, track).await;
+            loved.insert(key, result.is_ok());
+            results.push(result);
+        }
+
+        results
+    }
+
+    /// Send a signed write request to the `LastFM` API.
+    ///
+    /// # Arguments
+    /// * `method` - The method to call.
+    /// * `params` - The parameters to pass to the API.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If the handler has no session.
+    /// * `LastFmError::Api` - If the API returns an error.
+    async fn post_signed(&self, method: &str, params: &QueryParams) -> Result<()> {
+        let Some(session) = &self.session else {
+            return Err(LastFmError::Other(format!(
+                "{method} requires an authenticated session, see `with_session`"
+            )));
+        };
+
+        let mut final_params = ParamBuilder::new()
+            .method(method)
+            .param(
+                "api_key",
+                self.base_options.get("api_key").map_or("", String::as_str),
+            )
+            .param("sk", &session.session_key)
+            .extend(params.clone())
+            .build();
+        let api_sig = sign_params(&final_params, &session.api_secret);
+        final_params.insert("api_sig".to_string(), api_sig);
+        final_params.insert("format".to_string(), "json".to_string());
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+
+        let response = self
+            .client
+            .post(self.url.base())
+            .header(reqwest::header::USER_AGENT, &self.user_agent)
+            .form(&final_params)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error: LastFmErrorResponse = response.json().await?;
+            return Err(LastFmError::Api(error));
+        }
+
+        let body = response.bytes().await?;
+
+        // Last.fm sometimes reports errors with a 200 status
+        if let Ok(error) = serde_json::from_slice::<LastFmErrorResponse>(&body) {
+            return Err(LastFmError::Api(error));
+        }
+
+        Ok(())
+    }
+
+    /// Build the URL a request to an API method would be sent to.
+    ///
+    /// The URL is assembled exactly like for real requests: the API key,
+    /// format and username are merged in, parameters are sorted by key and
+    /// form-encoded. Nothing is sent, and API key rotation doesn't advance.
+    ///
+    /// # Arguments
+    /// * `method` - The method to call (e.g. `user.getinfo`).
+    /// * `params` - Additional parameters to pass to the API.
+    ///
+    /// # Returns
+    /// * `String` - The full request URL, including the API key.
+    #[must_use]
+    pub fn build_url(&self, method: &str, params: &[(&str, &str)]) -> String {
+        let params: QueryParams = params
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect();
+
+        self.request_url(method, &params, self.upcoming_api_key())
+            .build()
+    }
+
+    /// Assemble the full URL of a request, using `api_key` instead of the configured key if set.
+    fn request_url(&self, method: &str, params: &QueryParams, api_key: Option<&str>) -> Url {
+        let mut final_params = ParamBuilder::new()
+            .extend(self.base_options.clone())
+            .method(method)
+            .extend(params.clone());
+        if let Some(api_key) = api_key {
+            final_params = final_params.param("api_key", api_key);
+        }
+
+        self.url.clone().add_args(final_params.build())
+    }
+
+    /// Fetch data from the `LastFM` API, retrying transient failures.
+    ///
+    /// # Arguments
+    /// * `method` - The method to call.
+    /// * `params` - The parameters to pass to the API.
+    ///
+    /// # Returns
+    /// * `Result<T, Error>` - The fetched data.
+    async fn fetch<T: DeserializeOwned>(&self, method: &str, params: &QueryParams) -> Result<T> {
+        let mut attempt = 0;
+
+        loop {
+            match self.fetch_once(method, params).await {
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    let delay = self.retry_delay(attempt);
+                    tracing::warn!(method, error = %e, ?delay, "request failed, retrying");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Exponential backoff before retry number `attempt` (from 0), with up to 50% jitter.
+    fn retry_delay(&self, attempt: u32) -> Duration {
+        let delay = self.retry_base_delay.saturating_mul(1 << attempt.min(16));
+        let max_jitter = u64::try_from(delay.as_millis() / 2).unwrap_or(u64::MAX);
+        let jitter = RandomState::new().build_hasher().finish() % (max_jitter + 1);
+
+        delay + Duration::from_millis(jitter)
+    }
+
+    /// Send a single request to the `LastFM` API.
+    async fn fetch_once<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &QueryParams,
+    ) -> Result<T> {
+        let url = self.request_url(method, params, self.next_api_key());
+
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await;
+        }
+
+        #[cfg(feature = "testing")]
+        if let Some(transport) = &self.transport {
+            return parse_response(transport.get(url.query_params())?.as_bytes());
+        }
+
+        if self.verbose {
+            tracing::trace!(url = %url.build_redacted(), "sending request");
+        }
+
+        let response = self
+            .client
+            .get(url.build())
+            .header(reqwest::header::USER_AGENT, &self.user_agent)
+            .send()
+            .await?;
+
+        let status = response.status();
+        let status_error = response.error_for_status_ref().err();
+        let body = response.bytes().await?;
+
+        if self.verbose {
+            let preview = String::from_utf8_lossy(&body[..body.len().min(VERBOSE_BODY_BYTES)]);
+            tracing::trace!(%status, len = body.len(), body = %preview, "received response");
+        }
+
+        // Check if the response is an error, keeping the status if Last.fm gave no details
+        if let Some(status_error) = status_error {
+            return Err(match serde_json::from_slice::<LastFmErrorResponse>(&body) {
+                Ok(error) => LastFmError::Api(error),
+                Err(_) => status_error.into(),
+            });
+        }
+
+        parse_response(&body)
+    }
+
+    /// Get and save recent tracks to a file, or any other `TrackStore`.
+    ///
+    /// # Arguments
+    /// * `store` - The directory, or other store, to save the tracks in.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// * `format` - The file format to save the tracks in.
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error.
+    /// * `LastFmError::Io` - If there is an error saving the file.
+    ///
+    /// # Returns
+    /// * `Result<String>` - Name of the saved collection, e.g. the file name
+    ///   relative to a `DataStore` root, as taken by `update_tracks_store`.
+    pub async fn get_and_save_recent_tracks<S: TrackStore>(
+        &self,
+        store: &S,
+        limit: impl Into<TrackLimit>,
+        format: FileFormat,
+        filename_prefix: &str,
+    ) -> Result<String> {
+        let tracks = self.get_user_recent_tracks(limit).await?;
+        tracing::debug!(tracks = tracks.len(), "saving recent tracks");
+        let filename = store
+            .save(&tracks, &format, filename_prefix)
+            .map_err(LastFmError::Io)?;
+        Ok(filename)
+    }
+
+    /// Get and save loved tracks to a file.
+    ///
+    /// # Arguments
+    /// * `store` - The directory to save the file in.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    /// * `format` - The file format to save the tracks in.
+    ///
+    /// # Errors
+    /// * `FileError` - If there was an error reading or writing the file
+    /// * `InvalidUtf8` - If the file path is not valid UTF-8
+    ///
+    /// # Returns
+    /// * `Result<String, Box<dyn std::error::Error>>` - The filename of the saved file.
+    pub async fn get_and_save_loved_tracks(
+        &self,
+        store: &DataStore,
+        limit: impl Into<TrackLimit>,
+        format: FileFormat,
+    ) -> Result<String> {
+        let tracks = self.get_user_loved_tracks(limit).await?;
+        let filename = store
+            .save_rows(&tracks, &format, "loved_tracks")
+            .map_err(LastFmError::Io)?;
+        Ok(filename)
+    }
+
+    /// Get and save top tracks to a file.
+    ///
+    /// # Arguments
+    /// * `store` - The directory to save the file in.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all available top tracks.
+    /// * `period` - Optional period filter
+    /// * `format` - The file format to save the tracks in.
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error.
+    /// * `LastFmError::Io` - If there is an error saving the file.
+    ///
+    /// # Returns
+    /// * `Result<String>` - The filename of the saved file.
+    pub async fn get_and_save_top_tracks(
+        &self,
+        store: &DataStore,
+        limit: impl Into<TrackLimit>,
+        period: Option<Period>,
+        format: FileFormat,
+    ) -> Result<String> {
+        let tracks = self.get_user_top_tracks(limit, period).await?;
+        let filename = store
+            .save_rows(&tracks, &format, "top_tracks")
+            .map_err(LastFmError::Io)?;
+        Ok(filename)
+    }
+
+    /// Get and save top artists to a file.
+    ///
+    /// # Arguments
+    /// * `store` - The directory to save the file in.
+    /// * `limit` - The number of artists to fetch. If None, fetch all available top artists.
+    /// * `period` - Optional period filter
+    /// * `format` - The file format to save the artists in.
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error.
+    /// * `LastFmError::Io` - If there is an error saving the file.
+    ///
+    /// # Returns
+    /// * `Result<String>` - The filename of the saved file.
+    pub async fn get_and_save_top_artists(
+        &self,
+        store: &DataStore,
+        limit: impl Into<TrackLimit>,
+        period: Option<Period>,
+        format: FileFormat,
+    ) -> Result<String> {
+        let artists = self.get_user_top_artists(limit, period).await?;
+        let filename = store
+            .save_rows(&artists, &format, "top_artists")
+            .map_err(LastFmError::Io)?;
+        Ok(filename)
+    }
+
+    /// Get and save top albums to a file.
+    ///
+    /// # Arguments
+    /// * `store` - The directory to save the file in.
+    /// * `limit` - The number of albums to fetch. If None, fetch all available top albums.
+    /// * `period` - Optional period filter
+    /// * `format` - The file format to save the albums in.
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error.
+    /// * `LastFmError::Io` - If there is an error saving the file.
+    ///
+    /// # Returns
+    /// * `Result<String>` - The filename of the saved file.
+    pub async fn get_and_save_top_albums(
+        &self,
+        store: &DataStore,
+        limit: impl Into<TrackLimit>,
+        period: Option<Period>,
+        format: FileFormat,
+    ) -> Result<String> {
+        let albums = self.get_user_top_albums(limit, period).await?;
+        let filename = store
+            .save_rows(&albums, &format, "top_albums")
+            .map_err(LastFmError::Io)?;
+        Ok(filename)
+    }
+
+    /// Get recent tracks for a user since a given timestamp.
+    ///
+    /// # Arguments
+    /// * `timestamp` - The timestamp (UNIX seconds) to fetch tracks since.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `timestamp` is negative.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Vec<RecentTrack>` - The fetched tracks.
+    #[allow(dead_code)]
+    pub async fn get_user_recent_tracks_since(
+        &self,
+        timestamp: i64,
+        limit: impl Into<TrackLimit>,
+    ) -> Result<Vec<RecentTrack>> {
+        check_timestamp(timestamp)?;
+
+        let mut params = QueryParams::new();
+        params.insert("from".to_string(), timestamp.to_string());
+
+        self.get_user_tracks::<UserRecentTracks>("user.getrecenttracks", limit.into(), Some(params))
+            .await
+    }
+
+    /// Get recent tracks for a user between two timestamps.
+    ///
+    /// # Arguments
+    /// * `from` - Start timestamp (UNIX seconds) of the range.
+    /// * `to` - End timestamp (UNIX seconds) of the range.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `from` or `to` is negative.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Result<Vec<RecentTrack>>` - The fetched tracks.
+    pub async fn get_user_recent_tracks_between(
+        &self,
+        from: i64,
+        to: i64,
+        limit: impl Into<TrackLimit>,
+    ) -> Result<Vec<RecentTrack>> {
+        check_timestamp(from)?;
+        check_timestamp(to)?;
+
+        let mut params = QueryParams::new();
+        params.insert("from".to_string(), from.to_string());
+        params.insert("to".to_string(), to.to_string());
+
+        self.get_user_tracks::<UserRecentTracks>("user.getrecenttracks", limit.into(), Some(params))
+            .await
+    }
+
+    /// Get every scrobble of a given day.
+    ///
+    /// The day runs from 00:00:00 to 23:59:59 UTC, so scrobbles are bucketed
+    /// by their UTC date rather than the listener's local date.
+    ///
+    /// # Arguments
+    /// * `year` - The year.
+    /// * `month` - The month (1-12).
+    /// * `day` - The day of the month.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If the date is invalid.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Result<Vec<RecentTrack>>` - The scrobbles of that day.
+    pub async fn get_scrobbles_on(
+        &self,
+        year: i32,
+        month: u32,
+        day: u32,
+    ) -> Result<Vec<RecentTrack>> {
+        let (from, to) = utc_day_bounds(year, month, day)?;
+
+        self.get_user_recent_tracks_between(from, to, TrackLimit::Unlimited)
+            .await
+    }
+
+    /// Get loved tracks for a user since a given timestamp.
+    ///
+    /// # Arguments
+    /// * `timestamp` - The timestamp (UNIX seconds) to fetch tracks since.
+    /// * `limit` - The number of tracks to fetch. If None, fetch all tracks.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `timestamp` is negative.
+    /// * `LastFmError::Api` - If the API returns an error.
+    ///
+    /// # Returns
+    /// * `Vec<LovedTrack>` - The fetched tracks.
+    #[allow(dead_code)]
+    pub async fn get_user_loved_tracks_since(
+        &self,
+        timestamp: i64,
+        limit: impl Into<TrackLimit>,
+    ) -> Result<Vec<LovedTrack>> {
+        check_timestamp(timestamp)?;
+
+        let tracks = self.get_user_loved_tracks(limit).await?;
+
+        Ok(tracks
+            .into_iter()
+            .filter(|track| {
+                track
+                    .get_timestamp()
+                    .is_some_and(|uts| i64::from(uts) > timestamp)
+            })
+            .collect())
+    }
+
+    /// Update a tracks file with new tracks.
+    ///
+    /// Tracks without a date are never appended: only the currently playing
+    /// track lacks one, and it would otherwise be duplicated once Last.fm
+    /// records it as a scrobble with a real timestamp. A now-playing entry
+    /// already stored at the end of the file is replaced by its scrobble, and
+    /// only scrobbles newer than the latest stored one are appended (Last.fm
+    /// returns the scrobble at the boundary timestamp again).
+    ///
+    /// Gaps longer than `BACKFILL_WINDOW_SECS` are filled one window at a time,
+    /// see `update_tracks_file_windowed`.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to update.
+    ///
+    /// # Errors
+    /// * `FileError` - If there was an error reading or writing the file
+    ///
+    /// # Returns
+    /// * `Result<String, Box<dyn std::error::Error>>` - The filename of the updated file.
+    #[allow(dead_code)]
+    pub async fn update_tracks_file<T: DeserializeOwned + Serialize + Timestamped>(
+        &self,
+        file_path: &Path,
+    ) -> Result<String> {
+        self.update_tracks_file_windowed::<T>(file_path, BACKFILL_WINDOW_SECS)
+            .await
+    }
+
+    /// Update a tracks file with new tracks, fetching large gaps in bounded windows.
+    ///
+    /// When the latest stored scrobble is more than `window_secs` old, the
+    /// range up to now is split into consecutive, non-overlapping windows
+    /// fetched oldest first. Each window is appended as soon as it is fetched,
+    /// so an interrupted update resumes from the last completed window.
+    /// Files without any timestamped track are filled in a single fetch.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to update.
+    /// * `window_secs` - Span of each fetched window, in seconds.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `window_secs` is not positive.
+    /// * `FileError` - If there was an error reading or writing the file
+    ///
+    /// # Returns
+    /// * `Result<String>` - The filename of the updated file.
+    pub async fn update_tracks_file_windowed<T: DeserializeOwned + Serialize + Timestamped>(
+        &self,
+        file_path: &Path,
+        window_secs: i64,
+    ) -> Result<String> {
+        // Get the most recent timestamp from the file
+        let last_timestamp = AnalysisHandler::get_most_recent_timestamp::<T>(file_path)?;
+
+        self.append_newer_scrobbles(last_timestamp, window_secs, |recent_tracks| {
+            Self::append_reconciled(recent_tracks, file_path)
+        })
+        .await
+    }
+
+    /// Update a collection of recent tracks in any `TrackStore`.
+    ///
+    /// Only scrobbles newer than the latest one stored are appended, so the
+    /// scrobble at the boundary timestamp, which Last.fm returns again, and
+    /// the currently playing track are skipped. Gaps longer than
+    /// `BACKFILL_WINDOW_SECS` are filled one window at a time, as in
+    /// `update_tracks_file`. Unlike `update_tracks_file`, a now-playing entry
+    /// stored by an earlier save is left as is rather than replaced by its
+    /// scrobble.
+    ///
+    /// # Arguments
+    /// * `store` - The store holding the collection.
+    /// * `name` - Name of the collection to update, as returned by `TrackStore::save`.
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error.
+    /// * `LastFmError::Io` - If the store cannot be read or written.
+    ///
+    /// # Returns
+    /// * `Result<String>` - Name of the updated collection.
+    pub async fn update_tracks_store<S: TrackStore>(
+        &self,
+        store: &S,
+        name: &str,
+    ) -> Result<String> {
+        let last_timestamp = store.latest_timestamp(name).map_err(LastFmError::Io)?;
+
+        self.append_newer_scrobbles(
+            last_timestamp.map(i64::from),
+            BACKFILL_WINDOW_SECS,
+            |recent_tracks| store.append(&recent_tracks, name).map_err(LastFmError::Io),
+        )
+        .await
+    }
+
+    /// Fetch the scrobbles newer than `last_timestamp` and pass them to `append`.
+    ///
+    /// When `last_timestamp` is more than `window_secs` old, the range up to
+    /// now is split into windows fetched oldest first, each appended as soon
+    /// as it is fetched.
+    ///
+    /// # Errors
+    /// * `LastFmError::Other` - If `window_secs` is not positive.
+    ///
+    /// # Returns
+    /// * `Result<String>` - What the last call to `append` returned.
+    async fn append_newer_scrobbles(
+        &self,
+        last_timestamp: Option<i64>,
+        window_secs: i64,
+        mut append: impl FnMut(Vec<RecentTrack>) -> Result<String>,
+    ) -> Result<String> {
+        if window_secs <= 0 {
+            return Err(LastFmError::Other(
+                "Backfill window must be positive".to_string(),
+            ));
+        }
+
+        let now = Utc::now().timestamp();
+        let Some(mut latest) = last_timestamp.filter(|ts| now - ts > window_secs) else {
+            let recent_tracks = newer_scrobbles(
+                self.get_user_recent_tracks_since(last_timestamp.unwrap_or(0), None)
+                    .await?,
+                last_timestamp,
+            );
+
+            return append(recent_tracks);
+        };
+
+        // The gap is longer than a window, so there is at least one
+        let mut appended = String::new();
+        for (from, to) in backfill_windows(latest + 1, now, window_secs) {
+            let recent_tracks = newer_scrobbles(
+                self.get_user_recent_tracks_between(from, to, TrackLimit::Unlimited)
+                    .await?,
+                Some(latest),
+            );
+            latest = recent_tracks
+                .iter()
+                .filter_map(Timestamped::get_timestamp)
+                .map(i64::from)
+                .fold(latest, i64::max);
+
+            appended = append(recent_tracks)?;
+        }
+
+        Ok(appended)
+    }
+
+    /// Append scrobbles to a tracks file, first finalizing a stored now-playing entry.
+    ///
+    /// See `FileHandler::reconcile_now_playing`.
+    fn append_reconciled(mut recent_tracks: Vec<RecentTrack>, file_path: &Path) -> Result<String> {
+        if let Some(index) = FileHandler::reconcile_now_playing(&recent_tracks, file_path)? {
+            recent_tracks.remove(index);
+        }
+
+        // Append the new tracks to the file
+        Ok(match FileFormat::from_path(file_path) {
+            Some(FileFormat::Csv) => FileHandler::append_csv(&recent_tracks, file_path),
+            _ => FileHandler::append(&recent_tracks, file_path),
+        }?)
+    }
+
+    /// Export play counts for the last X songs with additional track information
+    ///
+    /// # Arguments
+    /// * `store` - The directory to save the file in
+    /// * `limit` - Number of recent tracks to analyze
+    /// * `image_source` - Whether `image_url` holds the album art or the artist picture
+    ///
+    /// # Errors
+    /// * `FileError` - If there was an error reading or writing the file
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path to the saved JSON file containing play counts
+    pub async fn export_recent_play_counts(
+        &self,
+        store: &DataStore,
+        limit: impl Into<TrackLimit>,
+        image_source: ImageSource,
+    ) -> Result<String> {
+        // Get recent tracks
+        let tracks = self.get_user_recent_tracks(limit.into()).await?;
+
+        // Count plays and collect track info
+        let mut play_counts_vec = count_plays(tracks, self.preferred_image_size);
+        self.apply_image_source(&mut play_counts_vec, image_source)
+            .await?;
+
+        // Save to file
+        let filename = store
+            .save(&[play_counts_vec], &FileFormat::Json, "play_counts")
+            .map_err(LastFmError::Io)?;
+
+        Ok(filename)
+    }
+
+    /// Update or create a file with play counts for the last X songs with additional track information
+    ///
+    /// # Arguments
+    /// * `limit` - Number of recent tracks to analyze
+    /// * `file_path` - Path to the file to update/create
+    /// * `image_source` - Whether `image_url` holds the album art or the artist picture
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error
+    /// * `LastFmError::Io` - If there is an error reading or writing the file
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path to the updated/created JSON file containing play counts
+    pub async fn update_recent_play_counts(
+        &self,
+        limit: impl Into<TrackLimit>,
+        file_path: &str,
+        image_source: ImageSource,
+    ) -> Result<String> {
+        // Get recent tracks
+        let tracks = self.get_user_recent_tracks(limit.into()).await?;
+        let mut play_counts_vec = count_plays(tracks, self.preferred_image_size);
+        self.apply_image_source(&mut play_counts_vec, image_source)
+            .await?;
+
+        // Create the file (overwriting if it exists)
+        let file = File::create(file_path).map_err(LastFmError::Io)?;
+        serde_json::to_writer_pretty(file, &play_counts_vec).map_err(LastFmError::Parse)?;
+
+        Ok(file_path.to_string())
+    }
+
+    /// Export play counts for the last X songs as a self-contained HTML snippet
+    ///
+    /// The snippet lists the currently playing track first, then the most played
+    /// tracks, each with its album art, and can be embedded as-is in a web page.
+    ///
+    /// # Arguments
+    /// * `limit` - Number of recent tracks to analyze
+    /// * `file_path` - Path to the HTML file to create (overwriting if it exists)
+    /// * `image_source` - Whether entries show the album art or the artist picture
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error
+    /// * `LastFmError::Io` - If there is an error writing the file
+    ///
+    /// # Returns
+    /// * `Result<String>` - Path to the created HTML file
+    pub async fn export_play_counts_html(
+        &self,
+        limit: impl Into<TrackLimit>,
+        file_path: &str,
+        image_source: ImageSource,
+    ) -> Result<String> {
+        let tracks = self.get_user_recent_tracks(limit.into()).await?;
+        let mut play_counts = count_plays(tracks, self.preferred_image_size);
+        self.apply_image_source(&mut play_counts, image_source)
+            .await?;
+        let html = render_play_counts_html(&play_counts);
+
+        std::fs::write(file_path, html).map_err(LastFmError::Io)?;
+
+        Ok(file_path.to_string())
+    }
+
+    /// Check if the user is currently playing a track
+    ///
+    /// # Errors
+    /// * `LastFmError` - If there was an error communicating with Last.fm
+    ///
+    /// # Returns
+    /// * `Result<Option<RecentTrack>>` - The currently playing track if any
+    pub async fn is_currently_playing(&self) -> Result<Option<RecentTrack>> {
+        let params = ParamBuilder::new().limit(1).build();
+
+        let tracks = self
+            .get_user_tracks::<UserRecentTracks>(
+                "user.getrecenttracks",
+                TrackLimit::Limited(1),
+                Some(params),
+            )
+            .await?;
+
+        // Check if the first track has the "now playing" attribute
+        Ok(tracks.first().and_then(|track| {
+            if track
+                .attr
+                .as_ref()
+                .is_some_and(|val| val.nowplaying == "true")
+            {
+                Some(track.clone())
+            } else {
+                None
+            }
+        }))
+    }
+
+    /// Update a file with the currently playing track information
+    ///
+    /// The file is only replaced after a successful API response, and is
+    /// written atomically, so a failed request leaves the previous contents intact.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to update
+    ///
+    /// # Errors
+    /// * `LastFmError::Api` - If the API returns an error
+    /// * `LastFmError::Io` - If there is an error reading or writing the file
+    /// * `LastFmError::Parse` - If there is an error parsing the JSON
+    ///
+    /// # Returns
+    /// * `Result<Option<RecentTrack>>` - The currently playing track if any
+    pub async fn update_currently_listening(&self, file_path: &str) -> Result<Option<RecentTrack>> {
+        let current_track = self.is_currently_playing().await?;
+
+        let contents = if let Some(track) = &current_track {
+            serde_json::to_vec_pretty(track).map_err(LastFmError::Parse)?
+        } else {
+            // Write an empty object when no track is playing
+            serde_json::to_vec_pretty(&serde_json::json!({})).map_err(LastFmError::Parse)?
+        };
+
+        FileHandler::write_atomic(file_path, &contents).map_err(LastFmError::Io)?;
+
+        Ok(current_track)
+    }
+
+    /// Keep a file updated with the currently playing track until shut down.
+    ///
+    /// Calls `update_currently_listening` every `interval`. A failed update is
+    /// reported and retried later: the delay doubles after each consecutive
+    /// failure, up to `WATCH_MAX_BACKOFF` times the interval, and goes back to
+    /// `interval` after the next success.
+    ///
+    /// # Arguments
+    /// * `file_path` - Path to the file to update
+    /// * `interval` - Delay between two updates
+    /// * `shutdown` - Token stopping the loop once cancelled
+    pub async fn watch_now_playing(
+        &self,
+        file_path: &str,
+        interval: Duration,
+        shutdown: CancellationToken,
+    ) {
+        let max_delay = interval * WATCH_MAX_BACKOFF;
+        let mut delay = interval;
+
+        while !shutdown.is_cancelled() {
+            delay = match self.update_currently_listening(file_path).await {
+                Ok(_) => interval,
+                Err(e) => {
+                    tracing::warn!(file_path, error = %e, "failed to update currently listening");
+                    (delay * 2).min(max_delay)
+                }
+            };
+
+            tokio::select! {
+                () = shutdown.cancelled() => break,
+                () = tokio::time::sleep(delay) => {}
+            }
+        }
+    }
+
+    /// Serve the currently playing track as JSON over HTTP until shut down.
+    ///
+    /// Every `GET /` returns a `NowPlayingStatus`, refreshed from Last.fm every
+    /// `interval` rather than on each request, so page views never hit the API.
+    /// A failed refresh is reported and the previous state kept. Responses allow
+    /// any origin, so a personal site can fetch them directly from the browser.
+    ///
+    /// # Arguments
+    /// * `addr` - The address to listen on
+    /// * `interval` - Delay between two refreshes
+    /// * `shutdown` - Token stopping the server once cancelled
+    ///
+    /// # Errors
+    /// * `LastFmError::Io` - If the address cannot be bound or the server fails
+    #[cfg(feature = "server")]
+    pub async fn serve_now_playing(
+        &self,
+        addr: std::net::SocketAddr,
+        interval: Duration,
+        shutdown: CancellationToken,
+    ) -> Result<()> {
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .map_err(LastFmError::Io)?;
+
+        self.serve_now_playing_on(listener, interval, shutdown)
+            .await
+    }
+
+    /// Serve the now-playing state on an already bound listener.
+    #[cfg(feature = "server")]
+    async fn serve_now_playing_on(
+        &self,
+        listener: tokio::net::TcpListener,
+        interval: Duration,
+        shutdown: CancellationToken,
+    ) -> Result<()> {
+        let status = SharedNowPlaying::default();
+        let app = axum::Router::new()
+            .route("/", axum::routing::get(now_playing_response))
+            .with_state(Arc::clone(&status));
+
+        let refresh = async {
+            while !shutdown.is_cancelled() {
+                match self.is_currently_playing().await {
+                    Ok(track) => {
+                        *status.write().await = NowPlayingStatus {
+                            track,
+                            updated_at: Some(Utc::now().timestamp()),
+                        };
+                    }
+                    Err(e) => tracing::warn!(error = %e, "failed to refresh now playing"),
+                }
+
+                tokio::select! {
+                    () = shutdown.cancelled() => break,
+                    () = tokio::time::sleep(interval) => {}
+                }
+            }
+        };
+        let server = async {
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown.clone().cancelled_owned())
+                .await
+        };
+
+        let ((), served) = tokio::join!(refresh, server);
+        served.map_err(LastFmError::Io)
+    }
+}
+
+/// Respond with the current now-playing state.
+#[cfg(feature = "server")]
+async fn now_playing_response(
+    axum::extract::State(status): axum::extract::State<SharedNowPlaying>,
+) -> impl axum::response::IntoResponse {
+    (
+        [(axum::http::header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")],
+        axum::Json(status.read().await.clone()),
+    )
+}
+
+/// Whether a failed request may succeed if sent again.
+fn is_retryable(error: &LastFmError) -> bool {
+    match error {
+        LastFmError::Api(e) => RETRYABLE_API_ERRORS.contains(&e.error),
+        LastFmError::Http(e) => {
+            e.is_connect()
+                || e.is_timeout()
+                || e.status()
+                    .is_some_and(|status| RETRYABLE_STATUSES.contains(&status.as_u16()))
+        }
+        _ => false,
+    }
+}
+
+/// Parse the body of a successful response.
+///
+/// # Errors
+/// * `LastFmError::Api` - If the body is a Last.fm error.
+/// * `LastFmError::Parse` - If the body doesn't match `T`.
+fn parse_response<T: DeserializeOwned>(body: &[u8]) -> Result<T> {
+    // Last.fm sometimes reports errors with a 200 status
+    if let Ok(error) = serde_json::from_slice::<LastFmErrorResponse>(body) {
+        return Err(LastFmError::Api(error));
+    }
+
+    Ok(serde_json::from_slice::<T>(body)?)
+}
+
+/// Build the HTTP client with the TLS backend selected by the crate features.
+///
+/// `rustls` takes precedence when both `rustls` and `native-tls` are enabled.
+///
+/// # Errors
+/// * `LastFmError::Http` - If the TLS backend cannot be initialized.
+fn build_client(request_timeout: Option<Duration>) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if let Some(timeout) = request_timeout {
+        builder = builder.timeout(timeout);
+    }
+
+    #[cfg(feature = "rustls")]
+    let builder = builder.use_rustls_tls();
+
+    #[cfg(all(feature = "native-tls", not(feature = "rustls")))]
+    let builder = builder.use_native_tls();
+
+    Ok(builder.build()?)
+}
+
+/// Reject timestamps before the UNIX epoch, which Last.fm cannot represent.
+///
+/// # Errors
+/// * `LastFmError::Other` - If `timestamp` is negative.
+fn check_timestamp(timestamp: i64) -> Result<()> {
+    if timestamp < 0 {
+        return Err(LastFmError::Other(format!(
+            "Invalid timestamp: {timestamp} is before the UNIX epoch"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Compute the first and last second (UNIX timestamps) of a UTC day.
+///
+/// # Arguments
+/// * `year` - The year.
+/// * `month` - The month (1-12).
+/// * `day` - The day of the month.
+///
+/// # Errors
+/// * `LastFmError::Other` - If the date is invalid.
+fn utc_day_bounds(year: i32, month: u32, day: u32) -> Result<(i64, i64)> {
+    let start = NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .ok_or_else(|| LastFmError::Other(format!("Invalid date: {year}-{month}-{day}")))?
+        .and_utc()
+        .timestamp();
+
+    Ok((start, start + 86_399))
+}
+
+/// Split `[start, end]` into consecutive inclusive windows of at most `window_secs`.
+fn backfill_windows(start: i64, end: i64, window_secs: i64) -> Vec<(i64, i64)> {
+    let mut windows = Vec::new();
+    let mut from = start;
+
+    while from <= end {
+        let to = (from + window_secs - 1).min(end);
+        windows.push((from, to));
+        from = to + 1;
+    }
+
+    windows
+}
+
+/// Keep the scrobbles newer than the latest stored one, each only once.
+///
+/// This is the rule every history update uses: Last.fm's `from` filter is
+/// inclusive, so the scrobbles at `last_timestamp` are already stored, and
+/// anything older is too. Tracks without a date (currently playing) are
+/// dropped, as are repeats of the same `(timestamp, artist, name)` in `tracks`.
+fn newer_scrobbles(tracks: Vec<RecentTrack>, last_timestamp: Option<i64>) -> Vec<RecentTrack> {
+    let mut seen = HashSet::new();
+
+    tracks
+        .into_iter()
+        .filter(|track| {
+            track.get_timestamp().is_some_and(|uts| {
+                last_timestamp.is_none_or(|last| i64::from(uts) > last)
+                    && seen.insert((uts, track.artist.text.clone(), track.name.clone()))
+            })
+        })
+        .collect()
+}
+
+/// Compute the `api_sig` of a signed request.
+///
+/// Parameters are concatenated as `<key><value>` in key order, followed by the
+/// API secret, and hashed with MD5. `format` and `callback` are not signed.
+fn sign_params(params: &QueryParams, api_secret: &str) -> String {
+    let mut payload = String::new();
+    for (key, value) in params
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "format" | "callback"))
+    {
+        payload.push_str(key);
+        payload.push_str(value);
+    }
+    payload.push_str(api_secret);
+
+    format!("{:x}", md5::compute(payload))
+}
+
+/// List the fields of a paginated tracks response that are missing or of an
+/// unexpected type, so schema changes are reported before a long fetch.
+fn probe_schema(response: &serde_json::Value, root_key: &str, item_key: &str) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    let Some(root) = response
+        .get(root_key)
+        .and_then(serde_json::Value::as_object)
+    else {
+        issues.push(format!("`{root_key}` is missing or not an object"));
+        return issues;
+    };
+
+    match root.get("@attr").and_then(|attr| attr.get("total")) {
+        None => issues.push(format!("`{root_key}.@attr.total` is missing")),
+        Some(serde_json::Value::Number(total)) if total.is_u64() => {}
+        Some(serde_json::Value::String(total)) if total.parse::<u32>().is_ok() => {}
+        Some(_) => issues.push(format!("`{root_key}.@attr.total` is not a number")),
+    }
+
+    let first_item = match root.get(item_key) {
+        None => {
+            issues.push(format!("`{root_key}.{item_key}` is missing"));
+            None
+        }
+        Some(serde_json::Value::Array(items)) => items.first(),
+        Some(_) => {
+            issues.push(format!("`{root_key}.{item_key}` is not a list"));
+            None
+        }
+    };
+
+    if let Some(item) = first_item {
+        if !item.get("name").is_some_and(serde_json::Value::is_string) {
+            issues.push(format!(
+                "`{root_key}.{item_key}.name` is missing or not a string"
+            ));
+        }
+        // Artists are the only items without an artist of their own
+        if item_key != "artist" && !item.get("artist").is_some_and(serde_json::Value::is_object) {
+            issues.push(format!(
+                "`{root_key}.{item_key}.artist` is missing or not an object"
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Count plays per artist and track name, keeping the track information of the latest play.
+///
+/// Keying on the artist as well keeps same-titled tracks by different artists apart.
+///
+/// Each entry's `image_url` is the track image closest to `image_size`.
+fn count_plays(tracks: Vec<RecentTrack>, image_size: ImageSize) -> Vec<TrackPlayInfo> {
+    let mut play_counts: HashMap<String, TrackPlayInfo> = HashMap::new();
+
+    for track in tracks {
+        let entry = play_counts
+            .entry(format!("{} - {}", track.artist.text, track.name))
+            .or_insert(TrackPlayInfo {
+                name: track.name.clone(),
+                play_count: 0,
+                artist: track.artist.text.clone(),
+                album: Some(track.album.text.clone()).filter(|album| !album.is_empty()),
+                image_url: best_image(&track.image, image_size).map(str::to_string),
+                currently_playing: track
+                    .attr
+                    .as_ref()
+                    .is_some_and(|val| val.nowplaying == "true"),
+                url: track.canonical_url(),
+                date: track.date.map(|date| date.uts),
+            });
+
+        entry.play_count += 1;
+    }
+
+    play_counts.into_values().collect()
+}
+
+/// Escape the characters that are special in HTML text and attribute values.
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Render play counts as an HTML list, currently playing first then by play count.
+fn render_play_counts_html(play_counts: &[TrackPlayInfo]) -> String {
+    use std::fmt::Write;
+
+    let mut sorted: Vec<&TrackPlayInfo> = play_counts.iter().collect();
+    sorted.sort_by(|a, b| {
+        b.currently_playing
+            .cmp(&a.currently_playing)
+            .then(b.play_count.cmp(&a.play_count))
+            .then(a.name.cmp(&b.name))
+    });
+
+    let mut html = String::from("<ul class=\"lastfm-tracks\">\n");
+
+    // Writing to a `String` cannot fail, so the `fmt::Result`s are ignored
+    for info in sorted {
+        let class = if info.currently_playing {
+            "lastfm-track now-playing"
+        } else {
+            "lastfm-track"
+        };
+        let _ = writeln!(html, "  <li class=\"{class}\">");
+
+        if let Some(image_url) = info.image_url.as_deref().filter(|url| !url.is_empty()) {
+            let _ = writeln!(
+                html,
+                "    <img src=\"{}\" alt=\"{}\" width=\"64\" height=\"64\">",
+                escape_html(image_url),
+                escape_html(info.album.as_deref().unwrap_or(&info.name)),
+            );
+        }
+
+        let _ = writeln!(
+            html,
+            "    <a href=\"{}\">{}</a> by {}",
+            escape_html(&info.url),
+            escape_html(&info.name),
+            escape_html(&info.artist),
+        );
+
+        if info.currently_playing {
+            html.push_str("    <span class=\"lastfm-status\">Now playing</span>\n");
+        } else {
+            let _ = writeln!(
+                html,
+                "    <span class=\"lastfm-plays\">{} plays</span>",
+                info.play_count
+            );
+        }
+
+        html.push_str("  </li>\n");
+    }
+
+    html.push_str("</ul>\n");
+    html
+}
+
+/// Builds a `LastFMHandler` step by step.
+///
+/// Settings left unset fall back to the defaults of `LastFMHandler::new`: the
+/// API key is read from `LAST_FM_API_KEY` and requests go to `BASE_URL`.
+#[derive(Clone, Default)]
+pub struct LastFMHandlerBuilder {
+    api_key: Option<String>,
+    username: Option<String>,
+    base_url: Option<String>,
+    request_timeout: Option<Duration>,
+}
+
+impl fmt::Debug for LastFMHandlerBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LastFMHandlerBuilder")
+            .field("api_key", &self.api_key.as_ref().map(|_| REDACTED))
+            .field("username", &self.username)
+            .field("base_url", &self.base_url)
+            .field("request_timeout", &self.request_timeout)
+            .finish()
+    }
+}
+
+impl LastFMHandlerBuilder {
+    /// Set the Last.fm API key, instead of reading `LAST_FM_API_KEY`.
+    ///
+    /// # Arguments
+    /// * `api_key` - The Last.fm API key.
+    #[must_use]
+    pub fn api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Set the username tracks are fetched for.
+    ///
+    /// # Arguments
+    /// * `username` - The Last.fm username.
+    #[must_use]
+    pub fn username(mut self, username: &str) -> Self {
+        self.username = Some(username.to_string());
+        self
+    }
+
+    /// Send requests to another endpoint, such as a proxy or a mock server.
+    ///
+    /// # Arguments
+    /// * `base_url` - The base URL to send requests to.
+    #[must_use]
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = Some(base_url.to_string());
+        self
+    }
+
+    /// Give up on requests that take longer than `timeout`.
+    ///
+    /// # Arguments
+    /// * `timeout` - Timeout of each HTTP request.
+    #[must_use]
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Build the handler.
+    ///
+    /// # Errors
+    /// * `LastFmError::MissingEnvVar` - If no API key was set and `LAST_FM_API_KEY` is not set.
+    /// * `LastFmError::Other` - If no username was set, or the API key or username is empty.
+    /// * `LastFmError::Http` - If the HTTP client cannot be built.
+    ///
+    /// # Returns
+    /// * `Result<LastFMHandler>` - The configured handler.
+    pub fn build(self) -> Result<LastFMHandler> {
+        let Some(username) = self.username else {
+            return Err(LastFmError::Other("Username must be set".to_string()));
+        };
+        let api_key = match self.api_key {
+            Some(api_key) => api_key,
+            None => get_required_env_var("LAST_FM_API_KEY")?,
+        };
+
+        let mut config = Config::new(&api_key, &username);
+        config.base_url = self.base_url;
+        config.request_timeout = self.request_timeout;
+
+        LastFMHandler::from_config(&config)
+    }
+}
+
+/// Fetches the same data for several users concurrently.
+#[derive(Debug, Clone)]
+pub struct MultiUserHandler {
+    handlers: Vec<(String, LastFMHandler)>,
+}
+
+impl MultiUserHandler {
+    /// Run the same fetch for every user concurrently.
+    ///
+    /// # Arguments
+    /// * `fetch` - Function receiving the handler of a user and returning the fetch to run.
+    ///
+    /// # Returns
+    /// * `HashMap<String, Result<Vec<T>>>` - The result of the fetch for each username.
+    pub async fn fetch_all<T, F, Fut>(&self, fetch: F) -> HashMap<String, Result<Vec<T>>>
+    where
+        F: Fn(LastFMHandler) -> Fut,
+        Fut: Future<Output = Result<Vec<T>>>,
+    {
+        let futures = self.handlers.iter().map(|(username, handler)| {
+            let fut = fetch(handler.clone());
+            async move { (username.clone(), fut.await) }
+        });
+
+        join_all(futures).await.into_iter().collect()
+    }
+
+    /// Get recent tracks for every user.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks to fetch per user. If None, fetch all tracks.
+    ///
+    /// # Returns
+    /// * `HashMap<String, Result<Vec<RecentTrack>>>` - The fetched tracks for each username.
+    pub async fn get_user_recent_tracks(
+        &self,
+        limit: impl Into<TrackLimit>,
+    ) -> HashMap<String, Result<Vec<RecentTrack>>> {
+        let limit = limit.into();
+        self.fetch_all(|handler| async move { handler.get_user_recent_tracks(limit).await })
+            .await
+    }
+
+    /// Get loved tracks for every user.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks to fetch per user. If None, fetch all tracks.
+    ///
+    /// # Returns
+    /// * `HashMap<String, Result<Vec<LovedTrack>>>` - The fetched tracks for each username.
+    pub async fn get_user_loved_tracks(
+        &self,
+        limit: impl Into<TrackLimit>,
+    ) -> HashMap<String, Result<Vec<LovedTrack>>> {
+        let limit = limit.into();
+        self.fetch_all(|handler| async move { handler.get_user_loved_tracks(limit).await })
+            .await
+    }
+
+    /// Get top tracks for every user.
+    ///
+    /// # Arguments
+    /// * `limit` - The number of tracks to fetch per user. If None, fetch all available top tracks.
+    /// * `period` - Optional period filter
+    ///
+    /// # Returns
+    /// * `HashMap<String, Result<Vec<TopTrack>>>` - The fetched tracks for each username.
+    pub async fn get_user_top_tracks(
+        &self,
+        limit: impl Into<TrackLimit>,
+        period: Option<Period>,
+    ) -> HashMap<String, Result<Vec<TopTrack>>> {
+        let limit = limit.into();
+        self.fetch_all(|handler| async move { handler.get_user_top_tracks(limit, period).await })
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{Matcher, Server};
+
+    fn recent_tracks_body(total: u32, count: usize) -> String {
+        let names: Vec<(String, u32)> = (0..count)
+            .map(|i| (format!("Track {i}"), 1_700_000_000 - i as u32))
+            .collect();
+        recent_tracks_body_of(total, &names)
+    }
+
+    /// A page of recent tracks by "Artist", one per `(name, uts)` pair.
+    fn recent_tracks_body_of(total: u32, names: &[(impl AsRef<str>, u32)]) -> String {
+        let tracks: Vec<serde_json::Value> = names
+            .iter()
+            .map(|(name, uts)| {
+                serde_json::json!({
+                    "artist": { "mbid": "", "#text": "Artist" },
+                    "streamable": "0",
+                    "image": [],
+                    "album": { "mbid": "", "#text": "Album" },
+                    "date": { "uts": uts.to_string(), "#text": "" },
+                    "name": name.as_ref(),
+                    "mbid": "",
+                    "url": ""
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "recenttracks": {
+                "track": tracks,
+                "@attr": {
+                    "user": "test_user",
+                    "totalPages": "1",
+                    "page": "1",
+                    "perPage": "1",
+                    "total": total.to_string()
+                }
+            }
+        })
+        .to_string()
+    }
+
+    fn top_tracks_body(total: u32, count: usize) -> String {
+        let tracks: Vec<serde_json::Value> = (0..count)
+            .map(|i| {
+                serde_json::json!({
+                    "streamable": { "fulltrack": "0", "#text": "0" },
+                    "mbid": "",
+                    "name": format!("Track {i}"),
+                    "image": [],
+                    "artist": { "mbid": "", "url": "", "name": "Artist" },
+                    "url": "",
+                    "duration": "240",
+                    "@attr": { "rank": (i + 1).to_string() },
+                    "playcount": (100 - i).to_string()
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "toptracks": {
+                "track": tracks,
+                "@attr": {
+                    "user": "test_user",
+                    "totalPages": "1",
+                    "page": "1",
+                    "perPage": "1",
+                    "total": total.to_string()
+                }
+            }
+        })
+        .to_string()
+    }
+
+    fn loved_tracks_body(total: u32, count: usize) -> String {
+        loved_tracks_body_from(total, count, 1_700_000_000)
+    }
+
+    /// Like `loved_tracks_body`, with the newest track loved at `first_uts`.
+    fn loved_tracks_body_from(total: u32, count: usize, first_uts: usize) -> String {
+        let tracks: Vec<serde_json::Value> = (0..count)
+            .map(|i| {
+                serde_json::json!({
+                    "artist": { "mbid": "", "url": "", "name": "Artist" },
+                    "date": { "uts": (first_uts - i).to_string(), "#text": "" },
+                    "image": [],
+                    "streamable": { "fulltrack": "0", "#text": "0" },
+                    "name": format!("Loved {i}"),
+                    "mbid": "",
+                    "url": ""
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "lovedtracks": {
+                "track": tracks,
+                "@attr": {
+                    "user": "test_user",
+                    "totalPages": "1",
+                    "page": "1",
+                    "perPage": "1",
+                    "total": total.to_string()
+                }
+            }
+        })
+        .to_string()
+    }
+
+    fn mock_handler(server: &Server) -> LastFMHandler {
+        let mut handler = LastFMHandler::from_config(&Config::new("test_key", "test_user"))
+            .unwrap()
+            .with_base_url(&format!("{}/", server.url()));
+        handler.retry_base_delay = Duration::from_millis(1);
+        handler
+    }
+
+    #[test]
+    fn test_track_limit_from_str() {
+        assert!(matches!("all".parse(), Ok(TrackLimit::Unlimited)));
+        assert!(matches!("Unlimited".parse(), Ok(TrackLimit::Unlimited)));
+        assert!(matches!("500".parse(), Ok(TrackLimit::Limited(500))));
+        assert!(matches!(
+            "lots".parse::<TrackLimit>(),
+            Err(LastFmError::Other(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_for_users_fetches_each_user() {
+        let mut server = Server::new_async().await;
+        let _alice = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("user".into(), "alice".into()))
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .create_async()
+            .await;
+        let _bob = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("user".into(), "bob".into()))
+            .with_status(200)
+            .with_body(recent_tracks_body(2, 2))
+            .create_async()
+            .await;
+
+        let results = mock_handler(&server)
+            .for_users(&["alice", "bob"])
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await;
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results["alice"].as_ref().unwrap().len(), 1);
+        assert_eq!(results["bob"].as_ref().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_after_first_chunk_returns_partial_tracks() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(12_000, 2))
+            .create_async()
+            .await;
+
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        let handler = mock_handler(&server).with_progress_callback(move |_| trigger.cancel());
+
+        let outcome = handler
+            .get_user_recent_tracks_cancellable(TrackLimit::Unlimited, &cancel)
+            .await
+            .unwrap();
+
+        assert!(outcome.is_cancelled());
+        // One chunk of five pages, two tracks per mocked page
+        assert_eq!(outcome.into_tracks().len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_user_agent_header() {
+        let mut server = Server::new_async().await;
+        let default_mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .match_header("user-agent", DEFAULT_USER_AGENT)
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .expect_at_least(1)
+            .create_async()
+            .await;
+
+        mock_handler(&server)
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+        default_mock.assert_async().await;
+
+        let custom_mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .match_header("user-agent", "my-app/1.0")
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .expect_at_least(1)
+            .create_async()
+            .await;
+
+        mock_handler(&server)
+            .with_user_agent("my-app/1.0")
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+        custom_mock.assert_async().await;
+    }
+
+    fn track_info_body(artist: &str, track: &str) -> String {
+        serde_json::json!({
+            "track": {
+                "name": track,
+                "mbid": "",
+                "url": "",
+                "duration": "240000",
+                "listeners": "1000",
+                "playcount": "5000",
+                "artist": { "name": artist, "mbid": "", "url": "" },
+                "toptags": { "tag": [{ "name": "rock", "url": "" }] }
+            }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_enrich_recent_tracks_looks_up_each_pair_once() {
+        let mut server = Server::new_async().await;
+        let found = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "track.getinfo".into()),
+                Matcher::UrlEncoded("track".into(), "Track 0".into()),
+            ]))
+            .with_status(200)
+            .with_body(track_info_body("Artist", "Track 0"))
+            .expect(1)
+            .create_async()
+            .await;
+        let missing = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "track.getinfo".into()),
+                Matcher::UrlEncoded("track".into(), "Track 1".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"error":6,"message":"Track not found"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let page: UserRecentTracks = serde_json::from_str(&recent_tracks_body(2, 2)).unwrap();
+        let mut tracks: Vec<RecentTrack> = page
+            .recenttracks
+            .track
+            .into_iter()
+            .map(RecentTrack::from)
+            .collect();
+        tracks.push(tracks[0].clone());
+
+        let infos = mock_handler(&server).enrich_recent_tracks(&tracks).await;
+
+        found.assert_async().await;
+        missing.assert_async().await;
+        assert_eq!(infos.len(), 2);
+        let info = infos[&("Artist".to_string(), "Track 0".to_string())]
+            .as_ref()
+            .unwrap();
+        assert_eq!(info.listeners, 1000);
+        assert_eq!(info.duration_secs(), Some(240));
+        assert_eq!(info.tags, ["rock"]);
+        assert!(matches!(
+            infos[&("Artist".to_string(), "Track 1".to_string())],
+            Err(LastFmError::Api(ref e)) if e.error == 6
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_track_info_cache_calls_once_per_unique_track() {
+        let mut server = Server::new_async().await;
+        let first = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "track.getinfo".into()),
+                Matcher::UrlEncoded("track".into(), "Song1".into()),
+            ]))
+            .with_status(200)
+            .with_body(track_info_body("Artist1", "Song1"))
+            .expect(1)
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "track.getinfo".into()),
+                Matcher::UrlEncoded("track".into(), "Song2".into()),
+            ]))
+            .with_status(200)
+            .with_body(track_info_body("Artist2", "Song2"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let tracks = vec![
+            ("Artist1".to_string(), "Song1".to_string()),
+            ("Artist2".to_string(), "Song2".to_string()),
+            ("Artist1".to_string(), "Song1".to_string()),
+            ("Artist1".to_string(), "Song1".to_string()),
+        ];
+
+        let infos = mock_handler(&server).get_tracks_info(&tracks).await;
+
+        assert_eq!(infos.len(), 4);
+        assert!(infos.iter().all(Result::is_ok));
+        assert_eq!(infos[3].as_ref().unwrap().tags, vec!["rock".to_string()]);
+        first.assert_async().await;
+        second.assert_async().await;
+    }
+
+    #[test]
+    fn test_utc_day_bounds() {
+        assert_eq!(
+            utc_day_bounds(2024, 12, 4).unwrap(),
+            (1_733_270_400, 1_733_356_799)
+        );
+        assert!(matches!(
+            utc_day_bounds(2024, 2, 30),
+            Err(LastFmError::Other(_))
+        ));
+    }
+
+    #[test]
+    fn test_progress_event_eta() {
+        let event = ProgressEvent::new(250, 1000, 1, 4, Duration::from_secs(10));
+
+        assert_eq!(event.eta, Some(Duration::from_secs(30)));
+        assert!((event.percent_complete() - 25.0).abs() < f64::EPSILON);
+
+        let done = ProgressEvent::new(1000, 1000, 4, 4, Duration::from_secs(40));
+        assert_eq!(done.eta, Some(Duration::ZERO));
+
+        let empty = ProgressEvent::new(0, 1000, 0, 4, Duration::from_secs(1));
+        assert_eq!(empty.eta, None);
+    }
+
+    #[tokio::test]
+    async fn test_recent_tracks_chronological() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(5, 5))
+            .create_async()
+            .await;
+
+        let tracks = mock_handler(&server)
+            .get_user_recent_tracks_chronological(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        assert_eq!(tracks.len(), 5);
+        assert!(tracks
+            .windows(2)
+            .all(|w| w[0].get_timestamp() <= w[1].get_timestamp()));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_raw_returns_untyped_json() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "tag.getinfo".into()),
+                Matcher::UrlEncoded("tag".into(), "rock".into()),
+                Matcher::UrlEncoded("api_key".into(), "test_key".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"tag":{"name":"rock","total":12345}}"#)
+            .create_async()
+            .await;
+
+        let value = mock_handler(&server)
+            .fetch_raw("tag.getinfo", &[("tag", "rock")])
+            .await
+            .unwrap();
+
+        assert_eq!(value["tag"]["name"], "rock");
+        assert_eq!(value["tag"]["total"], 12345);
+    }
+
+    #[test]
+    fn test_build_url_matches_request_encoding() {
+        let handler = LastFMHandler::from_config(&Config::new("test_key", "test_user")).unwrap();
+
+        assert_eq!(
+            handler.build_url(
+                "track.getinfo",
+                &[("artist", "Sigur Rós"), ("track", "Hoppípolla")]
+            ),
+            "https://ws.audioscrobbler.com/2.0/?api_key=test_key&artist=Sigur+R%C3%B3s\
+             &format=json&limit=1000&method=track.getinfo&track=Hopp%C3%ADpolla&user=test_user"
+        );
+
+        let rotating = handler.with_api_keys(&["key1", "key2"]);
+        assert!(rotating
+            .build_url("user.getinfo", &[])
+            .contains("api_key=key1"));
+        assert!(rotating
+            .build_url("user.getinfo", &[])
+            .contains("api_key=key1"));
+    }
+
+    #[derive(Clone, Default)]
+    struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_verbose_logging_redacts_api_key() {
+        let mut server = Server::new_async().await;
+        let _recent = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .create_async()
+            .await;
+
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        mock_handler(&server)
+            .with_verbose_logging(true)
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("method=user.getrecenttracks"));
+        assert!(output.contains("api_key=REDACTED"));
+        assert!(!output.contains("test_key"));
+        assert!(output.contains("Track 0"));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_redacted_from_errors_and_debug() {
+        let mut handler = LastFMHandler::from_config(&Config::new("test_key", "test_user"))
+            .unwrap()
+            .with_base_url("http://127.0.0.1:1/");
+        handler.retry_base_delay = Duration::from_millis(1);
+
+        let debug = format!("{handler:?}");
+        assert!(!debug.contains("test_key"));
+        assert!(debug.contains("REDACTED"));
+
+        let err = handler.get_user_loved_tracks_total().await.unwrap_err();
+        assert!(matches!(err, LastFmError::Http(_)));
+        let message = err.to_string();
+        assert!(message.contains("api_key=REDACTED"), "{message}");
+        assert!(message.contains("user=test_user"), "{message}");
+        assert!(!message.contains("test_key"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn test_weekly_album_chart_requests_week_range() {
+        let mut server = Server::new_async().await;
+        let chart = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.getweeklyalbumchart".into()),
+                Matcher::UrlEncoded("from".into(), "1733011200".into()),
+                Matcher::UrlEncoded("to".into(), "1733616000".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "weeklyalbumchart": {
+                        "album": [{
+                            "artist": { "mbid": "", "#text": "Artist" },
+                            "mbid": "",
+                            "url": "",
+                            "name": "Album",
+                            "@attr": { "rank": "1" },
+                            "playcount": "12"
+                        }],
+                        "@attr": { "from": "1733011200", "user": "test_user", "to": "1733616000" }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let albums = mock_handler(&server)
+            .get_weekly_album_chart(1_733_011_200, 1_733_616_000)
+            .await
+            .unwrap();
+
+        chart.assert_async().await;
+        assert_eq!(albums.len(), 1);
+        assert_eq!(albums[0].name, "Album");
+    }
+
+    #[tokio::test]
+    async fn test_weekly_track_chart_for_listed_week() {
+        let mut server = Server::new_async().await;
+        let _list = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded(
+                "method".into(),
+                "user.getweeklychartlist".into(),
+            ))
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "weeklychartlist": {
+                        "chart": [
+                            { "#text": "", "from": "1732406400", "to": "1733011200" },
+                            { "#text": "", "from": "1733011200", "to": "1733616000" }
+                        ],
+                        "@attr": { "user": "test_user" }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+        let chart = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.getweeklytrackchart".into()),
+                Matcher::UrlEncoded("from".into(), "1733011200".into()),
+                Matcher::UrlEncoded("to".into(), "1733616000".into()),
+            ]))
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "weeklytrackchart": {
+                        "track": [{
+                            "artist": { "mbid": "", "#text": "Artist" },
+                            "image": [{ "size": "small", "#text": "" }],
+                            "mbid": "",
+                            "url": "",
+                            "name": "Song",
+                            "@attr": { "rank": "1" },
+                            "playcount": "7"
+                        }],
+                        "@attr": { "from": "1733011200", "user": "test_user", "to": "1733616000" }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server);
+        let weeks = handler.get_weekly_chart_list().await.unwrap();
+        assert_eq!(
+            weeks,
+            vec![
+                (1_732_406_400, 1_733_011_200),
+                (1_733_011_200, 1_733_616_000)
+            ]
+        );
+
+        let (from, to) = weeks[1];
+        let tracks = handler
+            .get_weekly_track_chart(i64::from(from), i64::from(to))
+            .await
+            .unwrap();
+
+        chart.assert_async().await;
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0].to_string(), "Artist - Song");
+        assert_eq!(tracks[0].playcount, 7);
+    }
+
+    #[tokio::test]
+    async fn test_plan_fetch_counts_calls_without_fetching() {
+        let mut server = Server::new_async().await;
+        let discovery = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.getrecenttracks".into()),
+                Matcher::UrlEncoded("limit".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body(12_345, 1))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server);
+
+        assert_eq!(
+            handler.plan_fetch(TrackLimit::Unlimited).await.unwrap(),
+            FetchPlan {
+                total_tracks: 12_345,
+                api_calls: 14,
+                chunks: 3,
+            }
+        );
+        assert_eq!(
+            handler.plan_fetch(Some(500)).await.unwrap(),
+            FetchPlan {
+                total_tracks: 500,
+                api_calls: 2,
+                chunks: 1,
+            }
+        );
+
+        discovery.assert_async().await;
+    }
+
+    #[test]
+    fn test_count_plays_separates_same_title_by_artist() {
+        let play = |artist: &str, uts: u32| {
+            serde_json::json!({
+                "artist": { "mbid": "", "#text": artist },
+                "streamable": "0",
+                "image": [],
+                "album": { "mbid": "", "#text": "" },
+                "date": { "uts": uts.to_string(), "#text": "" },
+                "name": "Intro",
+                "mbid": "",
+                "url": ""
+            })
+        };
+        let body = serde_json::json!({
+            "recenttracks": {
+                "track": [play("A", 3), play("B", 2), play("A", 1)],
+                "@attr": { "user": "test_user", "totalPages": "1", "page": "1",
+                           "perPage": "3", "total": "3" }
+            }
+        })
+        .to_string();
+        let page: UserRecentTracks = serde_json::from_str(&body).unwrap();
+
+        let mut play_counts = count_plays(
+            page.tracks().into_iter().map(RecentTrack::from).collect(),
+            ImageSize::Large,
+        );
+        play_counts.sort_by(|a, b| a.artist.cmp(&b.artist));
+
+        let counts: Vec<(&str, &str, u32)> = play_counts
+            .iter()
+            .map(|info| (info.artist.as_str(), info.name.as_str(), info.play_count))
+            .collect();
+        assert_eq!(counts, vec![("A", "Intro", 2), ("B", "Intro", 1)]);
+        // Empty albums are left out, so renderers fall back to the track name
+        assert!(play_counts.iter().all(|info| info.album.is_none()));
+    }
+
+    #[test]
+    fn test_render_play_counts_html() {
+        let play_counts = vec![
+            TrackPlayInfo {
+                name: "Song <1>".to_string(),
+                play_count: 3,
+                artist: "Artist1".to_string(),
+                album: Some("Album1".to_string()),
+                image_url: Some("https://img.example/album1.png".to_string()),
+                currently_playing: false,
+                date: Some(1_700_000_000),
+                url: "https://www.last.fm/music/Artist1/_/Song1".to_string(),
+            },
+            TrackPlayInfo {
+                name: "Song2".to_string(),
+                play_count: 1,
+                artist: "Artist2".to_string(),
+                album: None,
+                image_url: None,
+                currently_playing: true,
+                date: None,
+                url: String::new(),
+            },
+        ];
+
+        let html = render_play_counts_html(&play_counts);
+
+        assert!(html.contains("Song &lt;1&gt;"));
+        assert!(html.contains("<img src=\"https://img.example/album1.png\""));
+        assert!(html.contains("3 plays"));
+        assert!(html.contains("Now playing"));
+        assert!(html.find("Song2").unwrap() < html.find("Song &lt;1&gt;").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_since_methods_share_timestamp_type() {
+        let mut server = Server::new_async().await;
+        let recent = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.getrecenttracks".into()),
+                Matcher::UrlEncoded("from".into(), "1699999998".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body(2, 2))
+            .expect(2)
+            .create_async()
+            .await;
+        let _loved = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded(
+                "method".into(),
+                "user.getlovedtracks".into(),
+            ))
+            .with_status(200)
+            .with_body(loved_tracks_body(3, 3))
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server);
+        let since: i64 = 1_699_999_998;
+
+        let recent_tracks = handler
+            .get_user_recent_tracks_since(since, TrackLimit::Unlimited)
+            .await
+            .unwrap();
+        let loved_tracks = handler
+            .get_user_loved_tracks_since(since, TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        assert_eq!(recent_tracks.len(), 2);
+        assert_eq!(loved_tracks.len(), 2);
+        recent.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_since_methods_reject_negative_timestamps() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server);
+
+        assert!(matches!(
+            handler.get_user_recent_tracks_since(-1, None).await,
+            Err(LastFmError::Other(_))
+        ));
+        assert!(matches!(
+            handler.get_user_loved_tracks_since(-1, None).await,
+            Err(LastFmError::Other(_))
+        ));
+        assert!(matches!(
+            handler
+                .get_user_recent_tracks_between(-86_400, 0, None)
+                .await,
+            Err(LastFmError::Other(_))
+        ));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_user_artist_tracks_rejects_negative_timestamps() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server);
+
+        assert!(matches!(
+            handler
+                .get_user_artist_tracks("Artist", Some(-1), None, TrackLimit::Unlimited)
+                .await,
+            Err(LastFmError::Other(_))
+        ));
+        assert!(matches!(
+            handler
+                .get_user_artist_tracks("Artist", Some(0), Some(-1), TrackLimit::Unlimited)
+                .await,
+            Err(LastFmError::Other(_))
+        ));
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_get_recent_tracks_page_makes_a_single_request() {
+        let mut server = Server::new_async().await;
+        let page = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.getrecenttracks".into()),
+                Matcher::UrlEncoded("page".into(), "5".into()),
+                Matcher::UrlEncoded("limit".into(), "20".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body(500, 20))
+            .expect(1)
+            .create_async()
+            .await;
+        let others = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .expect(0)
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server);
+        let tracks = handler.get_recent_tracks_page(5, 20).await.unwrap();
+
+        assert_eq!(tracks.len(), 20);
+        assert!(matches!(
+            handler.get_recent_tracks_page(0, 20).await,
+            Err(LastFmError::Other(_))
+        ));
+        page.assert_async().await;
+        others.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_check_connection_ok() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.getinfo".into()),
+                Matcher::UrlEncoded("user".into(), "test_user".into()),
+            ]))
+            .with_status(200)
+            .with_body(r#"{"user":{"name":"test_user"}}"#)
+            .create_async()
+            .await;
+
+        mock_handler(&server).check_connection().await.unwrap();
+
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_check_connection_invalid_api_key() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .with_body(r#"{"error":10,"message":"Invalid API key - You must be granted a valid key by last.fm"}"#)
+            .create_async()
+            .await;
+
+        let result = mock_handler(&server).check_connection().await;
+
+        assert!(matches!(result, Err(LastFmError::InvalidApiKey)));
+    }
+
+    #[tokio::test]
+    async fn test_check_connection_unknown_user() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(404)
+            .with_body(r#"{"error":6,"message":"User not found"}"#)
+            .create_async()
+            .await;
+
+        let result = mock_handler(&server).check_connection().await;
+
+        assert!(matches!(result, Err(LastFmError::UserNotFound(ref user)) if user == "test_user"));
+    }
+
+    #[tokio::test]
+    async fn test_error_body_with_success_status_is_api_error() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(r#"{"error":6,"message":"User not found"}"#)
+            .create_async()
+            .await;
+
+        let result = mock_handler(&server)
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await;
+
+        assert!(matches!(result, Err(LastFmError::Api(ref e)) if e.error == 6));
+    }
+
+    #[cfg(feature = "testing")]
+    #[derive(Default)]
+    struct RecordingTransport {
+        pages: Mutex<Vec<u32>>,
+        limits: Mutex<Vec<u32>>,
+    }
+
+    #[cfg(feature = "testing")]
+    impl HttpTransport for RecordingTransport {
+        fn get(&self, params: &QueryParams) -> Result<String> {
+            let page: u32 = params["page"].parse().unwrap();
+            let limit: u32 = params["limit"].parse().unwrap();
+            self.pages.lock().unwrap().push(page);
+            self.limits.lock().unwrap().push(limit);
+
+            let names: Vec<String> = (0..limit)
+                .map(|i| format!("Track {}", (page - 1) * limit + i))
+                .collect();
+            let tracks: Vec<(&str, u32)> = names
+                .iter()
+                .enumerate()
+                .map(|(i, name)| (name.as_str(), 1_700_000_000 - page * limit - i as u32))
+                .collect();
+
+            Ok(recent_tracks_body_of(25, &tracks))
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(start_paused = true)]
+    async fn test_total_cache_skips_discovery_within_ttl() {
+        let transport = Arc::new(RecordingTransport::default());
+        let handler = LastFMHandler::from_config(&Config::new("test_key", "test_user"))
+            .unwrap()
+            .with_transport(Arc::clone(&transport))
+            .with_total_cache_ttl(Duration::from_secs(60));
+        let discoveries = || {
+            transport
+                .limits
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|limit| **limit == 1)
+                .count()
+        };
+
+        for _ in 0..2 {
+            let tracks = handler
+                .get_user_recent_tracks(TrackLimit::Unlimited)
+                .await
+                .unwrap();
+            assert_eq!(tracks.len(), 25);
+        }
+        assert_eq!(discoveries(), 1);
+
+        tokio::time::advance(Duration::from_secs(59)).await;
+        handler
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+        assert_eq!(discoveries(), 1);
+
+        // Once the TTL has elapsed, the total is discovered again
+        tokio::time::advance(Duration::from_secs(1)).await;
+        handler
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+        assert_eq!(discoveries(), 2);
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test]
+    async fn test_transport_sees_pages_in_chunk_order() {
+        let transport = Arc::new(RecordingTransport::default());
+
+        let tracks = LastFMHandler::from_config(&Config::new("test_key", "test_user"))
+            .unwrap()
+            .with_max_page_limit(2)
+            .with_transport(Arc::clone(&transport))
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        // A discovery request, then three chunks of up to `CHUNK_MULTIPLIER` pages
+        assert_eq!(
+            *transport.pages.lock().unwrap(),
+            [1, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13]
+        );
+        assert_eq!(tracks.len(), 25);
+    }
+
+    #[tokio::test]
+    async fn test_page_shift_dedup_drops_boundary_duplicate() {
+        let mut server = Server::new_async().await;
+        let _discovery = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("limit".into(), "1".into()))
+            .with_status(200)
+            .with_body(recent_tracks_body_of(4, &[("D", 400)]))
+            .create_async()
+            .await;
+        let _first = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".into(), "2".into()),
+                Matcher::UrlEncoded("page".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body_of(4, &[("D", 400), ("C", 300)]))
+            .create_async()
+            .await;
+        // A new scrobble arrived after page 1, pushing "C" onto page 2 and "A" off it
+        let _second = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".into(), "2".into()),
+                Matcher::UrlEncoded("page".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body_of(5, &[("C", 300), ("B", 200)]))
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server).with_max_page_limit(2);
+
+        let shifted = handler
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+        let deduped = handler
+            .with_page_shift_dedup(true)
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        assert_eq!(shifted.len(), 4);
+        let names: Vec<&str> = deduped.iter().map(|track| track.name.as_str()).collect();
+        // "A" was skipped by the shift and can only be recovered by fetching again
+        assert_eq!(names, vec!["D", "C", "B"]);
+    }
+
+    #[tokio::test]
+    async fn test_recent_tracks_stream_drops_page_shift_duplicates() {
+        let mut server = Server::new_async().await;
+        let _discovery = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("limit".into(), "1".into()))
+            .with_status(200)
+            .with_body(recent_tracks_body_of(4, &[("D", 400)]))
+            .create_async()
+            .await;
+        let _first = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".into(), "2".into()),
+                Matcher::UrlEncoded("page".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body_of(4, &[("D", 400), ("C", 300)]))
+            .create_async()
+            .await;
+        // A new scrobble arrived after page 1, pushing "C" onto page 2
+        let _second = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".into(), "2".into()),
+                Matcher::UrlEncoded("page".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body_of(5, &[("C", 300), ("B", 200)]))
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server)
+            .with_max_page_limit(2)
+            .with_page_shift_dedup(true);
+
+        let streamed: Vec<RecentTrack> = handler
+            .recent_tracks_stream(TrackLimit::Unlimited)
+            .try_collect()
+            .await
+            .unwrap();
+        let collected = handler
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        let names: Vec<&str> = streamed.iter().map(|track| track.name.as_str()).collect();
+        assert_eq!(names, vec!["D", "C", "B"]);
+        assert_eq!(streamed.len(), collected.len());
+    }
+
+    #[tokio::test]
+    async fn test_recent_tracks_stream_fetches_lazily() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(25, 2))
+            .expect(6)
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server).with_max_page_limit(2);
+
+        // Only the discovery request and the first chunk are needed for 3 tracks
+        let first: Vec<RecentTrack> = handler
+            .recent_tracks_stream(TrackLimit::Unlimited)
+            .take(3)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 3);
+        mock.assert_async().await;
+
+        let all: Vec<RecentTrack> = handler
+            .recent_tracks_stream(TrackLimit::Limited(25))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(all.len(), 25);
+    }
+
+    #[tokio::test]
+    async fn test_max_page_limit_recomputes_pages() {
+        let mut server = Server::new_async().await;
+        let last_page = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("page".into(), "13".into()),
+                Matcher::UrlEncoded("limit".into(), "100".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body(1_250, 2))
+            .expect(1)
+            .create_async()
+            .await;
+        let beyond = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("page".into(), "14".into()))
+            .with_status(200)
+            .with_body(recent_tracks_body(1_250, 2))
+            .expect(0)
+            .create_async()
+            .await;
+        // Discovery request plus pages 1 to 12
+        let pages = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(1_250, 2))
+            .expect(13)
+            .create_async()
+            .await;
+
+        let chunks = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&chunks);
+        let handler = mock_handler(&server)
+            .with_max_page_limit(100)
+            .with_progress_callback(move |event| recorded.lock().unwrap().push(event.total_chunks));
+
+        handler
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        // 1250 tracks at 100 per page and 5 pages per chunk
+        assert_eq!(*chunks.lock().unwrap(), vec![3, 3, 3]);
+        last_page.assert_async().await;
+        beyond.assert_async().await;
+        pages.assert_async().await;
+    }
+
+    /// Fetch `total` tracks at 100 per page and check exactly `pages` pages are requested
+    async fn assert_pages_for_total(total: u32, pages: usize) {
+        let mut server = Server::new_async().await;
+        let beyond = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("page".into(), (pages + 1).to_string()))
+            .expect(0)
+            .create_async()
+            .await;
+        let empty = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("limit".into(), "0".into()))
+            .expect(0)
+            .create_async()
+            .await;
+        // Discovery request plus one request per page
+        let requests = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(total, 100))
+            .expect(pages + 1)
+            .create_async()
+            .await;
+
+        let tracks = mock_handler(&server)
+            .with_max_page_limit(100)
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        assert_eq!(tracks.len(), total as usize);
+        beyond.assert_async().await;
+        empty.assert_async().await;
+        requests.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_total_of_exactly_one_chunk() {
+        assert_pages_for_total(500, 5).await;
+    }
+
+    #[tokio::test]
+    async fn test_total_of_one_chunk_plus_one() {
+        assert_pages_for_total(501, 6).await;
+    }
+
+    #[tokio::test]
+    async fn test_total_of_exactly_two_chunks() {
+        assert_pages_for_total(1_000, 10).await;
+    }
+
+    #[tokio::test]
+    async fn test_get_and_save_top_tracks() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.gettoptracks".into()),
+                Matcher::UrlEncoded("period".into(), "7day".into()),
+            ]))
+            .with_status(200)
+            .with_body(top_tracks_body(3, 3))
+            .create_async()
+            .await;
+
+        let root = std::env::temp_dir().join("async_lastfm_test_save_top_tracks");
+        let store = DataStore::new(&root);
+
+        let filename = mock_handler(&server)
+            .get_and_save_top_tracks(
+                &store,
+                TrackLimit::Unlimited,
+                Some(Period::Week),
+                FileFormat::Json,
+            )
+            .await
+            .unwrap();
+
+        let saved: Vec<TopTrack> = store
+            .load(Path::new(&filename).file_name().unwrap())
+            .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(Path::new(&filename).starts_with(&root));
+        assert!(filename.contains("top_tracks_"));
+        assert_eq!(saved.len(), 3);
+        assert_eq!(saved[0].playcount, 100);
+    }
+
+    fn top_artists_page(ranks: std::ops::Range<u32>, total: u32) -> String {
+        let artists: Vec<serde_json::Value> = ranks
+            .map(|rank| {
+                serde_json::json!({
+                    "streamable": "0",
+                    "image": [{ "size": "large", "#text": format!("https://img/{rank}.png") }],
+                    "mbid": "",
+                    "url": "",
+                    "playcount": (100 - rank).to_string(),
+                    "@attr": { "rank": rank.to_string() },
+                    "name": format!("Artist {rank}")
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "topartists": {
+                "artist": artists,
+                "@attr": {
+                    "user": "test_user",
+                    "totalPages": "2",
+                    "page": "1",
+                    "perPage": "2",
+                    "total": total.to_string()
+                }
+            }
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_get_user_top_artists_paginates() {
+        let mut server = Server::new_async().await;
+        let _discovery = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.gettopartists".into()),
+                Matcher::UrlEncoded("period".into(), "12month".into()),
+                Matcher::UrlEncoded("limit".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(top_artists_page(1..2, 3))
+            .create_async()
+            .await;
+        let first = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".into(), "2".into()),
+                Matcher::UrlEncoded("page".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(top_artists_page(1..3, 3))
+            .expect(1)
+            .create_async()
+            .await;
+        let second = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("limit".into(), "2".into()),
+                Matcher::UrlEncoded("page".into(), "2".into()),
+            ]))
+            .with_status(200)
+            .with_body(top_artists_page(3..4, 3))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let artists = mock_handler(&server)
+            .with_strict_schema(true)
+            .with_max_page_limit(2)
+            .get_user_top_artists(TrackLimit::Unlimited, Some(Period::TwelveMonth))
+            .await
+            .unwrap();
+
+        first.assert_async().await;
+        second.assert_async().await;
+        let names: Vec<String> = artists.iter().map(ToString::to_string).collect();
+        assert_eq!(names, ["Artist 1", "Artist 2", "Artist 3"]);
+        assert_eq!(artists[0].playcount, 99);
+        assert_eq!(artists[2].attr.rank, "3");
+        assert_eq!(artists[2].image[0].text, "https://img/3.png");
+    }
+
+    #[tokio::test]
+    async fn test_get_and_save_top_artists_and_albums() {
+        let mut server = Server::new_async().await;
+        let _artists = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded(
+                "method".into(),
+                "user.gettopartists".into(),
+            ))
+            .with_status(200)
+            .with_body(top_artists_page(1..3, 2))
+            .create_async()
+            .await;
+        let _albums = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded(
+                "method".into(),
+                "user.gettopalbums".into(),
+            ))
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "topalbums": {
+                        "album": [{
+                            "artist": { "url": "", "name": "Artist", "mbid": "" },
+                            "mbid": "",
+                            "url": "",
+                            "playcount": "42",
+                            "@attr": { "rank": "1" },
+                            "name": "Album"
+                        }],
+                        "@attr": {
+                            "user": "test_user",
+                            "totalPages": "1",
+                            "page": "1",
+                            "perPage": "50",
+                            "total": "1"
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let root = std::env::temp_dir().join("async_lastfm_test_save_top_artists_albums");
+        let store = DataStore::new(&root);
+        let handler = mock_handler(&server);
+
+        let artists_file = handler
+            .get_and_save_top_artists(&store, TrackLimit::Limited(2), None, FileFormat::Csv)
+            .await
+            .unwrap();
+        let albums_file = handler
+            .get_and_save_top_albums(&store, TrackLimit::Unlimited, None, FileFormat::Json)
+            .await
+            .unwrap();
+
+        let artists_csv = std::fs::read_to_string(&artists_file).unwrap();
+        let albums: Vec<TopAlbum> = store
+            .load(Path::new(&albums_file).file_name().unwrap())
+            .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(artists_file.contains("top_artists_"));
+        assert_eq!(
+            artists_csv.lines().collect::<Vec<_>>(),
+            [
+                "rank,name,playcount,url",
+                "1,Artist 1,99,",
+                "2,Artist 2,98,"
+            ]
+        );
+        assert!(albums_file.contains("top_albums_"));
+        assert_eq!(albums.len(), 1);
+        assert_eq!(albums[0].to_string(), "Artist - Album");
+    }
+
+    #[tokio::test]
+    async fn test_get_user_top_albums() {
+        let mut server = Server::new_async().await;
+        let albums_mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded(
+                "method".into(),
+                "user.gettopalbums".into(),
+            ))
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "topalbums": {
+                        "album": [{
+                            "artist": { "url": "", "name": "Artist", "mbid": "" },
+                            "image": [{ "size": "extralarge", "#text": "https://img/album.png" }],
+                            "mbid": "",
+                            "url": "https://www.last.fm/music/Artist/Album",
+                            "playcount": "42",
+                            "@attr": { "rank": "1" },
+                            "name": "Album"
+                        }],
+                        "@attr": {
+                            "user": "test_user",
+                            "totalPages": "1",
+                            "page": "1",
+                            "perPage": "50",
+                            "total": "1"
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .expect(2)
+            .create_async()
+            .await;
+
+        let albums = mock_handler(&server)
+            .with_strict_schema(true)
+            .get_user_top_albums(TrackLimit::Unlimited, None)
+            .await
+            .unwrap();
+
+        albums_mock.assert_async().await;
+        assert_eq!(albums.len(), 1);
+        assert_eq!(albums[0].to_string(), "Artist - Album");
+        assert_eq!(albums[0].playcount, 42);
+        assert_eq!(albums[0].image[0].text, "https://img/album.png");
+    }
+
+    #[tokio::test]
+    async fn test_short_period_top_tracks_fetch_single_page() {
+        let mut server = Server::new_async().await;
+        let discovery = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.gettoptracks".into()),
+                Matcher::UrlEncoded("period".into(), "7day".into()),
+                Matcher::UrlEncoded("limit".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(top_tracks_body(3, 1))
+            .expect(1)
+            .create_async()
+            .await;
+        let page = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("period".into(), "7day".into()),
+                Matcher::UrlEncoded("limit".into(), "3".into()),
+                Matcher::UrlEncoded("page".into(), "1".into()),
+            ]))
+            .with_status(200)
+            .with_body(top_tracks_body(3, 3))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let tracks = mock_handler(&server)
+            .get_user_top_tracks(TrackLimit::Limited(1000), Some(Period::Week))
+            .await
+            .unwrap();
+
+        discovery.assert_async().await;
+        page.assert_async().await;
+        assert_eq!(tracks.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_update_tracks_file_skips_now_playing() {
+        let mut body: serde_json::Value = serde_json::from_str(&recent_tracks_body(2, 1)).unwrap();
+        body["recenttracks"]["track"]
+            .as_array_mut()
+            .unwrap()
+            .insert(
+                0,
+                serde_json::json!({
+                    "artist": { "mbid": "", "#text": "Artist" },
+                    "streamable": "0",
+                    "image": [],
+                    "album": { "mbid": "", "#text": "Album" },
+                    "@attr": { "nowplaying": "true" },
+                    "name": "Now Playing",
+                    "mbid": "",
+                    "url": ""
+                }),
+            );
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join("async_lastfm_test_skip_now_playing.json");
+        std::fs::write(&path, "[]").unwrap();
+
+        mock_handler(&server)
+            .update_tracks_file::<RecentTrack>(&path)
+            .await
+            .unwrap();
+
+        let saved: Vec<RecentTrack> = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(saved.len(), 1);
+        assert!(saved.iter().all(|track| track.date.is_some()));
+        assert_eq!(saved[0].name, "Track 0");
+    }
+
+    #[tokio::test]
+    async fn test_update_tracks_file_skips_stored_boundary_scrobble() {
+        let last = u32::try_from(Utc::now().timestamp() - 60).unwrap();
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body_of(
+                2,
+                &[("New", last + 30), ("Old", last)],
+            ))
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join("async_lastfm_test_skip_boundary.json");
+        let response: UserRecentTracks =
+            serde_json::from_str(&recent_tracks_body_of(1, &[("Old", last)])).unwrap();
+        let stored: Vec<RecentTrack> = response
+            .tracks()
+            .into_iter()
+            .map(RecentTrack::from)
+            .collect();
+        std::fs::write(&path, serde_json::to_string(&stored).unwrap()).unwrap();
+
+        let handler = mock_handler(&server);
+        for _ in 0..2 {
+            handler
+                .update_tracks_file::<RecentTrack>(&path)
+                .await
+                .unwrap();
+        }
+
+        let saved: Vec<RecentTrack> = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let names: Vec<&str> = saved.iter().map(|track| track.name.as_str()).collect();
+        assert_eq!(names, vec!["Old", "New"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_tracks_file_appends_to_ndjson() {
+        let last = u32::try_from(Utc::now().timestamp() - 60).unwrap();
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body_of(
+                2,
+                &[("New", last + 30), ("Old", last)],
+            ))
+            .create_async()
+            .await;
+
+        let response: UserRecentTracks =
+            serde_json::from_str(&recent_tracks_body_of(1, &[("Old", last)])).unwrap();
+        let stored =
+            serde_json::to_string(&RecentTrack::from(response.tracks().remove(0))).unwrap() + "\n";
+        let path = std::env::temp_dir().join("async_lastfm_test_update_ndjson.ndjson");
+        std::fs::write(&path, &stored).unwrap();
+
+        mock_handler(&server)
+            .update_tracks_file::<RecentTrack>(&path)
+            .await
+            .unwrap();
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(content.starts_with(&stored));
+        let names: Vec<String> = content
+            .lines()
+            .map(|line| serde_json::from_str::<RecentTrack>(line).unwrap().name)
+            .collect();
+        assert_eq!(names, vec!["Old", "New"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_tracks_store_appends_newer_scrobbles() {
+        let last = u32::try_from(Utc::now().timestamp() - 60).unwrap();
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body_of(
+                2,
+                &[("New", last + 30), ("Old", last)],
+            ))
+            .create_async()
+            .await;
+
+        let response: UserRecentTracks =
+            serde_json::from_str(&recent_tracks_body_of(1, &[("Old", last)])).unwrap();
+        let stored: Vec<RecentTrack> = response
+            .tracks()
+            .into_iter()
+            .map(RecentTrack::from)
+            .collect();
+
+        let root = std::env::temp_dir().join("async_lastfm_test_update_store");
+        let store = DataStore::new(&root);
+        // The name `save` returns is the one the other store methods take
+        let name = TrackStore::save(&store, &stored, &FileFormat::Ndjson, "recent").unwrap();
+
+        let handler = mock_handler(&server);
+        for _ in 0..2 {
+            handler.update_tracks_store(&store, &name).await.unwrap();
+        }
+        let saved: Vec<RecentTrack> = store.load(&name).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let names: Vec<&str> = saved.iter().map(|track| track.name.as_str()).collect();
+        assert_eq!(names, vec!["Old", "New"]);
+    }
+
+    #[test]
+    fn test_backfill_windows_cover_range_without_overlap() {
+        let windows = backfill_windows(1_000, 3_500, 1_000);
+
+        assert_eq!(
+            windows,
+            vec![(1_000, 1_999), (2_000, 2_999), (3_000, 3_500)]
+        );
+        assert!(windows.windows(2).all(|w| w[1].0 == w[0].1 + 1));
+        assert!(backfill_windows(10, 9, 1_000).is_empty());
+    }
+
+    #[test]
+    fn test_newer_scrobbles_keep_each_new_scrobble_once() {
+        let response: UserRecentTracks = serde_json::from_str(&recent_tracks_body_of(
+            4,
+            &[
+                ("Song3", 300),
+                ("Song3", 300),
+                ("Song2", 200),
+                ("Song1", 100),
+            ],
+        ))
+        .unwrap();
+        let mut tracks: Vec<RecentTrack> = response
+            .tracks()
+            .into_iter()
+            .map(RecentTrack::from)
+            .collect();
+        let mut now_playing = tracks[0].clone();
+        now_playing.date = None;
+        tracks.insert(0, now_playing);
+        let mut replay = tracks[3].clone();
+        replay.artist.text = "Other".to_string();
+        tracks.push(replay);
+
+        let names = |tracks: Vec<RecentTrack>| -> Vec<(String, String)> {
+            tracks
+                .into_iter()
+                .map(|track| (track.artist.text, track.name))
+                .collect()
+        };
+
+        assert_eq!(
+            names(newer_scrobbles(tracks.clone(), Some(200))),
+            names(vec![tracks[1].clone()])
+        );
+        assert_eq!(newer_scrobbles(tracks.clone(), None).len(), 4);
+        assert_eq!(newer_scrobbles(tracks, Some(300)).len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_update_tracks_file_backfills_large_gap_in_windows() {
+        let last_scrobble = Utc::now().timestamp() - 20 * 24 * 60 * 60;
+        let scrobble = u32::try_from(last_scrobble + 60).unwrap();
+        let mut server = Server::new_async().await;
+        let windowed = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "user.getrecenttracks".into()),
+                Matcher::Regex("from=".into()),
+                Matcher::Regex("to=".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body_of(1, &[("New", scrobble)]))
+            .expect(6)
+            .create_async()
+            .await;
+
+        let response: UserRecentTracks = serde_json::from_str(&recent_tracks_body(1, 1)).unwrap();
+        let mut stored_track = RecentTrack::from(response.tracks().remove(0));
+        stored_track.date.as_mut().unwrap().uts = u32::try_from(last_scrobble).unwrap();
+
+        let path = std::env::temp_dir().join("async_lastfm_test_backfill.json");
+        std::fs::write(&path, serde_json::to_string(&[stored_track]).unwrap()).unwrap();
+
+        mock_handler(&server)
+            .update_tracks_file::<RecentTrack>(&path)
+            .await
+            .unwrap();
+        let saved: Vec<RecentTrack> = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // Three one-week windows, each a discovery request and a page request.
+        // Every window returns the same scrobble, which is only stored once
+        windowed.assert_async().await;
+        assert_eq!(saved.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_update_tracks_file_finalizes_stored_now_playing() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join("async_lastfm_test_finalize_now_playing.json");
+        std::fs::write(
+            &path,
+            serde_json::json!([{
+                "artist": { "mbid": "", "#text": "Artist" },
+                "streamable": false,
+                "image": [],
+                "album": { "mbid": "", "#text": "Album" },
+                "@attr": { "nowplaying": "true" },
+                "name": "Track 0",
+                "mbid": "",
+                "url": ""
+            }])
+            .to_string(),
+        )
+        .unwrap();
+
+        mock_handler(&server)
+            .update_tracks_file::<RecentTrack>(&path)
+            .await
+            .unwrap();
+
+        let saved: Vec<RecentTrack> = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].get_timestamp(), Some(1_700_000_000));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_update_tracks_file_accepts_non_utf8_path() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join(OsStr::from_bytes(b"async_lastfm_test_\xff.json"));
+        assert!(path.to_str().is_none());
+        std::fs::write(&path, "[]").unwrap();
+
+        let result = mock_handler(&server)
+            .update_tracks_file::<RecentTrack>(&path)
+            .await;
+        let saved: Vec<RecentTrack> = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(result.is_ok());
+        assert_eq!(saved.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_from_config_uses_explicit_settings() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("api_key".into(), "config_key".into()),
+                Matcher::UrlEncoded("user".into(), "config_user".into()),
+            ]))
+            .match_header("user-agent", "config-agent")
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let mut config = Config::new("config_key", "config_user");
+        config.base_url = Some(format!("{}/", server.url()));
+        config.user_agent = Some("config-agent".to_string());
+
+        let tracks = LastFMHandler::from_config(&config)
+            .unwrap()
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        assert_eq!(tracks.len(), 1);
+        mock.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_builder_overrides_key_and_base_url() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("api_key".into(), "builder_key".into()),
+                Matcher::UrlEncoded("user".into(), "builder_user".into()),
+            ]))
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let builder = LastFMHandler::builder()
+            .api_key("builder_key")
+            .username("builder_user")
+            .base_url(&format!("{}/", server.url()))
+            .request_timeout(Duration::from_secs(5));
+        assert!(!format!("{builder:?}").contains("builder_key"));
+
+        let tracks = builder
+            .build()
+            .unwrap()
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        assert_eq!(tracks.len(), 1);
+        mock.assert_async().await;
+    }
+
+    #[test]
+    fn test_builder_requires_username() {
+        assert!(matches!(
+            LastFMHandler::builder().api_key("key").build(),
+            Err(LastFmError::Other(_))
+        ));
+        assert!(matches!(
+            LastFMHandler::builder()
+                .api_key("")
+                .username("user")
+                .build(),
+            Err(LastFmError::Other(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_builder_request_timeout() {
+        let mut server = Server::new_async().await;
+        let _slow = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_chunked_body(|writer| {
+                std::thread::sleep(Duration::from_millis(500));
+                writer.write_all(recent_tracks_body(1, 1).as_bytes())
+            })
+            .create_async()
+            .await;
+
+        let handler = LastFMHandler::builder()
+            .api_key("key")
+            .username("user")
+            .base_url(&format!("{}/", server.url()))
+            .request_timeout(Duration::from_millis(50))
+            .build()
+            .unwrap()
+            .with_max_retries(0);
+
+        assert!(matches!(
+            handler.get_user_recent_tracks(TrackLimit::Limited(1)).await,
+            Err(LastFmError::Http(_))
+        ));
+    }
+
+    #[test]
+    fn test_from_config_rejects_empty_settings() {
+        assert!(matches!(
+            LastFMHandler::from_config(&Config::new("", "user")),
+            Err(LastFmError::Other(_))
+        ));
+        assert!(matches!(
+            LastFMHandler::from_config(&Config::new("key", "")),
+            Err(LastFmError::Other(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_max_api_calls_stops_with_partial_results() {
+        let mut server = Server::new_async().await;
+        // Discovery request plus the 5 pages of the first chunk, for each fetch
+        let requests = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(12_345, 100))
+            .expect(18)
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server)
+            .with_max_page_limit(100)
+            .with_max_api_calls(8);
+
+        let outcome = handler
+            .get_user_recent_tracks_cancellable(TrackLimit::Unlimited, &CancellationToken::new())
+            .await
+            .unwrap();
+        let result = handler.get_user_recent_tracks(TrackLimit::Unlimited).await;
+        let streamed: Vec<Result<RecentTrack>> = handler
+            .recent_tracks_stream(TrackLimit::Unlimited)
+            .collect()
+            .await;
+
+        assert!(matches!(outcome, FetchOutcome::BudgetExceeded(ref tracks) if tracks.len() == 500));
+        assert!(
+            matches!(result, Err(LastFmError::Other(ref msg)) if msg.contains("call budget exceeded"))
+        );
+        assert_eq!(streamed.len(), 501);
+        assert!(matches!(streamed.last(), Some(Err(LastFmError::Other(_)))));
+        requests.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_zero_total_still_fetches_one_page() {
+        let mut server = Server::new_async().await;
+        // Discovery request plus the fallback page
+        let page = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("page".into(), "1".into()))
+            .with_status(200)
+            .with_body(recent_tracks_body(0, 3))
+            .expect(2)
+            .create_async()
+            .await;
+
+        let tracks = mock_handler(&server)
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        assert_eq!(tracks.len(), 3);
+        page.assert_async().await;
+    }
+
+    #[tokio::test]
+    async fn test_update_currently_listening_keeps_file_on_api_error() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(500)
+            .with_body(r#"{"error":8,"message":"Operation failed"}"#)
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join("async_lastfm_test_currently_listening.json");
+        let path = path.to_str().unwrap();
+        std::fs::write(path, r#"{"name":"Previous"}"#).unwrap();
+
+        let result = mock_handler(&server).update_currently_listening(path).await;
+        let content = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert!(matches!(result, Err(LastFmError::Api(ref e)) if e.error == 8));
+        assert_eq!(content, r#"{"name":"Previous"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_retries_rate_limited_requests() {
+        let mut server = Server::new_async().await;
+        let rate_limited = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(429)
+            .with_body("Too Many Requests")
+            .expect(2)
+            .create_async()
+            .await;
+        let ok = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let page = mock_handler(&server)
+            .get_recent_tracks_page(1, 1)
+            .await
+            .unwrap();
+
+        rate_limited.assert_async().await;
+        ok.assert_async().await;
+        assert_eq!(page.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fetch_retries_rate_limit_error_code() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(r#"{"error":29,"message":"Rate limit exceeded"}"#)
+            .expect(3)
+            .create_async()
+            .await;
+
+        let result = mock_handler(&server)
+            .with_max_retries(2)
+            .get_user_loved_tracks_total()
+            .await;
+
+        mock.assert_async().await;
+        assert!(matches!(result, Err(LastFmError::Api(ref e)) if e.error == 29));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_does_not_retry_invalid_api_key() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(403)
+            .with_body(r#"{"error":10,"message":"Invalid API key"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let result = mock_handler(&server).get_user_loved_tracks_total().await;
+
+        mock.assert_async().await;
+        assert!(matches!(result, Err(LastFmError::Api(ref e)) if e.error == 10));
+    }
+
+    #[tokio::test]
+    async fn test_watch_now_playing_polls_until_cancelled() {
+        let mut server = Server::new_async().await;
+        let mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(1, 1))
+            .expect_at_least(3)
+            .create_async()
+            .await;
+
+        let path = std::env::temp_dir().join("async_lastfm_test_watch_now_playing.json");
+        let path = path.to_str().unwrap().to_string();
+        let shutdown = CancellationToken::new();
+
+        let handler = mock_handler(&server);
+        let watcher = {
+            let path = path.clone();
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                handler
+                    .watch_now_playing(&path, Duration::from_millis(10), shutdown)
+                    .await;
+            })
+        };
+
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(1), watcher)
+            .await
+            .expect("watcher should stop once cancelled")
+            .unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{}");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "server")]
+    #[tokio::test]
+    async fn test_serve_now_playing_returns_json() {
+        let mut server = Server::new_async().await;
+        let _recent = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "recenttracks": {
+                        "track": [{
+                            "artist": { "mbid": "", "#text": "Artist" },
+                            "streamable": "0",
+                            "image": [],
+                            "album": { "mbid": "", "#text": "Album" },
+                            "@attr": { "nowplaying": "true" },
+                            "name": "Playing",
+                            "mbid": "",
+                            "url": ""
+                        }],
+                        "@attr": {
+                            "user": "test_user",
+                            "totalPages": "1",
+                            "page": "1",
+                            "perPage": "1",
+                            "total": "1"
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let shutdown = CancellationToken::new();
+
+        let handler = mock_handler(&server);
+        let serving = {
+            let shutdown = shutdown.clone();
+            tokio::spawn(async move {
+                handler
+                    .serve_now_playing_on(listener, Duration::from_millis(10), shutdown)
+                    .await
+            })
+        };
+
+        let mut body = serde_json::Value::Null;
+        for _ in 0..50 {
+            let response = reqwest::get(&url).await.unwrap();
+            assert_eq!(response.headers()["access-control-allow-origin"], "*");
+            body = response.json().await.unwrap();
+            if !body["track"].is_null() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(1), serving)
+            .await
+            .expect("server should stop once cancelled")
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(body["track"]["name"], "Playing");
+        assert_eq!(body["track"]["artist"]["#text"], "Artist");
+        assert!(body["updated_at"].is_i64());
+    }
+
+    #[tokio::test]
+    async fn test_api_keys_rotate_across_requests() {
+        let mut server = Server::new_async().await;
+        let mut mocks = Vec::new();
+        for (key, expected) in [("key1", 2), ("key2", 1), ("key3", 1)] {
+            mocks.push(
+                server
+                    .mock("GET", "/")
+                    .match_query(Matcher::UrlEncoded("api_key".into(), key.into()))
+                    .with_status(200)
+                    .with_body(recent_tracks_body(10, 1))
+                    .expect(expected)
+                    .create_async()
+                    .await,
+            );
+        }
+
+        let handler = mock_handler(&server).with_api_keys(&["key1", "key2", "key3"]);
+        for page in 1..=4 {
+            handler.get_recent_tracks_page(page, 1).await.unwrap();
+        }
+
+        for mock in mocks {
+            mock.assert_async().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_loved_tracks_are_newest_first_with_total() {
+        let mut server = Server::new_async().await;
+        let mut mocks = Vec::new();
+        for (page, first_uts) in [
+            ("1", 1_700_000_000),
+            ("2", 1_800_000_000),
+            ("3", 1_600_000_000),
+        ] {
+            mocks.push(
+                server
+                    .mock("GET", "/")
+                    .match_query(Matcher::AllOf(vec![
+                        Matcher::UrlEncoded("page".into(), page.into()),
+                        Matcher::UrlEncoded("limit".into(), "2".into()),
+                    ]))
+                    .with_status(200)
+                    .with_body(loved_tracks_body_from(6, 2, first_uts))
+                    .create_async()
+                    .await,
+            );
+        }
+        let _discovery = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded("limit".into(), "1".into()))
+            .with_status(200)
+            .with_body(loved_tracks_body(6, 1))
+            .create_async()
+            .await;
+
+        let handler = mock_handler(&server).with_max_page_limit(2);
+        let tracks = handler
+            .get_user_loved_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        assert_eq!(tracks.len(), 6);
+        assert!(tracks
+            .windows(2)
+            .all(|pair| pair[0].get_timestamp() >= pair[1].get_timestamp()));
+        assert_eq!(tracks[0].get_timestamp(), Some(1_800_000_000));
+        assert_eq!(handler.get_user_loved_tracks_total().await.unwrap(), 6);
+    }
+
+    #[test]
+    fn test_client_builds_with_selected_tls_backend() {
+        assert!(build_client(None).is_ok());
+        assert!(LastFMHandler::from_config(&Config::new("key", "user")).is_ok());
+    }
+
+    #[test]
+    fn test_getters_reflect_construction_inputs() {
+        let handler = LastFMHandler::from_config(&Config::new("key", "some_user")).unwrap();
+
+        assert_eq!(handler.username(), "some_user");
+        assert_eq!(handler.base_url(), BASE_URL);
+        assert_eq!(handler.max_limit(), API_MAX_LIMIT);
+
+        let mut config = Config::new("key", "other_user");
+        config.base_url = Some("http://localhost:1234/".to_string());
+        config.max_page_limit = Some(200);
+        let handler = LastFMHandler::from_config(&config).unwrap();
+
+        assert_eq!(handler.username(), "other_user");
+        assert_eq!(handler.base_url(), "http://localhost:1234/");
+        assert_eq!(handler.max_limit(), 200);
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_library_respects_rate_limit() {
+        let mut server = Server::new_async().await;
+        let mut mocks = Vec::new();
+        for (method, body) in [
+            ("user.getrecenttracks", recent_tracks_body(2, 2)),
+            ("user.getlovedtracks", loved_tracks_body(1, 1)),
+            ("user.gettoptracks", top_tracks_body(3, 3)),
+        ] {
+            mocks.push(
+                server
+                    .mock("GET", "/")
+                    .match_query(Matcher::UrlEncoded("method".into(), method.into()))
+                    .with_status(200)
+                    .with_body(body)
+                    .expect(2)
+                    .create_async()
+                    .await,
+            );
+        }
+
+        let snapshot = mock_handler(&server)
+            .with_rate_limit(50)
+            .snapshot_library()
+            .await
+            .unwrap();
+
+        assert_eq!(snapshot.recent_tracks.len(), 2);
+        assert_eq!(snapshot.loved_tracks.len(), 1);
+        assert_eq!(snapshot.top_tracks.len(), 3);
+        for mock in &mocks {
+            mock.assert_async().await;
+        }
+    }
+
+    /// Answers library endpoints, recording the method and (paused) time of each request
+    #[cfg(feature = "testing")]
+    #[derive(Default)]
+    struct TimedTransport {
+        requests: Mutex<Vec<(String, tokio::time::Instant)>>,
+    }
+
+    #[cfg(feature = "testing")]
+    impl HttpTransport for TimedTransport {
+        fn get(&self, params: &QueryParams) -> Result<String> {
+            let method = params["method"].clone();
+            let body = match method.as_str() {
+                "user.getrecenttracks" => recent_tracks_body(2, 2),
+                "user.getlovedtracks" => loved_tracks_body(1, 1),
+                _ => top_tracks_body(3, 3),
+            };
+            self.requests
+                .lock()
+                .unwrap()
+                .push((method, tokio::time::Instant::now()));
+
+            Ok(body)
+        }
+    }
+
+    #[cfg(feature = "testing")]
+    #[tokio::test(start_paused = true)]
+    async fn test_snapshot_library_spaces_concurrent_requests() {
+        let transport = Arc::new(TimedTransport::default());
+
+        LastFMHandler::from_config(&Config::new("test_key", "test_user"))
+            .unwrap()
+            .with_transport(Arc::clone(&transport))
+            .with_rate_limit(50)
+            .snapshot_library()
+            .await
+            .unwrap();
+
+        let requests = transport.requests.lock().unwrap();
+        for method in [
+            "user.getrecenttracks",
+            "user.getlovedtracks",
+            "user.gettoptracks",
+        ] {
+            let hits = requests.iter().filter(|(name, _)| name == method).count();
+            assert_eq!(hits, 2, "{method}");
+        }
+        // However the endpoints interleave, consecutive requests are a full slot apart
+        for pair in requests.windows(2) {
+            assert!(pair[1].1 - pair[0].1 >= Duration::from_millis(20));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_progress_callback_counts_increase() {
+        let mut server = Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(recent_tracks_body(12_000, 2))
+            .create_async()
+            .await;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&events);
+        let handler = mock_handler(&server).with_progress_callback(move |event| {
+            recorded.lock().unwrap().push(event);
+        });
+
+        handler
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await
+            .unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert!(events.windows(2).all(|w| w[0].fetched < w[1].fetched));
+        assert!(events.windows(2).all(|w| w[0].chunk < w[1].chunk));
+        assert!(events
+            .iter()
+            .all(|e| e.total == 12_000 && e.total_chunks == 3));
+    }
+
+    /// A single scrobble carrying album art, for image source tests.
+    fn recent_track_with_album_art_body() -> String {
+        let mut body: serde_json::Value =
+            serde_json::from_str(&recent_tracks_body_of(1, &[("Track", 1_700_000_000)])).unwrap();
+        body["recenttracks"]["track"][0]["image"] =
+            serde_json::json!([{ "size": "extralarge", "#text": "https://img/album.png" }]);
+        body.to_string()
+    }
+
+    async fn play_counts_with_image_source(
+        source: ImageSource,
+        expected_artist_calls: usize,
+    ) -> Vec<serde_json::Value> {
+        let mut server = Server::new_async().await;
+        let artist_info = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded(
+                "method".into(),
+                "artist.getinfo".into(),
+            ))
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "artist": {
+                        "name": "Artist",
+                        "url": "",
+                        "image": [
+                            { "size": "small", "#text": "https://img/artist_small.png" },
+                            { "size": "extralarge", "#text": "https://img/artist.png" }
+                        ]
+                    }
+                })
+                .to_string(),
+            )
+            .expect(expected_artist_calls)
+            .create_async()
+            .await;
+        let _recent = server
+            .mock("GET", "/")
+            .match_query(Matcher::UrlEncoded(
+                "method".into(),
+                "user.getrecenttracks".into(),
+            ))
+            .with_status(200)
+            .with_body(recent_track_with_album_art_body())
+            .create_async()
+            .await;
+
+        let path =
+            std::env::temp_dir().join(format!("async_lastfm_test_image_source_{source:?}.json"));
+        let path = path.to_str().unwrap();
+
+        mock_handler(&server)
+            .update_recent_play_counts(TrackLimit::Limited(1), path, source)
+            .await
+            .unwrap();
+        let content = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        artist_info.assert_async().await;
+
+        serde_json::from_str(&content).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_play_counts_use_album_art_by_default() {
+        let play_counts = play_counts_with_image_source(ImageSource::default(), 0).await;
+
+        assert_eq!(play_counts[0]["image_url"], "https://img/album.png");
+    }
+
+    #[tokio::test]
+    async fn test_play_counts_use_artist_image_when_requested() {
+        let play_counts = play_counts_with_image_source(ImageSource::Artist, 1).await;
+
+        assert_eq!(play_counts[0]["image_url"], "https://img/artist.png");
+    }
+
+    #[tokio::test]
+    async fn test_export_uses_preferred_image_size() {
+        let mut server = Server::new_async().await;
+        let _recent = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(
+                serde_json::json!({
+                    "recenttracks": {
+                        "track": [{
+                            "artist": { "mbid": "", "#text": "Artist" },
+                            "streamable": "0",
+                            "image": [
+                                { "size": "small", "#text": "https://img/small.png" },
+                                { "size": "medium", "#text": "https://img/medium.png" },
+                                { "size": "large", "#text": "https://img/large.png" },
+                                { "size": "extralarge", "#text": "https://img/extralarge.png" }
+                            ],
+                            "album": { "mbid": "", "#text": "Album" },
+                            "date": { "uts": "1700000000", "#text": "" },
+                            "name": "Track",
+                            "mbid": "",
+                            "url": ""
+                        }],
+                        "@attr": {
+                            "user": "test_user",
+                            "totalPages": "1",
+                            "page": "1",
+                            "perPage": "1",
+                            "total": "1"
+                        }
+                    }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let root = std::env::temp_dir().join("async_lastfm_test_preferred_image_size");
+        let store = DataStore::new(&root);
+
+        let filename = mock_handler(&server)
+            .with_preferred_image_size(ImageSize::Medium)
+            .export_recent_play_counts(&store, TrackLimit::Limited(1), ImageSource::Album)
+            .await
+            .unwrap();
+
+        let saved: Vec<Vec<serde_json::Value>> = store
+            .load(Path::new(&filename).file_name().unwrap())
+            .unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert_eq!(saved[0][0]["image_url"], "https://img/medium.png");
+    }
+
+    #[tokio::test]
+    async fn test_schema_probe_flags_drifted_response() {
+        let drifted = serde_json::json!({
+            "recenttracks": {
+                "track": [{ "name": "Track", "artist": "Artist" }],
+                "@attr": { "user": "test_user", "total": { "value": 3000 } }
+            }
+        })
+        .to_string();
+
+        let mut server = Server::new_async().await;
+        let discovery = server
+            .mock("GET", "/")
+            .match_query(Matcher::Any)
+            .with_status(200)
+            .with_body(drifted)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let result = mock_handler(&server)
+            .with_strict_schema(true)
+            .get_user_recent_tracks(TrackLimit::Unlimited)
+            .await;
+
+        discovery.assert_async().await;
+        let Err(LastFmError::SchemaMismatch(issues)) = result else {
+            panic!("expected a schema mismatch, got {result:?}");
+        };
+        assert_eq!(
+            issues,
+            vec![
+                "`recenttracks.@attr.total` is not a number".to_string(),
+                "`recenttracks.track.artist` is missing or not an object".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_schema_probe_accepts_expected_response() {
+        let response: serde_json::Value =
+            serde_json::from_str(&recent_tracks_body(3000, 1)).unwrap();
+
+        assert!(probe_schema(&response, "recenttracks", "track").is_empty());
+        assert_eq!(
+            probe_schema(&response, "lovedtracks", "track"),
+            vec!["`lovedtracks` is missing or not an object".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_sign_params_sorts_keys_and_skips_format() {
+        let params = ParamBuilder::new()
+            .method("track.love")
+            .param("track", "Track")
+            .param("artist", "Artist")
+            .param("api_key", "key")
+            .param("sk", "session")
+            .param("format", "json")
+            .build();
+
+        assert_eq!(
+            sign_params(&params, "secret"),
+            "13c1fb83eda6f52125f794cf6ec4b0ca"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_love_track_requires_session() {
+        let server = Server::new_async().await;
+
+        let result = mock_handler(&server).love_track("Artist", "Track").await;
+
+        assert!(matches!(result, Err(LastFmError::Other(_))));
+    }
+
+    #[tokio::test]
+    async fn test_love_tracks_reports_each_pair() {
+        let mut server = Server::new_async().await;
+        let loved = server
+            .mock("POST", "/")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("method".into(), "track.love".into()),
+                Matcher::UrlEncoded("artist".into(), "Artist".into()),
+                Matcher::UrlEncoded("sk".into(), "session".into()),
+                Matcher::Regex("api_sig=[0-9a-f]{32}".into()),
+            ]))
+            .with_status(200)
+            .with_body("{}")
+            .expect(2)
+            .create_async()
+            .await;
+        let missing = server
+            .mock("POST", "/")
+            .match_body(Matcher::UrlEncoded("artist".into(), "Unknown".into()))
+            .with_status(200)
+            .with_body(r#"{"error":6,"message":"Track not found"}"#)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let tracks = [
+            ("Artist", "First"),
+            ("Unknown", "Track"),
+            ("Artist", "Second"),
+            ("artist", "first"),
+            ("Unknown", "Track"),
+        ]
+        .map(|(artist, track)| (artist.to_string(), track.to_string()));
+
+        let results = mock_handler(&server)
+            .with_session("secret", "session")
+            .love_tracks(&tracks)
+            .await;
+
+        loved.assert_async().await;
+        missing.assert_async().await;
+        assert_eq!(results.len(), 5);
+        assert!(results[0].is_ok());
+        assert!(matches!(&results[1], Err(LastFmError::Api(e)) if e.error == 6));
+        assert!(results[2].is_ok());
+        assert!(results[3].is_ok());
+        assert!(matches!(results[4], Err(LastFmError::Other(_))));
     }
 }
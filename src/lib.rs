@@ -1,6 +1,9 @@
 #[path = "analytics.rs"]
 pub mod analytics;
 
+#[path = "cache.rs"]
+mod cache;
+
 #[path = "config.rs"]
 pub mod config;
 
@@ -10,6 +13,19 @@ pub mod file_handler;
 #[path = "lastfm_handler.rs"]
 pub mod lastfm_handler;
 
+#[path = "prelude.rs"]
+pub mod prelude;
+
+#[path = "rate_limiter.rs"]
+mod rate_limiter;
+
+#[path = "store.rs"]
+pub mod store;
+
+#[cfg(test)]
+#[path = "test_utils.rs"]
+mod test_utils;
+
 #[path = "types.rs"]
 pub mod types;
 
@@ -18,3 +34,9 @@ pub mod url_builder;
 
 #[path = "error.rs"]
 pub mod error;
+
+// Offline analysis needs neither an API key nor environment variables
+pub use analytics::AnalysisHandler;
+pub use file_handler::{DataStore, FileFormat, FileHandler};
+
+pub use error::{LastFmError, Result};
@@ -0,0 +1,32 @@
+//! The most commonly used types, for glob import.
+//!
+//! ```
+//! use async_lastfm::prelude::*;
+//!
+//! fn summarize(tracks: &[RecentTrack]) -> Result<TrackStats> {
+//!     if tracks.is_empty() {
+//!         return Err(LastFmError::Other("no tracks".to_string()));
+//!     }
+//!     Ok(AnalysisHandler::analyze_tracks(tracks, 5))
+//! }
+//!
+//! let limit = TrackLimit::Limited(50);
+//! let period = Period::Month;
+//! let format = FileFormat::Json;
+//!
+//! assert!(matches!(limit, TrackLimit::Limited(50)));
+//! assert!(matches!(period, Period::Month));
+//! assert!(matches!(format, FileFormat::Json));
+//! assert!(summarize(&[]).is_err());
+//! ```
+
+pub use crate::analytics::{
+    AnalysisHandler, GenreOptions, GenreStats, ListeningPatterns, TrackStats,
+};
+pub use crate::error::{LastFmError, Result};
+pub use crate::file_handler::{DataStore, FileFormat, FileHandler};
+pub use crate::lastfm_handler::{LastFMHandler, Period, TrackLimit};
+pub use crate::types::{
+    ImageSize, LovedTrack, RecentTrack, RecentTrackExtended, Timestamped, TopAlbum, TopArtist,
+    TopTrack, TrackInfo, TrackPlayInfo,
+};
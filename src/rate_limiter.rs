@@ -0,0 +1,53 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{sleep_until, Instant};
+
+/// Spaces requests out so that at most one starts per `interval`.
+///
+/// Shared between clones of a handler, so concurrent fetches draw from the same budget.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `requests_per_second` requests per second.
+    pub fn new(requests_per_second: u32) -> Self {
+        RateLimiter {
+            interval: Duration::from_secs(1) / requests_per_second.max(1),
+            next_slot: Mutex::new(None),
+        }
+    }
+
+    /// Wait until a request may be sent.
+    pub async fn acquire(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().await;
+            let now = Instant::now();
+            let slot = next_slot.map_or(now, |next| next.max(now));
+            *next_slot = Some(slot + self.interval);
+            slot
+        };
+
+        sleep_until(slot).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_are_spaced() {
+        let limiter = RateLimiter::new(10);
+        let start = Instant::now();
+
+        for _ in 0..5 {
+            limiter.acquire().await;
+        }
+
+        // The first request goes out immediately, the next four wait 100ms each
+        assert_eq!(start.elapsed(), Duration::from_millis(400));
+    }
+}
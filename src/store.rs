@@ -0,0 +1,345 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::Result;
+use std::path::Path;
+#[cfg(feature = "sqlite")]
+use std::sync::Mutex;
+
+use crate::file_handler::{CsvRow, DataStore, FileFormat};
+use crate::types::{RecentTrack, Timestamped};
+
+/// Somewhere tracks can be persisted, such as a directory of files or a database.
+///
+/// Items are grouped in named collections: a file name relative to the root
+/// for `DataStore`, a collection name for `SqliteStore`. A collection that
+/// doesn't exist yet is empty, and appending to it creates it.
+pub trait TrackStore {
+    /// Save items to a new collection.
+    ///
+    /// # Arguments
+    /// * `data` - Items to save
+    /// * `format` - File format, ignored by stores that aren't file based
+    /// * `name` - Prefix of the file name, or name of the collection
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the items cannot be written
+    ///
+    /// # Returns
+    /// * `Result<String>` - Name of the collection, to pass to the other methods
+    fn save<T: Serialize + CsvRow + Timestamped>(
+        &self,
+        data: &[T],
+        format: &FileFormat,
+        name: &str,
+    ) -> Result<String>;
+
+    /// Append items to an existing collection.
+    ///
+    /// # Arguments
+    /// * `data` - Items to append
+    /// * `name` - Name of the collection
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the items cannot be written
+    ///
+    /// # Returns
+    /// * `Result<String>` - Name of the collection
+    fn append<T: Serialize + DeserializeOwned + Clone + CsvRow + Timestamped>(
+        &self,
+        data: &[T],
+        name: &str,
+    ) -> Result<String>;
+
+    /// Load every item of a collection.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the collection
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the collection cannot be read or deserialized
+    ///
+    /// # Returns
+    /// * `Result<Vec<T>>` - The loaded items, empty if the collection doesn't exist
+    fn load<T: DeserializeOwned>(&self, name: &str) -> Result<Vec<T>>;
+
+    /// Timestamp of the most recent scrobble in a collection of recent tracks.
+    ///
+    /// # Arguments
+    /// * `name` - Name of the collection
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the collection cannot be read
+    ///
+    /// # Returns
+    /// * `Result<Option<u32>>` - The timestamp, `None` if no track has one
+    fn latest_timestamp(&self, name: &str) -> Result<Option<u32>> {
+        Ok(self
+            .load::<RecentTrack>(name)?
+            .iter()
+            .filter_map(Timestamped::get_timestamp)
+            .max())
+    }
+}
+
+impl TrackStore for DataStore {
+    fn save<T: Serialize + CsvRow + Timestamped>(
+        &self,
+        data: &[T],
+        format: &FileFormat,
+        name: &str,
+    ) -> Result<String> {
+        let path = self.save_rows(data, format, name)?;
+
+        // Saved files are named relative to the root, like the other methods expect
+        Ok(Path::new(&path)
+            .file_name()
+            .map_or(path.clone(), |file_name| {
+                file_name.to_string_lossy().into_owned()
+            }))
+    }
+
+    fn append<T: Serialize + DeserializeOwned + Clone + CsvRow + Timestamped>(
+        &self,
+        data: &[T],
+        name: &str,
+    ) -> Result<String> {
+        std::fs::create_dir_all(self.root())?;
+        match FileFormat::from_path(Path::new(name)) {
+            Some(FileFormat::Csv) => self.append_csv(data, name),
+            _ => DataStore::append(self, data, name),
+        }?;
+
+        Ok(name.to_string())
+    }
+
+    fn load<T: DeserializeOwned>(&self, name: &str) -> Result<Vec<T>> {
+        match DataStore::load(self, name) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            loaded => loaded,
+        }
+    }
+}
+
+/// A SQLite database of tracks.
+///
+/// Items are stored as JSON, keyed by collection and timestamp, so appending
+/// a scrobble that is already stored does nothing. Items without a
+/// timestamp (currently playing) are not stored.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    /// Open the database at `path`, creating it if needed.
+    ///
+    /// # Arguments
+    /// * `path` - Path to the database file
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the database cannot be opened or initialized
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::init(rusqlite::Connection::open(path).map_err(std::io::Error::other)?)
+    }
+
+    /// Open a database that only lives in memory, mostly useful for tests.
+    ///
+    /// # Errors
+    /// * `std::io::Error` - If the database cannot be initialized
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(rusqlite::Connection::open_in_memory().map_err(std::io::Error::other)?)
+    }
+
+    fn init(connection: rusqlite::Connection) -> Result<Self> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS tracks (
+                    collection TEXT NOT NULL,
+                    uts INTEGER NOT NULL,
+                    data TEXT NOT NULL,
+                    PRIMARY KEY (collection, uts)
+                )",
+            )
+            .map_err(std::io::Error::other)?;
+
+        Ok(SqliteStore {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Insert the timestamped items of `data`, optionally clearing the collection first.
+    fn insert<T: Serialize + Timestamped>(
+        &self,
+        data: &[T],
+        name: &str,
+        replace: bool,
+    ) -> Result<String> {
+        let mut connection = self
+            .connection
+            .lock()
+            .map_err(|_| std::io::Error::other("SQLite connection lock poisoned"))?;
+        let transaction = connection.transaction().map_err(std::io::Error::other)?;
+
+        if replace {
+            transaction
+                .execute("DELETE FROM tracks WHERE collection = ?1", [name])
+                .map_err(std::io::Error::other)?;
+        }
+        {
+            let mut statement = transaction
+                .prepare("INSERT OR IGNORE INTO tracks (collection, uts, data) VALUES (?1, ?2, ?3)")
+                .map_err(std::io::Error::other)?;
+            for item in data {
+                if let Some(uts) = item.get_timestamp() {
+                    statement
+                        .execute(rusqlite::params![name, uts, serde_json::to_string(item)?])
+                        .map_err(std::io::Error::other)?;
+                }
+            }
+        }
+        transaction.commit().map_err(std::io::Error::other)?;
+
+        Ok(name.to_string())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl TrackStore for SqliteStore {
+    fn save<T: Serialize + CsvRow + Timestamped>(
+        &self,
+        data: &[T],
+        _format: &FileFormat,
+        name: &str,
+    ) -> Result<String> {
+        self.insert(data, name, true)
+    }
+
+    fn append<T: Serialize + DeserializeOwned + Clone + CsvRow + Timestamped>(
+        &self,
+        data: &[T],
+        name: &str,
+    ) -> Result<String> {
+        self.insert(data, name, false)
+    }
+
+    fn load<T: DeserializeOwned>(&self, name: &str) -> Result<Vec<T>> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| std::io::Error::other("SQLite connection lock poisoned"))?;
+        let mut statement = connection
+            .prepare("SELECT data FROM tracks WHERE collection = ?1 ORDER BY uts")
+            .map_err(std::io::Error::other)?;
+        let rows = statement
+            .query_map([name], |row| row.get::<_, String>(0))
+            .map_err(std::io::Error::other)?;
+
+        rows.map(|data| Ok(serde_json::from_str(&data.map_err(std::io::Error::other)?)?))
+            .collect()
+    }
+
+    fn latest_timestamp(&self, name: &str) -> Result<Option<u32>> {
+        let connection = self
+            .connection
+            .lock()
+            .map_err(|_| std::io::Error::other("SQLite connection lock poisoned"))?;
+
+        connection
+            .query_row(
+                "SELECT MAX(uts) FROM tracks WHERE collection = ?1",
+                [name],
+                |row| row.get(0),
+            )
+            .map_err(std::io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::create_recent_track_at;
+
+    /// Saves, appends and reloads through the trait only
+    fn round_trip(store: &impl TrackStore, format: &FileFormat, name: &str) -> Vec<RecentTrack> {
+        let collection = store
+            .save(
+                &[create_recent_track_at("Artist", "Song1", 100)],
+                format,
+                name,
+            )
+            .unwrap();
+        store
+            .append(
+                &[create_recent_track_at("Artist", "Song2", 200)],
+                &collection,
+            )
+            .unwrap();
+
+        assert_eq!(store.latest_timestamp(&collection).unwrap(), Some(200));
+        store.load(&collection).unwrap()
+    }
+
+    #[test]
+    fn test_data_store_as_track_store() {
+        let root = std::env::temp_dir().join("async_lastfm_test_track_store");
+        let tracks = round_trip(&DataStore::new(&root), &FileFormat::Ndjson, "recent");
+        std::fs::remove_dir_all(&root).unwrap();
+
+        let names: Vec<&str> = tracks.iter().map(|track| track.name.as_str()).collect();
+        assert_eq!(names, vec!["Song1", "Song2"]);
+    }
+
+    /// Collections that don't exist yet are empty, and appending creates them
+    fn missing_collection(store: &impl TrackStore, name: &str) {
+        assert!(store.load::<RecentTrack>(name).unwrap().is_empty());
+        assert_eq!(store.latest_timestamp(name).unwrap(), None);
+
+        store
+            .append(&[create_recent_track_at("Artist", "Song1", 100)], name)
+            .unwrap();
+        assert_eq!(store.latest_timestamp(name).unwrap(), Some(100));
+    }
+
+    #[test]
+    fn test_data_store_missing_collection() {
+        let root = std::env::temp_dir().join("async_lastfm_test_track_store_missing");
+        for name in ["recent.json", "recent.ndjson"] {
+            missing_collection(&DataStore::new(&root), name);
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_sqlite_store_appends_idempotently() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let tracks = round_trip(&store, &FileFormat::Json, "recent");
+        assert_eq!(tracks.len(), 2);
+
+        store
+            .append(
+                &[
+                    create_recent_track_at("Artist", "Song2", 200),
+                    create_recent_track_at("Artist", "Song3", 300),
+                    crate::test_utils::create_recent_track("Artist", "Now Playing"),
+                ],
+                "recent",
+            )
+            .unwrap();
+        let tracks: Vec<RecentTrack> = store.load("recent").unwrap();
+        let names: Vec<&str> = tracks.iter().map(|track| track.name.as_str()).collect();
+        assert_eq!(names, vec!["Song1", "Song2", "Song3"]);
+
+        store
+            .save(
+                &[create_recent_track_at("Artist", "Song4", 400)],
+                &FileFormat::Json,
+                "recent",
+            )
+            .unwrap();
+        assert_eq!(store.load::<RecentTrack>("recent").unwrap().len(), 1);
+        missing_collection(&store, "other");
+    }
+}
@@ -0,0 +1,35 @@
+//! Fixtures shared by the unit tests of several modules.
+
+use crate::types::{BaseMbidText, Date, RecentTrack};
+
+/// A recent track without a date, as if currently playing.
+pub(crate) fn create_recent_track(artist: &str, name: &str) -> RecentTrack {
+    RecentTrack {
+        artist: BaseMbidText {
+            mbid: String::new(),
+            text: artist.to_string(),
+        },
+        streamable: false,
+        image: Vec::new(),
+        album: BaseMbidText {
+            mbid: String::new(),
+            text: String::new(),
+        },
+        attr: None,
+        date: None,
+        name: name.to_string(),
+        mbid: String::new(),
+        url: String::new(),
+    }
+}
+
+/// A recent track scrobbled at `uts`.
+pub(crate) fn create_recent_track_at(artist: &str, name: &str, uts: u32) -> RecentTrack {
+    RecentTrack {
+        date: Some(Date {
+            uts,
+            text: String::new(),
+        }),
+        ..create_recent_track(artist, name)
+    }
+}
@@ -1,5 +1,8 @@
+use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
 
 // UTILS
 fn u32_from_str<'de, D>(deserializer: D) -> Result<u32, D::Error>
@@ -11,6 +14,24 @@ where
     s.parse::<u32>().map_err(serde::de::Error::custom)
 }
 
+/// Accepts a `u32` given either as a string (API responses) or a number (saved files)
+fn u32_from_str_or_number<'de, D>(deserializer: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrNumber {
+        Str(String),
+        Number(u32),
+    }
+
+    match StrOrNumber::deserialize(deserializer)? {
+        StrOrNumber::Str(s) => s.parse::<u32>().map_err(serde::de::Error::custom),
+        StrOrNumber::Number(n) => Ok(n),
+    }
+}
+
 fn bool_from_str<'de, D>(deserializer: D) -> Result<bool, D::Error>
 where
     D: Deserializer<'de>,
@@ -24,6 +45,23 @@ where
     }
 }
 
+fn images_from_one_or_many<'de, D>(deserializer: D) -> Result<Vec<TrackImage>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        Many(Vec<TrackImage>),
+        One(TrackImage),
+    }
+
+    match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::Many(images) => Ok(images),
+        OneOrMany::One(image) => Ok(vec![image]),
+    }
+}
+
 // BASE SCHEMAS ===============================================================
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BaseOptions {
@@ -31,40 +69,142 @@ pub struct BaseOptions {
     pub page: u16,
 }
 
+/// Pagination attributes of a list response.
+///
+/// Only `total` is required; the other fields default when an endpoint omits
+/// them, and numbers are accepted as strings or plain JSON numbers.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BaseResponse {
+    #[serde(default)]
     pub user: String,
-    #[serde(deserialize_with = "u32_from_str", rename = "totalPages")]
+    #[serde(
+        default,
+        deserialize_with = "u32_from_str_or_number",
+        rename = "totalPages",
+        alias = "totalpages"
+    )]
     pub total_pages: u32,
-    #[serde(deserialize_with = "u32_from_str")]
+    #[serde(default, deserialize_with = "u32_from_str_or_number")]
     pub page: u32,
-    #[serde(deserialize_with = "u32_from_str", rename = "perPage")]
+    #[serde(
+        default,
+        deserialize_with = "u32_from_str_or_number",
+        rename = "perPage",
+        alias = "perpage"
+    )]
     pub per_page: u32,
-    #[serde(deserialize_with = "u32_from_str")]
+    #[serde(deserialize_with = "u32_from_str_or_number")]
     pub total: u32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BaseMbidText {
+    #[serde(default)]
     pub mbid: String,
-    #[serde(rename = "#text")]
+    #[serde(default, rename = "#text")]
     pub text: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct BaseObject {
+    #[serde(default)]
     pub mbid: String,
+    #[serde(default)]
     pub url: String,
+    #[serde(default)]
     pub name: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TrackImage {
+    #[serde(default)]
     pub size: String,
-    #[serde(rename = "#text")]
+    #[serde(default, rename = "#text")]
     pub text: String,
 }
 
+/// Image sizes returned by Last.fm, from smallest to largest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageSize {
+    Small,
+    Medium,
+    Large,
+    ExtraLarge,
+}
+
+impl ImageSize {
+    fn as_api_str(self) -> &'static str {
+        match self {
+            ImageSize::Small => "small",
+            ImageSize::Medium => "medium",
+            ImageSize::Large => "large",
+            ImageSize::ExtraLarge => "extralarge",
+        }
+    }
+}
+
+/// Pick the URL of the image closest to `size`.
+///
+/// Falls back to larger sizes first, then smaller ones, then any image with a URL.
+///
+/// # Arguments
+/// * `images` - Images as returned by Last.fm
+/// * `size` - Preferred size
+///
+/// # Returns
+/// * `Option<&str>` - The image URL, or `None` if no image has one
+#[must_use]
+pub fn best_image(images: &[TrackImage], size: ImageSize) -> Option<&str> {
+    const SIZES: [ImageSize; 4] = [
+        ImageSize::Small,
+        ImageSize::Medium,
+        ImageSize::Large,
+        ImageSize::ExtraLarge,
+    ];
+    let position = SIZES.iter().position(|s| *s == size).unwrap_or_default();
+
+    let find = |size: ImageSize| {
+        images
+            .iter()
+            .find(|image| image.size == size.as_api_str() && !image.text.is_empty())
+            .map(|image| image.text.as_str())
+    };
+
+    SIZES[position..]
+        .iter()
+        .chain(SIZES[..position].iter().rev())
+        .find_map(|size| find(*size))
+        .or_else(|| {
+            images
+                .iter()
+                .find(|image| !image.text.is_empty())
+                .map(|image| image.text.as_str())
+        })
+}
+
+/// Build the Last.fm page URL of a track.
+///
+/// Names are form-encoded like on Last.fm: spaces become `+` and reserved
+/// characters such as `/` or `&` are percent-encoded.
+///
+/// # Arguments
+/// * `artist` - Artist name
+/// * `track` - Track name
+///
+/// # Returns
+/// * `String` - The URL, e.g. `https://www.last.fm/music/AC%2FDC/_/Back+in+Black`
+#[must_use]
+pub fn track_url(artist: &str, track: &str) -> String {
+    let encode =
+        |name: &str| url::form_urlencoded::byte_serialize(name.as_bytes()).collect::<String>();
+
+    format!(
+        "https://www.last.fm/music/{}/_/{}",
+        encode(artist),
+        encode(track)
+    )
+}
+
 // #[derive(Serialize, Debug, Deserialize, Clone)]
 // pub struct Date {
 //     #[serde(deserialize_with = "u32_from_str")]
@@ -75,8 +215,9 @@ pub struct TrackImage {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Streamable {
+    #[serde(default)]
     pub fulltrack: String,
-    #[serde(rename = "#text")]
+    #[serde(default, rename = "#text")]
     pub text: String,
 }
 
@@ -93,11 +234,17 @@ pub struct Artist {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LovedTrack {
     pub artist: BaseObject,
-    pub date: Date,
+    /// When the track was loved; absent for some old loves
+    #[serde(default)]
+    pub date: Option<Date>,
+    #[serde(deserialize_with = "images_from_one_or_many")]
     pub image: Vec<TrackImage>,
     pub streamable: Streamable,
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub mbid: String,
+    #[serde(default)]
     pub url: String,
 }
 
@@ -118,6 +265,7 @@ pub struct ApiRecentTrackExtended {
     pub artist: BaseObject,
     #[serde(deserialize_with = "bool_from_str")]
     pub streamable: bool,
+    #[serde(deserialize_with = "images_from_one_or_many")]
     pub image: Vec<TrackImage>,
     pub album: BaseObject,
     #[serde(rename = "@attr")]
@@ -154,8 +302,22 @@ pub struct UserRecentTracks {
     pub recenttracks: RecentTracks,
 }
 
+// Artist Tracks Schema
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ArtistTracks {
+    pub track: Vec<ApiRecentTrack>,
+    #[serde(rename = "@attr")]
+    pub attr: BaseResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UserArtistTracks {
+    pub artisttracks: ArtistTracks,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Attributes {
+    #[serde(default)]
     pub nowplaying: String,
 }
 
@@ -165,6 +327,7 @@ pub struct ApiRecentTrack {
     pub artist: BaseMbidText,
     #[serde(deserialize_with = "bool_from_str")]
     pub streamable: bool,
+    #[serde(deserialize_with = "images_from_one_or_many")]
     pub image: Vec<TrackImage>,
     pub album: BaseMbidText,
     #[serde(rename = "@attr")]
@@ -192,15 +355,19 @@ pub struct RecentTrack {
     pub album: BaseMbidText,
     pub attr: Option<Attributes>,
     pub date: Option<Date>,
+    #[serde(default)]
     pub name: String,
+    #[serde(default)]
     pub mbid: String,
+    #[serde(default)]
     pub url: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Date {
+    #[serde(deserialize_with = "u32_from_str_or_number")]
     pub uts: u32,
-    #[serde(rename = "#text")]
+    #[serde(default, rename = "#text")]
     pub text: String,
 }
 
@@ -230,11 +397,147 @@ impl From<ApiDate> for Date {
     }
 }
 
+impl RecentTrack {
+    /// The track's Last.fm page, built from the artist and track names when Last.fm omitted it.
+    ///
+    /// # Returns
+    /// * `String` - `url` if present, otherwise the URL from `track_url`
+    #[must_use]
+    pub fn canonical_url(&self) -> String {
+        if self.url.is_empty() {
+            track_url(&self.artist.text, &self.name)
+        } else {
+            self.url.clone()
+        }
+    }
+
+    /// Get the time this track was scrobbled, in UTC.
+    ///
+    /// # Returns
+    /// * `Option<DateTime<Utc>>` - The scrobble time, or `None` if the track is currently playing
+    #[must_use]
+    pub fn played_at(&self) -> Option<DateTime<Utc>> {
+        self.date
+            .as_ref()
+            .and_then(|d| DateTime::from_timestamp(i64::from(d.uts), 0))
+    }
+
+    /// Get the time this track was scrobbled, in the local timezone.
+    ///
+    /// # Returns
+    /// * `Option<DateTime<Local>>` - The scrobble time, or `None` if the track is currently playing
+    #[must_use]
+    pub fn played_at_local(&self) -> Option<DateTime<Local>> {
+        self.played_at().map(|dt| dt.with_timezone(&Local))
+    }
+}
+
+impl LovedTrack {
+    /// Get the time this track was loved, in UTC.
+    ///
+    /// Only `date.uts` is used, so loves whose `#text` is empty still have a date.
+    ///
+    /// # Returns
+    /// * `Option<DateTime<Utc>>` - The time the track was loved, or `None` if Last.fm omitted it
+    #[must_use]
+    pub fn loved_at(&self) -> Option<DateTime<Utc>> {
+        self.date
+            .as_ref()
+            .and_then(|d| DateTime::from_timestamp(i64::from(d.uts), 0))
+    }
+
+    /// Get the time this track was loved, in UTC.
+    ///
+    /// Same as `loved_at`, named like `RecentTrack::played_at`.
+    ///
+    /// # Returns
+    /// * `Option<DateTime<Utc>>` - The time the track was loved
+    #[must_use]
+    pub fn played_at(&self) -> Option<DateTime<Utc>> {
+        self.loved_at()
+    }
+
+    /// Get the time this track was loved, in the local timezone.
+    ///
+    /// # Returns
+    /// * `Option<DateTime<Local>>` - The time the track was loved
+    #[must_use]
+    pub fn played_at_local(&self) -> Option<DateTime<Local>> {
+        self.played_at().map(|dt| dt.with_timezone(&Local))
+    }
+}
+
+// Recent tracks are identified by artist, name and scrobble time
+impl PartialEq for RecentTrack {
+    fn eq(&self, other: &Self) -> bool {
+        self.artist.text == other.artist.text
+            && self.name == other.name
+            && self.get_timestamp() == other.get_timestamp()
+    }
+}
+
+impl Eq for RecentTrack {}
+
+impl Hash for RecentTrack {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.artist.text.hash(state);
+        self.name.hash(state);
+        self.get_timestamp().hash(state);
+    }
+}
+
+// Loved tracks are identified by artist and name
+impl PartialEq for LovedTrack {
+    fn eq(&self, other: &Self) -> bool {
+        self.artist.name == other.artist.name && self.name == other.name
+    }
+}
+
+impl Eq for LovedTrack {}
+
+impl Hash for LovedTrack {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.artist.name.hash(state);
+        self.name.hash(state);
+    }
+}
+
+impl fmt::Display for RecentTrack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.artist.text, self.name)
+    }
+}
+
+impl fmt::Display for LovedTrack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.artist.name, self.name)
+    }
+}
+
+/// A lightweight view of a scrobble: just who, what and when
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MinimalTrack {
+    pub artist: String,
+    pub name: String,
+    pub timestamp: Option<u32>,
+}
+
 pub trait Timestamped {
     #[allow(dead_code)]
     fn get_timestamp(&self) -> Option<u32>;
 }
 
+/// Sort tracks oldest-first.
+///
+/// The API returns tracks newest-first; tracks without a timestamp
+/// (currently playing) are placed at the end.
+///
+/// # Arguments
+/// * `tracks` - Tracks to sort in place
+pub fn sort_by_timestamp<T: Timestamped>(tracks: &mut [T]) {
+    tracks.sort_by_key(|track| track.get_timestamp().map_or(u64::MAX, u64::from));
+}
+
 impl Timestamped for RecentTrack {
     fn get_timestamp(&self) -> Option<u32> {
         self.date.as_ref().map(|d| d.uts)
@@ -243,7 +546,7 @@ impl Timestamped for RecentTrack {
 
 impl Timestamped for LovedTrack {
     fn get_timestamp(&self) -> Option<u32> {
-        Some(self.date.uts)
+        self.date.as_ref().map(|d| d.uts)
     }
 }
 
@@ -256,19 +559,62 @@ pub struct RankAttr {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TopTrack {
     pub streamable: Streamable,
+    #[serde(default)]
     pub mbid: String,
+    #[serde(default)]
     pub name: String,
+    #[serde(deserialize_with = "images_from_one_or_many")]
     pub image: Vec<TrackImage>,
     pub artist: BaseObject,
+    #[serde(default)]
     pub url: String,
-    #[serde(deserialize_with = "u32_from_str")]
+    /// Duration in seconds, 0 when unknown
+    #[serde(deserialize_with = "u32_from_str_or_number")]
     pub duration: u32,
     #[serde(rename = "@attr")]
     pub attr: RankAttr,
-    #[serde(deserialize_with = "u32_from_str")]
+    #[serde(deserialize_with = "u32_from_str_or_number")]
     pub playcount: u32,
 }
 
+impl TopTrack {
+    /// The track duration in seconds, `None` when unknown.
+    #[must_use]
+    pub fn duration_secs(&self) -> Option<u32> {
+        (self.duration > 0).then_some(self.duration)
+    }
+
+    /// Convert to the shape used by play count exports.
+    ///
+    /// Unlike counts computed from recent scrobbles, `play_count` is the
+    /// user's all-time play count reported by Last.fm.
+    ///
+    /// # Arguments
+    /// * `image_size` - Preferred image size, see `LastFMHandler::with_preferred_image_size`
+    ///
+    /// # Returns
+    /// * `TrackPlayInfo` - The play count entry, with the closest available image
+    #[must_use]
+    pub fn to_play_info(&self, image_size: ImageSize) -> TrackPlayInfo {
+        TrackPlayInfo {
+            name: self.name.clone(),
+            play_count: self.playcount,
+            artist: self.artist.name.clone(),
+            album: None,
+            image_url: best_image(&self.image, image_size).map(str::to_string),
+            currently_playing: false,
+            date: None,
+            url: self.url.clone(),
+        }
+    }
+}
+
+impl fmt::Display for TopTrack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.artist.name, self.name)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TopTracks {
     pub track: Vec<TopTrack>,
@@ -280,3 +626,783 @@ pub struct TopTracks {
 pub struct UserTopTracks {
     pub toptracks: TopTracks,
 }
+
+/// Represents a track's play count information
+#[derive(Debug, Serialize)]
+pub struct TrackPlayInfo {
+    pub name: String,
+    pub play_count: u32,
+    pub artist: String,
+    pub album: Option<String>,
+    pub image_url: Option<String>,
+    pub currently_playing: bool,
+    pub date: Option<u32>,
+    pub url: String,
+}
+
+// TOP ARTISTS AND ALBUMS SCHEMAS =============================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopArtist {
+    #[serde(default)]
+    pub mbid: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default, deserialize_with = "images_from_one_or_many")]
+    pub image: Vec<TrackImage>,
+    #[serde(rename = "@attr")]
+    pub attr: RankAttr,
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub playcount: u32,
+}
+
+impl fmt::Display for TopArtist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopArtists {
+    pub artist: Vec<TopArtist>,
+    #[serde(rename = "@attr")]
+    pub attr: BaseResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserTopArtists {
+    pub topartists: TopArtists,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopAlbum {
+    pub artist: BaseObject,
+    #[serde(default)]
+    pub mbid: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default, deserialize_with = "images_from_one_or_many")]
+    pub image: Vec<TrackImage>,
+    #[serde(rename = "@attr")]
+    pub attr: RankAttr,
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub playcount: u32,
+}
+
+impl fmt::Display for TopAlbum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.artist.name, self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TopAlbums {
+    pub album: Vec<TopAlbum>,
+    #[serde(rename = "@attr")]
+    pub attr: BaseResponse,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserTopAlbums {
+    pub topalbums: TopAlbums,
+}
+
+// WEEKLY CHART SCHEMAS ======================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyChartAttr {
+    pub user: String,
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub from: u32,
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub to: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyChartRange {
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub from: u32,
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub to: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyChartList {
+    #[serde(default)]
+    pub chart: Vec<WeeklyChartRange>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserWeeklyChartList {
+    pub weeklychartlist: WeeklyChartList,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyTrack {
+    pub artist: BaseMbidText,
+    #[serde(default)]
+    pub mbid: String,
+    #[serde(default)]
+    pub url: String,
+    pub name: String,
+    #[serde(default, deserialize_with = "images_from_one_or_many")]
+    pub image: Vec<TrackImage>,
+    #[serde(rename = "@attr")]
+    pub attr: RankAttr,
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub playcount: u32,
+}
+
+impl fmt::Display for WeeklyTrack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.artist.text, self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyTrackChart {
+    #[serde(default)]
+    pub track: Vec<WeeklyTrack>,
+    #[serde(rename = "@attr")]
+    pub attr: WeeklyChartAttr,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserWeeklyTrackChart {
+    pub weeklytrackchart: WeeklyTrackChart,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyAlbum {
+    pub artist: BaseMbidText,
+    #[serde(default)]
+    pub mbid: String,
+    #[serde(default)]
+    pub url: String,
+    pub name: String,
+    #[serde(rename = "@attr")]
+    pub attr: RankAttr,
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub playcount: u32,
+}
+
+impl fmt::Display for WeeklyAlbum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.artist.text, self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyAlbumChart {
+    #[serde(default)]
+    pub album: Vec<WeeklyAlbum>,
+    #[serde(rename = "@attr")]
+    pub attr: WeeklyChartAttr,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserWeeklyAlbumChart {
+    pub weeklyalbumchart: WeeklyAlbumChart,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyArtist {
+    #[serde(default)]
+    pub mbid: String,
+    #[serde(default)]
+    pub url: String,
+    pub name: String,
+    #[serde(rename = "@attr")]
+    pub attr: RankAttr,
+    #[serde(deserialize_with = "u32_from_str_or_number")]
+    pub playcount: u32,
+}
+
+impl fmt::Display for WeeklyArtist {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WeeklyArtistChart {
+    #[serde(default)]
+    pub artist: Vec<WeeklyArtist>,
+    #[serde(rename = "@attr")]
+    pub attr: WeeklyChartAttr,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct UserWeeklyArtistChart {
+    pub weeklyartistchart: WeeklyArtistChart,
+}
+
+// ARTIST INFO SCHEMAS =======================================================
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiArtistInfo {
+    pub name: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default, deserialize_with = "images_from_one_or_many")]
+    pub image: Vec<TrackImage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ArtistInfoResponse {
+    pub artist: ApiArtistInfo,
+}
+
+// TRACK INFO SCHEMAS ========================================================
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Tag {
+    pub name: String,
+    #[serde(default)]
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct TopTags {
+    #[serde(default)]
+    pub tag: Vec<Tag>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ApiTrackInfo {
+    pub name: String,
+    #[serde(default)]
+    pub mbid: String,
+    #[serde(default)]
+    pub url: String,
+    /// Duration in milliseconds, 0 when unknown
+    #[serde(deserialize_with = "u32_from_str")]
+    pub duration: u32,
+    #[serde(deserialize_with = "u32_from_str")]
+    pub listeners: u32,
+    #[serde(deserialize_with = "u32_from_str")]
+    pub playcount: u32,
+    pub artist: BaseObject,
+    #[serde(default)]
+    pub toptags: TopTags,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrackInfoResponse {
+    pub track: ApiTrackInfo,
+}
+
+/// Global information about a track, from `track.getInfo`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrackInfo {
+    pub name: String,
+    pub artist: String,
+    pub url: String,
+    /// Number of distinct listeners on Last.fm
+    pub listeners: u32,
+    /// Number of scrobbles across all Last.fm users
+    pub playcount: u32,
+    /// Duration in milliseconds as reported by `track.getInfo`, `None` when unknown
+    #[serde(alias = "duration")]
+    pub duration_ms: Option<u32>,
+    /// Names of the track's top tags
+    pub tags: Vec<String>,
+}
+
+impl TrackInfo {
+    /// The track duration in seconds, rounded down, `None` when unknown.
+    ///
+    /// Use this rather than `duration_ms` when mixing with `TopTrack` durations,
+    /// which Last.fm reports in seconds.
+    #[must_use]
+    pub fn duration_secs(&self) -> Option<u32> {
+        self.duration_ms.map(|ms| ms / 1000)
+    }
+}
+
+impl From<ApiTrackInfo> for TrackInfo {
+    fn from(api_info: ApiTrackInfo) -> Self {
+        TrackInfo {
+            name: api_info.name,
+            artist: api_info.artist.name,
+            url: api_info.url,
+            listeners: api_info.listeners,
+            playcount: api_info.playcount,
+            duration_ms: (api_info.duration > 0).then_some(api_info.duration),
+            tags: api_info
+                .toptags
+                .tag
+                .into_iter()
+                .map(|tag| tag.name)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{create_recent_track, create_recent_track_at};
+    use chrono::TimeZone;
+
+    fn api_recent_track_json(image: &serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "artist": { "mbid": "", "#text": "Artist" },
+            "streamable": "0",
+            "image": image,
+            "album": { "mbid": "", "#text": "Album" },
+            "date": { "uts": "1733318400", "#text": "04 Dec 2024, 13:20" },
+            "name": "Track",
+            "mbid": "",
+            "url": ""
+        })
+    }
+
+    #[test]
+    fn test_image_as_array() {
+        let json = api_recent_track_json(&serde_json::json!([
+            { "size": "small", "#text": "https://img/small.png" },
+            { "size": "large", "#text": "https://img/large.png" }
+        ]));
+
+        let track: ApiRecentTrack = serde_json::from_str(&json.to_string()).unwrap();
+
+        assert_eq!(track.image.len(), 2);
+        assert_eq!(track.image[1].size, "large");
+    }
+
+    #[test]
+    fn test_image_as_single_object() {
+        let json = api_recent_track_json(
+            &serde_json::json!({ "size": "large", "#text": "https://img/large.png" }),
+        );
+
+        let track: ApiRecentTrack = serde_json::from_str(&json.to_string()).unwrap();
+
+        assert_eq!(track.image.len(), 1);
+        assert_eq!(track.image[0].text, "https://img/large.png");
+    }
+
+    #[test]
+    fn test_deserialize_artist_tracks() {
+        let json = serde_json::json!({
+            "artisttracks": {
+                "track": [
+                    api_recent_track_json(&serde_json::json!([])),
+                    api_recent_track_json(&serde_json::json!([]))
+                ],
+                "@attr": {
+                    "user": "test_user",
+                    "artist": "Artist",
+                    "page": "1",
+                    "perPage": "50",
+                    "totalPages": "1",
+                    "total": "2"
+                }
+            }
+        });
+
+        let response: UserArtistTracks = serde_json::from_str(&json.to_string()).unwrap();
+
+        assert_eq!(response.artisttracks.attr.total, 2);
+        assert_eq!(response.artisttracks.track.len(), 2);
+        assert_eq!(response.artisttracks.track[0].artist.text, "Artist");
+    }
+
+    #[test]
+    fn test_base_response_without_user_and_numeric_total() {
+        let attr: BaseResponse = serde_json::from_str(r#"{"total": 1234}"#).unwrap();
+
+        assert_eq!(attr.user, "");
+        assert_eq!(attr.total, 1234);
+        assert_eq!(attr.page, 0);
+        assert_eq!(attr.total_pages, 0);
+
+        let attr: BaseResponse = serde_json::from_str(
+            r#"{"page": 2, "perpage": "50", "totalpages": "25", "total": "1234"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(attr.page, 2);
+        assert_eq!(attr.per_page, 50);
+        assert_eq!(attr.total_pages, 25);
+        assert_eq!(attr.total, 1234);
+    }
+
+    #[test]
+    fn test_recent_tracks_without_user() {
+        let json = serde_json::json!({
+            "recenttracks": {
+                "track": [api_recent_track_json(&serde_json::json!([]))],
+                "@attr": { "page": "1", "perPage": "1", "totalPages": "1", "total": 1 }
+            }
+        });
+
+        let response: UserRecentTracks = serde_json::from_str(&json.to_string()).unwrap();
+
+        assert_eq!(response.recenttracks.attr.total, 1);
+        assert_eq!(response.recenttracks.track.len(), 1);
+    }
+
+    #[test]
+    fn test_recent_track_played_at() {
+        let track = create_recent_track_at("Artist", "Track", 1_733_318_400);
+
+        assert_eq!(
+            track.played_at(),
+            Some(Utc.with_ymd_and_hms(2024, 12, 4, 13, 20, 0).unwrap())
+        );
+        assert_eq!(
+            track.played_at_local().map(|dt| dt.with_timezone(&Utc)),
+            track.played_at()
+        );
+    }
+
+    #[test]
+    fn test_now_playing_has_no_played_at() {
+        let track = create_recent_track("Artist", "Track");
+
+        assert_eq!(track.played_at(), None);
+        assert_eq!(track.played_at_local(), None);
+    }
+
+    fn loved_track() -> LovedTrack {
+        LovedTrack {
+            artist: BaseObject {
+                mbid: String::new(),
+                url: String::new(),
+                name: "Artist".to_string(),
+            },
+            date: Some(Date {
+                uts: 0,
+                text: String::new(),
+            }),
+            image: Vec::new(),
+            streamable: Streamable {
+                fulltrack: String::new(),
+                text: String::new(),
+            },
+            name: "Track".to_string(),
+            mbid: String::new(),
+            url: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_loved_track_played_at() {
+        let track = loved_track();
+
+        assert_eq!(
+            track.played_at(),
+            Some(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_display_track_types() {
+        assert_eq!(
+            create_recent_track("Artist", "Track").to_string(),
+            "Artist - Track"
+        );
+        assert_eq!(loved_track().to_string(), "Artist - Track");
+
+        let top_track = TopTrack {
+            streamable: Streamable {
+                fulltrack: String::new(),
+                text: String::new(),
+            },
+            mbid: String::new(),
+            name: "Top Track".to_string(),
+            image: Vec::new(),
+            artist: BaseObject {
+                mbid: String::new(),
+                url: String::new(),
+                name: "Top Artist".to_string(),
+            },
+            url: String::new(),
+            duration: 0,
+            attr: RankAttr {
+                rank: "1".to_string(),
+            },
+            playcount: 42,
+        };
+        assert_eq!(top_track.to_string(), "Top Artist - Top Track");
+    }
+
+    #[test]
+    fn test_durations_normalize_to_seconds() {
+        let response: TrackInfoResponse = serde_json::from_str(
+            r#"{
+                "track": {
+                    "name": "Top Track",
+                    "duration": "240000",
+                    "listeners": "10",
+                    "playcount": "20",
+                    "artist": { "mbid": "", "url": "", "name": "Top Artist" }
+                }
+            }"#,
+        )
+        .unwrap();
+        let track_info = TrackInfo::from(response.track);
+
+        let top_track: TopTrack = serde_json::from_value(serde_json::json!({
+            "streamable": { "fulltrack": "0", "#text": "0" },
+            "name": "Top Track",
+            "image": [],
+            "artist": { "mbid": "", "url": "", "name": "Top Artist" },
+            "duration": "240",
+            "@attr": { "rank": "1" },
+            "playcount": "3"
+        }))
+        .unwrap();
+
+        assert_eq!(track_info.duration_ms, Some(240_000));
+        assert_eq!(track_info.duration_secs(), Some(240));
+        assert_eq!(top_track.duration_secs(), track_info.duration_secs());
+
+        let unknown = TopTrack {
+            duration: 0,
+            ..top_track
+        };
+        assert_eq!(unknown.duration_secs(), None);
+    }
+
+    #[test]
+    fn test_top_track_to_play_info() {
+        let top_track: TopTrack = serde_json::from_value(serde_json::json!({
+            "streamable": { "fulltrack": "0", "#text": "0" },
+            "mbid": "",
+            "name": "Top Track",
+            "image": [
+                { "size": "small", "#text": "https://img/small.png" },
+                { "size": "large", "#text": "https://img/large.png" },
+                { "size": "extralarge", "#text": "https://img/extralarge.png" }
+            ],
+            "artist": { "mbid": "", "url": "", "name": "Top Artist" },
+            "url": "https://www.last.fm/music/Top+Artist/_/Top+Track",
+            "duration": "240",
+            "@attr": { "rank": "1" },
+            "playcount": "1234"
+        }))
+        .unwrap();
+
+        let info = top_track.to_play_info(ImageSize::Large);
+        let small = top_track.to_play_info(ImageSize::Small);
+
+        assert_eq!(info.play_count, 1234);
+        assert_eq!(info.name, "Top Track");
+        assert_eq!(info.artist, "Top Artist");
+        assert_eq!(info.image_url.as_deref(), Some("https://img/large.png"));
+        assert_eq!(small.image_url.as_deref(), Some("https://img/small.png"));
+        assert!(!info.currently_playing);
+        assert_eq!(info.date, None);
+    }
+
+    #[test]
+    fn test_sort_by_timestamp() {
+        let mut tracks = vec![
+            create_recent_track("Artist", "Track"),
+            create_recent_track_at("Artist", "Track", 300),
+            create_recent_track_at("Artist", "Track", 100),
+            create_recent_track_at("Artist", "Track", 200),
+        ];
+
+        sort_by_timestamp(&mut tracks);
+
+        let timestamps: Vec<Option<u32>> = tracks.iter().map(Timestamped::get_timestamp).collect();
+        assert_eq!(timestamps, vec![Some(100), Some(200), Some(300), None]);
+    }
+
+    fn hash_of<T: Hash>(value: &T) -> u64 {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_recent_track_equality_and_hash() {
+        let mut a = create_recent_track_at("Artist", "Track", 100);
+        let b = create_recent_track_at("Artist", "Track", 100);
+        a.url = "https://www.last.fm/music/Artist/_/Track".to_string();
+
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+        assert_ne!(a, create_recent_track_at("Artist", "Track", 101));
+
+        let set: std::collections::HashSet<RecentTrack> =
+            vec![a, b, create_recent_track_at("Artist", "Track", 101)]
+                .into_iter()
+                .collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    fn loved_track_json(date: Option<&str>) -> LovedTrack {
+        let date = date.map_or(String::new(), |date| format!(r#""date": {date},"#));
+        serde_json::from_str(&format!(
+            r##"{{
+                "artist": {{ "mbid": "", "url": "", "name": "Artist" }},
+                {date}
+                "image": [],
+                "streamable": {{ "fulltrack": "0", "#text": "0" }},
+                "name": "Track",
+                "mbid": "",
+                "url": ""
+            }}"##
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_loved_track_with_date() {
+        let track = loved_track_json(Some(
+            r##"{ "uts": "1733318400", "#text": "04 Dec 2024, 13:20" }"##,
+        ));
+
+        assert_eq!(track.date.as_ref().unwrap().text, "04 Dec 2024, 13:20");
+        assert_eq!(
+            track.loved_at(),
+            Some(Utc.with_ymd_and_hms(2024, 12, 4, 13, 20, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_loved_track_with_empty_date_text() {
+        let track = loved_track_json(Some(r##"{ "uts": "1733318400", "#text": "" }"##));
+
+        assert_eq!(track.date.as_ref().unwrap().text, "");
+        assert_eq!(track.get_timestamp(), Some(1_733_318_400));
+        assert_eq!(
+            track.loved_at(),
+            Some(Utc.with_ymd_and_hms(2024, 12, 4, 13, 20, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_loved_track_without_date() {
+        let track = loved_track_json(None);
+
+        assert!(track.date.is_none());
+        assert_eq!(track.get_timestamp(), None);
+        assert_eq!(track.loved_at(), None);
+    }
+
+    #[test]
+    fn test_canonical_url_prefers_provided_url() {
+        let mut track = create_recent_track_at("Artist", "Track", 1_733_318_400);
+        track.url = "https://www.last.fm/music/Artist/_/Given".to_string();
+
+        assert_eq!(
+            track.canonical_url(),
+            "https://www.last.fm/music/Artist/_/Given"
+        );
+    }
+
+    #[test]
+    fn test_canonical_url_built_when_missing() {
+        let mut track = create_recent_track_at("Artist", "Track", 1_733_318_400);
+        track.url = String::new();
+        track.artist.text = "AC/DC".to_string();
+        track.name = "Rock & Roll Ain't Noise Pollution".to_string();
+
+        assert_eq!(
+            track.canonical_url(),
+            "https://www.last.fm/music/AC%2FDC/_/Rock+%26+Roll+Ain%27t+Noise+Pollution"
+        );
+        assert_eq!(
+            track_url("Sigur Rós", "Hoppípolla"),
+            "https://www.last.fm/music/Sigur+R%C3%B3s/_/Hopp%C3%ADpolla"
+        );
+    }
+
+    #[test]
+    fn test_loved_track_equality_and_hash() {
+        let a = loved_track();
+        let mut b = loved_track();
+        b.date = Some(Date {
+            uts: 500,
+            text: String::new(),
+        });
+
+        assert_eq!(a, b);
+        assert_eq!(hash_of(&a), hash_of(&b));
+
+        let set: std::collections::HashSet<LovedTrack> = vec![a, b].into_iter().collect();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_deserialize_weekly_album_chart() {
+        let json = serde_json::json!({
+            "weeklyalbumchart": {
+                "album": [
+                    {
+                        "artist": { "mbid": "", "#text": "Artist" },
+                        "mbid": "",
+                        "url": "https://www.last.fm/music/Artist/Album",
+                        "name": "Album",
+                        "@attr": { "rank": "1" },
+                        "playcount": "12"
+                    }
+                ],
+                "@attr": { "from": "1733011200", "user": "test_user", "to": "1733616000" }
+            }
+        });
+
+        let response: UserWeeklyAlbumChart = serde_json::from_str(&json.to_string()).unwrap();
+        let chart = response.weeklyalbumchart;
+
+        assert_eq!(chart.attr.from, 1_733_011_200);
+        assert_eq!(chart.attr.to, 1_733_616_000);
+        assert_eq!(chart.album.len(), 1);
+        assert_eq!(chart.album[0].playcount, 12);
+        assert_eq!(chart.album[0].attr.rank, "1");
+        assert_eq!(chart.album[0].to_string(), "Artist - Album");
+    }
+
+    #[test]
+    fn test_deserialize_weekly_artist_chart() {
+        let json = serde_json::json!({
+            "weeklyartistchart": {
+                "artist": [
+                    {
+                        "mbid": "",
+                        "url": "https://www.last.fm/music/Artist",
+                        "name": "Artist",
+                        "@attr": { "rank": "1" },
+                        "playcount": "30"
+                    },
+                    {
+                        "mbid": "",
+                        "url": "https://www.last.fm/music/Other",
+                        "name": "Other",
+                        "@attr": { "rank": "2" },
+                        "playcount": "7"
+                    }
+                ],
+                "@attr": { "from": "1733011200", "user": "test_user", "to": "1733616000" }
+            }
+        });
+
+        let response: UserWeeklyArtistChart = serde_json::from_str(&json.to_string()).unwrap();
+        let chart = response.weeklyartistchart;
+
+        assert_eq!(chart.attr.user, "test_user");
+        assert_eq!(chart.artist.len(), 2);
+        assert_eq!(chart.artist[1].name, "Other");
+        assert_eq!(chart.artist[1].playcount, 7);
+    }
+
+    #[test]
+    fn test_deserialize_empty_weekly_chart() {
+        let json = serde_json::json!({
+            "weeklyartistchart": {
+                "artist": [],
+                "@attr": { "from": "1733011200", "user": "test_user", "to": "1733616000" }
+            }
+        });
+
+        let response: UserWeeklyArtistChart = serde_json::from_str(&json.to_string()).unwrap();
+
+        assert!(response.weeklyartistchart.artist.is_empty());
+    }
+}
@@ -1,8 +1,16 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::fmt;
 
-pub type QueryParams = HashMap<String, String>;
+/// Query parameters, ordered by key so the same request always builds the same URL.
+pub type QueryParams = BTreeMap<String, String>;
 
-#[derive(Debug, Clone)]
+/// Query parameters whose values are secrets.
+pub const SENSITIVE_PARAMS: &[&str] = &["api_key", "sk"];
+
+/// Placeholder replacing the value of sensitive parameters.
+pub const REDACTED: &str = "REDACTED";
+
+#[derive(Clone)]
 pub struct Url {
     base: String,
     query_params: QueryParams,
@@ -13,10 +21,20 @@ impl Url {
     pub fn new(base: &str) -> Self {
         Url {
             base: base.to_string(),
-            query_params: HashMap::new(),
+            query_params: QueryParams::new(),
         }
     }
 
+    #[must_use]
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    #[must_use]
+    pub fn query_params(&self) -> &QueryParams {
+        &self.query_params
+    }
+
     #[must_use]
     pub fn add_args(mut self, args: QueryParams) -> Self {
         self.query_params.extend(args);
@@ -24,19 +42,127 @@ impl Url {
         self
     }
 
+    /// Build the full URL, with parameters sorted by key and form-encoded.
     #[must_use]
     pub fn build(&self) -> String {
         if self.query_params.is_empty() {
             return self.base.clone();
         }
 
-        let query_string: Vec<String> = self
-            .query_params
-            .iter()
-            .map(|(k, v)| format!("{k}={v}"))
-            .collect();
+        let query_string = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&self.query_params)
+            .finish();
+
+        format!("{}?{}", self.base, query_string)
+    }
+
+    /// Build the full URL like `build`, with sensitive parameters masked.
+    ///
+    /// Use this whenever a URL is printed, logged or embedded in an error.
+    #[must_use]
+    pub fn build_redacted(&self) -> String {
+        Url {
+            base: self.base.clone(),
+            query_params: redact_params(&self.query_params),
+        }
+        .build()
+    }
+}
+
+impl fmt::Debug for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Url")
+            .field("base", &self.base)
+            .field("query_params", &redact_params(&self.query_params))
+            .finish()
+    }
+}
+
+/// Copy `params` with the values of sensitive parameters masked.
+pub(crate) fn redact_params(params: &QueryParams) -> QueryParams {
+    params
+        .iter()
+        .map(|(key, value)| {
+            let value = if SENSITIVE_PARAMS.contains(&key.as_str()) {
+                REDACTED
+            } else {
+                value
+            };
+            (key.clone(), value.to_string())
+        })
+        .collect()
+}
+
+/// Mask sensitive query parameters of an already-built URL in place.
+pub(crate) fn redact_url(url: &mut url::Url) {
+    if !url
+        .query_pairs()
+        .any(|(key, _)| SENSITIVE_PARAMS.contains(&key.as_ref()))
+    {
+        return;
+    }
+
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(key, value)| {
+            let value = if SENSITIVE_PARAMS.contains(&key.as_ref()) {
+                REDACTED.to_string()
+            } else {
+                value.into_owned()
+            };
+            (key.into_owned(), value)
+        })
+        .collect();
+
+    url.query_pairs_mut().clear().extend_pairs(pairs);
+}
+
+/// Chainable builder for `QueryParams`, avoiding hand-typed keys for common parameters.
+///
+/// Later calls override earlier ones for the same key.
+#[derive(Debug, Clone, Default)]
+pub struct ParamBuilder {
+    params: QueryParams,
+}
+
+impl ParamBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn method(self, method: &str) -> Self {
+        self.param("method", method)
+    }
+
+    #[must_use]
+    pub fn limit(self, limit: u32) -> Self {
+        self.param("limit", limit)
+    }
+
+    #[must_use]
+    pub fn page(self, page: u32) -> Self {
+        self.param("page", page)
+    }
+
+    #[must_use]
+    pub fn param(mut self, key: &str, value: impl ToString) -> Self {
+        self.params.insert(key.to_string(), value.to_string());
+
+        self
+    }
+
+    #[must_use]
+    pub fn extend(mut self, params: QueryParams) -> Self {
+        self.params.extend(params);
 
-        format!("{}?{}", self.base, query_string.join("&"))
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> QueryParams {
+        self.params
     }
 }
 
@@ -52,7 +178,7 @@ mod tests {
 
     #[test]
     fn test_single_query_param() {
-        let mut params = HashMap::new();
+        let mut params = QueryParams::new();
         params.insert("q".to_string(), "rust".to_string());
 
         let url = Url::new("https://www.google.com").add_args(params);
@@ -61,37 +187,131 @@ mod tests {
 
     #[test]
     fn test_multiple_query_params() {
-        let mut params = HashMap::new();
+        let mut params = QueryParams::new();
         params.insert("q".to_string(), "rust".to_string());
         params.insert("lang".to_string(), "en".to_string());
 
         let url = Url::new("https://www.google.com").add_args(params);
-        let built_url = url.build();
-        assert!(
-            built_url == "https://www.google.com?q=rust&lang=en"
-                || built_url == "https://www.google.com?lang=en&q=rust"
+        assert_eq!(url.build(), "https://www.google.com?lang=en&q=rust");
+    }
+
+    #[test]
+    fn test_params_sorted_and_encoded() {
+        let params = QueryParams::from([
+            ("track".to_string(), "Rock & Roll".to_string()),
+            ("artist".to_string(), "Sigur Rós".to_string()),
+        ]);
+
+        let url = Url::new("https://www.example.com").add_args(params);
+        assert_eq!(
+            url.build(),
+            "https://www.example.com?artist=Sigur+R%C3%B3s&track=Rock+%26+Roll"
+        );
+    }
+
+    #[test]
+    fn test_same_request_builds_identical_urls() {
+        let pairs = [
+            ("method", "user.getrecenttracks"),
+            ("user", "someone"),
+            ("api_key", "secret"),
+            ("limit", "200"),
+            ("format", "json"),
+            ("page", "3"),
+        ];
+        let build = |pairs: &mut dyn Iterator<Item = &(&str, &str)>| {
+            let mut params = ParamBuilder::new();
+            for (key, value) in pairs {
+                params = params.param(key, value);
+            }
+            Url::new("https://www.example.com").add_args(params.build())
+        };
+
+        let forward = build(&mut pairs.iter());
+        let backward = build(&mut pairs.iter().rev());
+
+        assert_eq!(forward.build(), backward.build());
+        assert_eq!(
+            forward.build(),
+            "https://www.example.com?api_key=secret&format=json&limit=200\
+             &method=user.getrecenttracks&page=3&user=someone"
+        );
+        let debug = format!("{forward:?}");
+        assert!(!debug.contains("secret"));
+        assert!(debug.contains(REDACTED));
+    }
+
+    #[test]
+    fn test_build_redacted_masks_secrets() {
+        let params = QueryParams::from([
+            ("api_key".to_string(), "secret_key".to_string()),
+            ("sk".to_string(), "session_key".to_string()),
+            ("method".to_string(), "user.getinfo".to_string()),
+            ("user".to_string(), "someone".to_string()),
+        ]);
+
+        let url = Url::new("https://www.example.com").add_args(params);
+        let redacted = url.build_redacted();
+
+        assert_eq!(
+            redacted,
+            "https://www.example.com?api_key=REDACTED&method=user.getinfo&sk=REDACTED&user=someone"
+        );
+        assert!(url.build().contains("api_key=secret_key"));
+    }
+
+    #[test]
+    fn test_redact_url() {
+        let mut url =
+            url::Url::parse("https://www.example.com/?api_key=secret_key&user=some+one").unwrap();
+        redact_url(&mut url);
+
+        assert_eq!(
+            url.as_str(),
+            "https://www.example.com/?api_key=REDACTED&user=some+one"
         );
     }
 
+    #[test]
+    fn test_reserved_characters_round_trip() {
+        let params = QueryParams::from([
+            ("artist".to_string(), "Simon & Garfunkel".to_string()),
+            ("track".to_string(), "a=b?c/d#e".to_string()),
+            ("album".to_string(), "Ágætis byrjun".to_string()),
+            ("odd key&".to_string(), "AC/DC".to_string()),
+            ("from".to_string(), "1700000000".to_string()),
+            ("api_key".to_string(), "0123abcdef".to_string()),
+        ]);
+
+        let built = Url::new("https://www.example.com/")
+            .add_args(params.clone())
+            .build();
+        assert!(built.contains("from=1700000000"));
+        assert!(built.contains("api_key=0123abcdef"));
+        assert!(built.contains("artist=Simon+%26+Garfunkel"));
+        assert!(built.contains("track=a%3Db%3Fc%2Fd%23e"));
+        assert!(!built.contains(' '));
+
+        let parsed = url::Url::parse(&built).unwrap();
+        let decoded: QueryParams = parsed.query_pairs().into_owned().collect();
+        assert_eq!(decoded, params);
+    }
+
     #[test]
     fn test_chained_param_addition() {
         let url = Url::new("https://www.example.com")
-            .add_args(HashMap::from([("page".to_string(), "1".to_string())]))
-            .add_args(HashMap::from([("limit".to_string(), "10".to_string())]));
+            .add_args(QueryParams::from([("page".to_string(), "1".to_string())]))
+            .add_args(QueryParams::from([("limit".to_string(), "10".to_string())]));
 
-        let built_url = url.build();
-        assert!(
-            built_url == "https://www.example.com?page=1&limit=10"
-                || built_url == "https://www.example.com?limit=10&page=1"
-        );
+        assert_eq!(url.build(), "https://www.example.com?limit=10&page=1");
     }
 
     #[test]
     fn test_param_overwrite() {
-        let mut params1 = HashMap::new();
+        let mut params1 = QueryParams::new();
         params1.insert("key".to_string(), "value1".to_string());
 
-        let mut params2 = HashMap::new();
+        let mut params2 = QueryParams::new();
         params2.insert("key".to_string(), "value2".to_string());
 
         let url = Url::new("https://www.example.com")
@@ -100,4 +320,39 @@ mod tests {
 
         assert_eq!(url.build(), "https://www.example.com?key=value2");
     }
+
+    #[test]
+    fn test_param_builder() {
+        let params = ParamBuilder::new()
+            .method("user.getrecenttracks")
+            .limit(50)
+            .page(3)
+            .param("from", 1_700_000_000)
+            .build();
+
+        assert_eq!(
+            params,
+            QueryParams::from([
+                ("method".to_string(), "user.getrecenttracks".to_string()),
+                ("limit".to_string(), "50".to_string()),
+                ("page".to_string(), "3".to_string()),
+                ("from".to_string(), "1700000000".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_param_builder_later_values_win() {
+        let base = QueryParams::from([
+            ("limit".to_string(), "1000".to_string()),
+            ("user".to_string(), "someone".to_string()),
+        ]);
+
+        let params = ParamBuilder::new().extend(base.clone()).limit(1).build();
+        assert_eq!(params["limit"], "1");
+        assert_eq!(params["user"], "someone");
+
+        let params = ParamBuilder::new().limit(1).extend(base).build();
+        assert_eq!(params["limit"], "1000");
+    }
 }
@@ -0,0 +1,19 @@
+use async_lastfm::types::RecentTrack;
+use async_lastfm::AnalysisHandler;
+use std::path::Path;
+
+#[test]
+fn test_analyze_fixture_without_env() {
+    std::env::remove_var("LAST_FM_API_KEY");
+
+    let fixture = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/recent_tracks.json");
+
+    let stats = AnalysisHandler::analyze_file::<RecentTrack>(&fixture, 2).unwrap();
+
+    assert_eq!(stats.total_tracks, 3);
+    assert_eq!(stats.most_played_artist, Some(("Artist1".to_string(), 2)));
+    assert_eq!(
+        AnalysisHandler::get_most_recent_timestamp::<RecentTrack>(&fixture).unwrap(),
+        Some(1_700_000_200)
+    );
+}